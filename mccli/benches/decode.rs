@@ -0,0 +1,76 @@
+//! Compares decoding payloads with the async codec to decoding them in place.
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use mccli::{
+    packet::Packet,
+    types::{ByteArray, McType, String, VarInt},
+};
+use std::hint::black_box;
+
+/// Shaped like a chat message.
+#[derive(Debug, PartialEq, McType)]
+struct Chat<'s> {
+    sender: String<'s>,
+    #[mc(length_prefixed)]
+    lines: Vec<String<'s>>,
+    #[mc(optional)]
+    signature: Option<ByteArray<'s>>,
+}
+
+/// Shaped like a chunk.
+#[derive(Debug, PartialEq, McType)]
+struct Chunk<'s> {
+    x: i32,
+    z: i32,
+    data: ByteArray<'s>,
+    #[mc(length_prefixed)]
+    block_entities: Vec<VarInt>,
+}
+
+fn payloads(rt: &tokio::runtime::Runtime) -> (Packet<'static>, Packet<'static>) {
+    let chat = Chat {
+        sender: String::borrowed("Notch"),
+        lines: vec![String::borrowed("hello there, this is a chat message"); 8],
+        signature: Some(ByteArray::from(vec![7; 256])),
+    };
+    let chunk = Chunk {
+        x: -3,
+        z: 12,
+        data: ByteArray::from(vec![0x42; 16 * 1024]),
+        block_entities: (0..64).map(VarInt::from).collect(),
+    };
+    rt.block_on(async {
+        (
+            Packet::new(0x00.into(), &chat).await.unwrap(),
+            Packet::new(0x01.into(), &chunk).await.unwrap(),
+        )
+    })
+}
+
+fn decode(c: &mut Criterion) {
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    let (chat, chunk) = payloads(&rt);
+
+    let mut group = c.benchmark_group("chat");
+    group.bench_function("async", |b| {
+        b.iter(|| rt.block_on(black_box(&chat).decode_as::<Chat>()).unwrap())
+    });
+    group.bench_function("borrowed", |b| {
+        b.iter(|| black_box(&chat).decode_borrowed::<Chat>().unwrap())
+    });
+    group.finish();
+
+    let mut group = c.benchmark_group("chunk");
+    group.bench_function("async", |b| {
+        b.iter(|| rt.block_on(black_box(&chunk).decode_as::<Chunk>()).unwrap())
+    });
+    group.bench_function("borrowed", |b| {
+        b.iter(|| black_box(&chunk).decode_borrowed::<Chunk>().unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, decode);
+criterion_main!(benches);
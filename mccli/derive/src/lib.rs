@@ -0,0 +1,505 @@
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{format_ident, quote};
+use syn::{
+    Data, DeriveInput, Expr, ExprLit, Field, Fields, GenericArgument, GenericParam, Ident,
+    Lifetime, LifetimeParam, Lit, LitInt, PathArguments, Type, parse_macro_input, spanned::Spanned,
+};
+
+/// How a field is encoded, picked with the `#[mc(...)]` attribute.
+enum Encoding<'f> {
+    /// The field's own `McType` implementation.
+    Plain,
+    /// An `Option<T>`, prefixed by a boolean saying whether it is present.
+    Optional(&'f Type),
+    /// A `Vec<T>`, prefixed by its length as a `VarInt`.
+    LengthPrefixed(&'f Type),
+    /// A `Vec<u8>` holding everything until the end of the input.
+    Rest,
+}
+
+fn inner_type<'t>(ty: &'t Type, wrapper: &str) -> syn::Result<&'t Type> {
+    if let Type::Path(path) = ty
+        && let Some(segment) = path.path.segments.last()
+        && segment.ident == wrapper
+        && let PathArguments::AngleBracketed(args) = &segment.arguments
+        && let Some(GenericArgument::Type(inner)) = args.args.first()
+    {
+        return Ok(inner);
+    }
+    Err(syn::Error::new(
+        ty.span(),
+        format!("expected a {wrapper}<T> field"),
+    ))
+}
+
+fn is_u8(ty: &Type) -> bool {
+    matches!(ty, Type::Path(path) if path.path.is_ident("u8"))
+}
+
+/// The `#[mc(...)]` attributes of a field.
+struct FieldAttrs<'f> {
+    encoding: Encoding<'f>,
+    /// The most characters a string field may have, from `max_len = N`.
+    max_len: Option<usize>,
+    /// Whether the field holds data that can't borrow from the input, from `owned`.
+    owned: bool,
+}
+
+impl<'f> FieldAttrs<'f> {
+    fn parse(field: &'f Field) -> syn::Result<Self> {
+        let mut attrs = Self {
+            encoding: Encoding::Plain,
+            max_len: None,
+            owned: false,
+        };
+        for attr in field.attrs.iter().filter(|a| a.path().is_ident("mc")) {
+            attr.parse_nested_meta(|meta| {
+                let encoding = if meta.path.is_ident("max_len") {
+                    if attrs.max_len.is_some() {
+                        return Err(meta.error("`max_len` can only be specified once"));
+                    }
+                    attrs.max_len = Some(meta.value()?.parse::<LitInt>()?.base10_parse()?);
+                    return Ok(());
+                } else if meta.path.is_ident("owned") {
+                    attrs.owned = true;
+                    return Ok(());
+                } else if meta.path.is_ident("optional") {
+                    Encoding::Optional(inner_type(&field.ty, "Option")?)
+                } else if meta.path.is_ident("length_prefixed") {
+                    Encoding::LengthPrefixed(inner_type(&field.ty, "Vec")?)
+                } else if meta.path.is_ident("rest") {
+                    if !is_u8(inner_type(&field.ty, "Vec")?) {
+                        return Err(meta.error("`rest` fields must be a Vec<u8>"));
+                    }
+                    Encoding::Rest
+                } else {
+                    return Err(meta.error(
+                        "expected `optional`, `length_prefixed`, `rest`, `max_len` or `owned`",
+                    ));
+                };
+                if !matches!(attrs.encoding, Encoding::Plain) {
+                    return Err(meta.error("only one encoding can be specified per field"));
+                }
+                attrs.encoding = encoding;
+                Ok(())
+            })?;
+        }
+        Ok(attrs)
+    }
+}
+
+fn read_field(field: &Field, attrs: &FieldAttrs) -> TokenStream2 {
+    let ty = &field.ty;
+    match attrs.encoding {
+        Encoding::Plain => quote! {
+            <#ty as ::mccli::types::McType>::read(&mut r).await?
+        },
+        Encoding::Optional(inner) => quote! {
+            if <bool as ::mccli::types::McType>::read(&mut r).await? {
+                Some(<#inner as ::mccli::types::McType>::read(&mut r).await?)
+            } else {
+                None
+            }
+        },
+        Encoding::LengthPrefixed(inner) => {
+            let read_len = quote! {
+                <::mccli::types::VarInt as ::mccli::types::McType>::read(&mut r).await?.length()?
+            };
+            if is_u8(inner) {
+                quote! {
+                    <::mccli::types::ByteArray as ::mccli::types::McType>::read(&mut r)
+                        .await?
+                        .into_vec()
+                }
+            } else {
+                quote! {{
+                    let len = #read_len;
+                    let mut elements = Vec::with_capacity(len.min(1024));
+                    for _ in 0..len {
+                        elements.push(<#inner as ::mccli::types::McType>::read(&mut r).await?);
+                    }
+                    elements
+                }}
+            }
+        }
+        Encoding::Rest => quote! {{
+            let mut buffer = Vec::new();
+            ::tokio::io::AsyncReadExt::read_to_end(&mut r, &mut buffer).await?;
+            buffer
+        }},
+    }
+}
+
+/// Like [`read_field`] but with `Decode<#lifetime>`, out of `buf: &mut &#lifetime [u8]`.
+fn decode_field(field: &Field, attrs: &FieldAttrs, lifetime: &Lifetime) -> TokenStream2 {
+    let ty = &field.ty;
+    let decode = |ty: &Type| quote!(<#ty as ::mccli::types::Decode<#lifetime>>::decode(buf)?);
+    // owned values can't borrow from `buf`, so they're read with the blocking codec, which
+    // advances `buf` as it reads
+    let value = |ty: &Type| {
+        if attrs.owned {
+            quote!(<#ty as ::mccli::types::McType>::read_blocking(&mut *buf)?)
+        } else {
+            decode(ty)
+        }
+    };
+    match attrs.encoding {
+        Encoding::Plain => value(ty),
+        Encoding::Optional(inner) => {
+            let bool = decode(&syn::parse_quote!(bool));
+            let inner = value(inner);
+            quote! {
+                if #bool {
+                    Some(#inner)
+                } else {
+                    None
+                }
+            }
+        }
+        Encoding::LengthPrefixed(inner) if is_u8(inner) => {
+            let bytes = decode(&syn::parse_quote!(::mccli::types::ByteArray<#lifetime>));
+            quote!(#bytes.into_vec())
+        }
+        Encoding::LengthPrefixed(inner) => {
+            let len = decode(&syn::parse_quote!(::mccli::types::VarInt));
+            let inner = value(inner);
+            quote! {{
+                let len = #len.length()?;
+                let mut elements = Vec::with_capacity(len.min(1024));
+                for _ in 0..len {
+                    elements.push(#inner);
+                }
+                elements
+            }}
+        }
+        Encoding::Rest => quote!(::std::mem::take(buf).to_vec()),
+    }
+}
+
+/// `value` is an expression of type `&T` where `T` is the field's type.
+fn write_field(field: &Field, value: TokenStream2) -> syn::Result<TokenStream2> {
+    Ok(match FieldAttrs::parse(field)?.encoding {
+        Encoding::Plain => quote! {
+            ::mccli::types::McType::write(#value, &mut w).await?;
+        },
+        Encoding::Optional(_) => quote! {
+            match #value {
+                Some(value) => {
+                    ::mccli::types::McType::write(&true, &mut w).await?;
+                    ::mccli::types::McType::write(value, &mut w).await?;
+                }
+                None => ::mccli::types::McType::write(&false, &mut w).await?,
+            }
+        },
+        Encoding::LengthPrefixed(inner) => {
+            let write_len = quote! {
+                ::mccli::types::McType::write(
+                    &::mccli::types::VarInt::try_from(#value.len()).map_err(::std::io::Error::other)?,
+                    &mut w,
+                )
+                .await?;
+            };
+            if is_u8(inner) {
+                quote! {
+                    #write_len
+                    ::tokio::io::AsyncWriteExt::write_all(&mut w, #value).await?;
+                }
+            } else {
+                quote! {
+                    #write_len
+                    for element in #value {
+                        ::mccli::types::McType::write(element, &mut w).await?;
+                    }
+                }
+            }
+        }
+        Encoding::Rest => quote! {
+            ::tokio::io::AsyncWriteExt::write_all(&mut w, #value).await?;
+        },
+    })
+}
+
+/// How [`read_fields`] reads each field.
+enum Reader<'l> {
+    /// [`read_field`], from `r`.
+    Async,
+    /// [`decode_field`], out of `buf`.
+    Decode(&'l Lifetime),
+}
+
+impl Reader<'_> {
+    /// Reads `field` of `ty`, tagging errors with where they happened.
+    fn read(&self, ty: &str, field: &Field, name: &str) -> syn::Result<TokenStream2> {
+        let attrs = FieldAttrs::parse(field)?;
+        let read = match self {
+            Self::Async => read_field(field, &attrs),
+            Self::Decode(lifetime) => decode_field(field, &attrs, lifetime),
+        };
+        let read = match (attrs.max_len, attrs.encoding) {
+            (None, _) => quote!(Ok(#read)),
+            (Some(max), Encoding::Plain) => quote!((#read).at_most(#max)),
+            (Some(max), Encoding::Optional(_)) => {
+                quote!((#read).map(|v| v.at_most(#max)).transpose())
+            }
+            (Some(_), _) => {
+                return Err(syn::Error::new(
+                    field.span(),
+                    "`max_len` only applies to strings and optional strings",
+                ));
+            }
+        };
+        Ok(match self {
+            Self::Async => quote! {
+                ::mccli::error::DecodeError::field_async(#ty, #name, async { #read }).await?
+            },
+            Self::Decode(_) => quote!(::mccli::error::DecodeError::field(#ty, #name, || #read)?),
+        })
+    }
+}
+
+/// Generates the expression that reads `fields` into `constructor`, which is called `ty` in
+/// errors.
+fn read_fields(
+    constructor: TokenStream2,
+    ty: &str,
+    fields: &Fields,
+    reader: &Reader,
+) -> syn::Result<TokenStream2> {
+    Ok(match fields {
+        Fields::Named(named) => {
+            let fields = named
+                .named
+                .iter()
+                .map(|f| {
+                    let name = f.ident.as_ref().expect("named fields have names");
+                    let read = reader.read(ty, f, &name.to_string())?;
+                    Ok(quote!(#name: #read))
+                })
+                .collect::<syn::Result<Vec<_>>>()?;
+            quote!(#constructor { #(#fields,)* })
+        }
+        Fields::Unnamed(unnamed) => {
+            let fields = unnamed
+                .unnamed
+                .iter()
+                .enumerate()
+                .map(|(i, f)| reader.read(ty, f, &i.to_string()))
+                .collect::<syn::Result<Vec<_>>>()?;
+            quote!(#constructor(#(#fields,)*))
+        }
+        Fields::Unit => constructor,
+    })
+}
+
+/// Generates a pattern that binds every field of `fields` and the statements that write them.
+fn write_fields(
+    constructor: TokenStream2,
+    fields: &Fields,
+) -> syn::Result<(TokenStream2, TokenStream2)> {
+    let bindings = (0..fields.len())
+        .map(|i| format_ident!("field_{i}"))
+        .collect::<Vec<_>>();
+    let writes = fields
+        .iter()
+        .zip(&bindings)
+        .map(|(f, binding)| write_field(f, quote!(#binding)))
+        .collect::<syn::Result<Vec<_>>>()?;
+    let pattern = match fields {
+        Fields::Named(named) => {
+            let names = named.named.iter().map(|f| &f.ident);
+            quote!(#constructor { #(#names: #bindings,)* })
+        }
+        Fields::Unnamed(_) => quote!(#constructor(#(#bindings,)*)),
+        Fields::Unit => constructor,
+    };
+    Ok((pattern, quote!(#(#writes)*)))
+}
+
+/// The id of a variant, from `#[mc(id = N)]`, its discriminant or the previous id plus one.
+fn variant_id(variant: &syn::Variant, previous: Option<i32>) -> syn::Result<i32> {
+    let mut id = None;
+    for attr in variant.attrs.iter().filter(|a| a.path().is_ident("mc")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("id") {
+                id = Some(meta.value()?.parse::<LitInt>()?.base10_parse()?);
+                Ok(())
+            } else {
+                Err(meta.error("expected `id`"))
+            }
+        })?;
+    }
+    if let Some(id) = id {
+        return Ok(id);
+    }
+    match &variant.discriminant {
+        Some((
+            _,
+            Expr::Lit(ExprLit {
+                lit: Lit::Int(lit), ..
+            }),
+        )) => lit.base10_parse(),
+        Some((_, expr)) => Err(syn::Error::new(
+            expr.span(),
+            "only integer literal discriminants are supported",
+        )),
+        None => Ok(previous.map_or(0, |p| p + 1)),
+    }
+}
+
+fn derive_mc_type_impl(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    // types that borrow decode from buffers that live as long as their first lifetime, others
+    // from any buffer
+    let mut decode_generics = input.generics.clone();
+    let lifetime = match input.generics.lifetimes().next() {
+        Some(param) => param.lifetime.clone(),
+        None => {
+            let lifetime = Lifetime::new("'mc", Span::call_site());
+            decode_generics.params.insert(
+                0,
+                GenericParam::Lifetime(LifetimeParam::new(lifetime.clone())),
+            );
+            lifetime
+        }
+    };
+    let (decode_impl_generics, _, _) = decode_generics.split_for_impl();
+    let decoder = Reader::Decode(&lifetime);
+    let (read, decode, write) = match &input.data {
+        Data::Struct(data) => {
+            let ty = name.to_string();
+            let read = read_fields(quote!(Self), &ty, &data.fields, &Reader::Async)?;
+            let decode = read_fields(quote!(Self), &ty, &data.fields, &decoder)?;
+            let (pattern, writes) = write_fields(quote!(Self), &data.fields)?;
+            (
+                quote!(Ok(#read)),
+                quote!(Ok(#decode)),
+                quote! {
+                    let #pattern = self;
+                    #writes
+                },
+            )
+        }
+        Data::Enum(data) => {
+            let mut previous = None;
+            let mut read_arms = Vec::new();
+            let mut decode_arms = Vec::new();
+            let mut write_arms = Vec::new();
+            for variant in &data.variants {
+                let id = variant_id(variant, previous)?;
+                previous = Some(id);
+                let ident: &Ident = &variant.ident;
+                let ty = format!("{name}::{ident}");
+                let constructor = quote!(Self::#ident);
+                let read = read_fields(constructor.clone(), &ty, &variant.fields, &Reader::Async)?;
+                let decode = read_fields(constructor, &ty, &variant.fields, &decoder)?;
+                let (pattern, writes) = write_fields(quote!(Self::#ident), &variant.fields)?;
+                read_arms.push(quote!(#id => Ok(#read),));
+                decode_arms.push(quote!(#id => Ok(#decode),));
+                write_arms.push(quote! {
+                    #pattern => {
+                        ::mccli::types::McType::write(
+                            &::mccli::types::VarInt::from(#id),
+                            &mut w,
+                        )
+                        .await?;
+                        #writes
+                    }
+                });
+            }
+            let name = name.to_string();
+            let invalid_id = quote! {
+                id => Err(::std::io::Error::new(
+                    ::std::io::ErrorKind::InvalidData,
+                    format!("invalid {} id: {id}", #name),
+                )),
+            };
+            (
+                quote! {
+                    match i32::from(
+                        <::mccli::types::VarInt as ::mccli::types::McType>::read(&mut r).await?,
+                    ) {
+                        #(#read_arms)*
+                        #invalid_id
+                    }
+                },
+                quote! {
+                    match i32::from(
+                        <::mccli::types::VarInt as ::mccli::types::Decode>::decode(buf)?,
+                    ) {
+                        #(#decode_arms)*
+                        #invalid_id
+                    }
+                },
+                quote! {
+                    match self {
+                        #(#write_arms)*
+                    }
+                },
+            )
+        }
+        Data::Union(_) => {
+            return Err(syn::Error::new(
+                input.span(),
+                "McType can't be derived for unions",
+            ));
+        }
+    };
+
+    Ok(quote! {
+        impl #impl_generics ::mccli::types::McType for #name #ty_generics #where_clause {
+            #[allow(unused_mut, unused_variables, clippy::needless_question_mark)]
+            async fn read<R: ::tokio::io::AsyncRead + Unpin + Send>(
+                mut r: R,
+            ) -> ::std::io::Result<Self> {
+                #read
+            }
+
+            #[allow(unused_mut, unused_variables)]
+            async fn write<W: ::tokio::io::AsyncWrite + Unpin + Send>(
+                &self,
+                mut w: W,
+            ) -> ::std::io::Result<()> {
+                #write
+                Ok(())
+            }
+        }
+
+        impl #decode_impl_generics ::mccli::types::Decode<#lifetime> for #name #ty_generics
+            #where_clause
+        {
+            #[allow(unused_variables, clippy::needless_question_mark)]
+            fn decode(buf: &mut &#lifetime [u8]) -> ::std::io::Result<Self> {
+                #decode
+            }
+        }
+    })
+}
+
+/// Derives `McType` for structs whose fields are all `McType`, in declaration order, and for
+/// enums, which are prefixed by their id as a `VarInt`. `Decode` is derived along with it,
+/// borrowing for as long as the type's first lifetime. Errors reading a field become a
+/// `mccli::error::DecodeError` naming the field.
+///
+/// Field attributes:
+/// - `#[mc(optional)]` on an `Option<T>`: a boolean followed by the value if it's `true`.
+/// - `#[mc(length_prefixed)]` on a `Vec<T>`: a `VarInt` length followed by the elements.
+/// - `#[mc(rest)]` on a `Vec<u8>`: the remaining bytes of the input.
+/// - `#[mc(max_len = N)]` on a string or optional string: the most characters it may have, on
+///   top of the reader's limits.
+/// - `#[mc(owned)]` on a field that doesn't borrow from the input, like a `String<'static>`, or
+///   its elements for `optional` and `length_prefixed`: `Decode` reads it with the blocking
+///   codec instead of borrowing.
+///
+/// Variant attributes:
+/// - `#[mc(id = N)]`: the id of the variant, by default its discriminant or the previous
+///   variant's id plus one.
+#[proc_macro_derive(McType, attributes(mc))]
+pub fn derive_mc_type(input: TokenStream) -> TokenStream {
+    match derive_mc_type_impl(parse_macro_input!(input as DeriveInput)) {
+        Ok(tt) => tt.into(),
+        Err(e) => e.into_compile_error().into(),
+    }
+}
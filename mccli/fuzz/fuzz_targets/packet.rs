@@ -0,0 +1,40 @@
+//! Reads a frame, compressed or not, and decodes it in every state and direction.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use mccli::packet::{
+    Packet,
+    registry::{Direction, State},
+};
+
+thread_local! {
+    static RUNTIME: tokio::runtime::Runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+}
+
+fuzz_target!(|data: &[u8]| {
+    let Some((&compressed, frame)) = data.split_first() else {
+        return;
+    };
+    RUNTIME.with(|runtime| {
+        runtime.block_on(async {
+            let threshold = (compressed & 1 == 1).then_some(256);
+            let Ok(packet) = Packet::read_compressed(frame, threshold).await else {
+                return;
+            };
+            for state in [
+                State::Handshaking,
+                State::Status,
+                State::Login,
+                State::Configuration,
+                State::Play,
+            ] {
+                for direction in [Direction::Clientbound, Direction::Serverbound] {
+                    let _ = packet.clone().decode(state, direction).await;
+                }
+            }
+        })
+    });
+});
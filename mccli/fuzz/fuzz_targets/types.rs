@@ -0,0 +1,58 @@
+//! Reads every data type with both codecs, which have to agree.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use mccli::types::{
+    Angle, BitSet, ByteArray, Decode, FixedBitSet, Identifier, McType, Position, Slot, String,
+    Uuid, VarInt, VarLong, nbt::Nbt, text::TextComponent,
+};
+
+/// Decodes `data` as a `T` with [`McType::read_blocking`] and [`Decode::decode`], checking they
+/// succeed on the same inputs and produce values that encode the same.
+fn check<'a, T: McType + Decode<'a>>(data: &'a [u8]) {
+    let read = T::read_blocking(data);
+    let decoded = T::decode(&mut &data[..]);
+    match (read, decoded) {
+        (Ok(read), Ok(decoded)) => {
+            let (mut a, mut b) = (Vec::new(), Vec::new());
+            read.write_blocking(&mut a).unwrap();
+            decoded.write_blocking(&mut b).unwrap();
+            assert_eq!(a, b);
+        }
+        (Err(_), Err(_)) => {}
+        (read, decoded) => panic!(
+            "the codecs disagree: read {:?}, decode {:?}",
+            read.err(),
+            decoded.err()
+        ),
+    }
+}
+
+fuzz_target!(|data: &[u8]| {
+    let Some((&ty, data)) = data.split_first() else {
+        return;
+    };
+    match ty % 20 {
+        0 => check::<VarInt>(data),
+        1 => check::<VarLong>(data),
+        2 => check::<String>(data),
+        3 => check::<ByteArray>(data),
+        4 => check::<Identifier>(data),
+        5 => check::<Position>(data),
+        6 => check::<Angle>(data),
+        7 => check::<BitSet>(data),
+        8 => check::<FixedBitSet<20>>(data),
+        9 => check::<Uuid>(data),
+        10 => check::<Slot>(data),
+        11 => check::<Nbt>(data),
+        12 => check::<TextComponent>(data),
+        13 => check::<Option<String>>(data),
+        14 => check::<Vec<VarInt>>(data),
+        15 => check::<[i32; 4]>(data),
+        16 => check::<bool>(data),
+        17 => check::<f64>(data),
+        18 => check::<i64>(data),
+        _ => check::<u16>(data),
+    }
+});
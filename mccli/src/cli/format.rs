@@ -0,0 +1,293 @@
+use clap::ValueEnum;
+use mccli::{ServerInfo, types::server::Status};
+use serde::Serialize;
+use std::fmt::Write as _;
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    /// A human readable layout
+    #[default]
+    Text,
+    /// One json object per server
+    Json,
+    /// One yaml document per server
+    Yaml,
+    /// An aligned table with one row per server
+    Table,
+    /// The prometheus text exposition format
+    Prometheus,
+}
+
+/// What is printed for each server.
+#[derive(Serialize)]
+pub struct Record<'r> {
+    pub target: &'r str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<&'r Status>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Whether the server was unreachable or answered with junk, see [`mccli::error::Category`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_category: Option<&'static str>,
+}
+
+impl<'r> Record<'r> {
+    pub fn new(target: &'r str, result: &'r mccli::Result<ServerInfo>) -> Self {
+        match result {
+            Ok(info) => Self {
+                target,
+                latency_ms: Some(info.latency.as_secs_f64() * 1000.),
+                status: Some(&info.status),
+                error: None,
+                error_category: None,
+            },
+            Err(error) => Self {
+                target,
+                latency_ms: None,
+                status: None,
+                error: Some(error.with_sources().to_string()),
+                error_category: Some(error.category().as_str()),
+            },
+        }
+    }
+}
+
+/// Renders `records` as a whole. Formats that aren't aggregated can also render each record on
+/// its own, as soon as it's available.
+pub fn render(format: Format, records: &[Record]) -> String {
+    match format {
+        Format::Text => records.iter().map(text).collect(),
+        Format::Json => records.iter().map(|r| json(r) + "\n").collect(),
+        Format::Yaml => records.iter().map(yaml).collect(),
+        Format::Table => table(records),
+        Format::Prometheus => prometheus(records),
+    }
+}
+
+/// Whether [`render`] needs every record at once.
+pub fn is_aggregated(format: Format) -> bool {
+    matches!(format, Format::Table | Format::Prometheus)
+}
+
+/// A tab separated line.
+pub fn text(record: &Record) -> String {
+    match (record.status, &record.error) {
+        (Some(status), _) => format!(
+            "{}\tonline\t{:.0}ms\t{}/{}\t{}\n",
+            record.target,
+            record.latency_ms.unwrap_or_default(),
+            status.players.online,
+            status.players.max,
+            status.version.name,
+        ),
+        (None, error) => format!(
+            "{}\toffline\t{}\n",
+            record.target,
+            error.as_deref().unwrap_or_default()
+        ),
+    }
+}
+
+/// A single line of json.
+pub fn json(record: &Record) -> String {
+    serde_json::to_string(record).expect("records are always serializable")
+}
+
+/// A yaml document, starting with its `---` separator.
+pub fn yaml(record: &Record) -> String {
+    let document = serde_yaml::to_string(record).expect("records are always serializable");
+    format!("---\n{document}")
+}
+
+pub fn table(records: &[Record]) -> String {
+    let header = [
+        "SERVER",
+        "STATUS",
+        "LATENCY",
+        "PLAYERS",
+        "VERSION",
+        "DESCRIPTION",
+    ];
+    let rows = records
+        .iter()
+        .map(|r| match (r.status, &r.error) {
+            (Some(status), _) => [
+                r.target.to_owned(),
+                "online".to_owned(),
+                format!("{:.0}ms", r.latency_ms.unwrap_or_default()),
+                format!("{}/{}", status.players.online, status.players.max),
+                status.version.name.clone(),
+                status
+                    .description
+                    .plain_text()
+                    .lines()
+                    .collect::<Vec<_>>()
+                    .join(" "),
+            ],
+            (None, error) => [
+                r.target.to_owned(),
+                "offline".to_owned(),
+                String::new(),
+                String::new(),
+                String::new(),
+                error.clone().unwrap_or_default(),
+            ],
+        })
+        .collect::<Vec<_>>();
+
+    let mut widths = header.map(str::len);
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    let mut out = String::new();
+    for row in std::iter::once(header.map(str::to_owned)).chain(rows) {
+        let mut line = String::new();
+        for (cell, width) in row.iter().zip(widths) {
+            let _ = write!(line, "{cell:width$}  ");
+        }
+        out.push_str(line.trim_end());
+        out.push('\n');
+    }
+    out
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', r"\\")
+        .replace('"', r#"\""#)
+        .replace('\n', r"\n")
+}
+
+pub fn prometheus(records: &[Record]) -> String {
+    type Metric = fn(&Record, &Status) -> Option<(String, f64)>;
+    let metrics: [(&str, &str, Metric); 4] = [
+        (
+            "minecraft_players_online",
+            "Players currently online.",
+            |_, s| Some((String::new(), s.players.online as f64)),
+        ),
+        (
+            "minecraft_players_max",
+            "Maximum number of players.",
+            |_, s| Some((String::new(), s.players.max as f64)),
+        ),
+        (
+            "minecraft_latency_seconds",
+            "Round trip time of the status ping.",
+            |r, _| Some((String::new(), r.latency_ms? / 1000.)),
+        ),
+        (
+            "minecraft_protocol_version",
+            "Protocol version reported by the server.",
+            |_, s| {
+                Some((
+                    format!(",version=\"{}\"", escape_label(&s.version.name)),
+                    f64::from(s.version.protocol),
+                ))
+            },
+        ),
+    ];
+
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "# HELP minecraft_up Whether the server answered the status ping."
+    );
+    let _ = writeln!(out, "# TYPE minecraft_up gauge");
+    for r in records {
+        let _ = writeln!(
+            out,
+            "minecraft_up{{server=\"{}\"}} {}",
+            escape_label(r.target),
+            u8::from(r.status.is_some())
+        );
+    }
+    let _ = writeln!(
+        out,
+        "# HELP minecraft_error Why the server didn't answer: unreachable, invalid_response or rejected."
+    );
+    let _ = writeln!(out, "# TYPE minecraft_error gauge");
+    for r in records {
+        if let Some(category) = r.error_category {
+            let _ = writeln!(
+                out,
+                "minecraft_error{{server=\"{}\",category=\"{category}\"}} 1",
+                escape_label(r.target)
+            );
+        }
+    }
+    for (name, help, metric) in metrics {
+        let _ = writeln!(out, "# HELP {name} {help}");
+        let _ = writeln!(out, "# TYPE {name} gauge");
+        for r in records {
+            let Some((labels, value)) = r.status.and_then(|s| metric(r, s)) else {
+                continue;
+            };
+            let _ = writeln!(
+                out,
+                "{name}{{server=\"{}\"{labels}}} {value}",
+                escape_label(r.target)
+            );
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::time::Duration;
+
+    fn info() -> mccli::Result<ServerInfo> {
+        Ok(ServerInfo {
+            status: serde_json::from_value(serde_json::json!({
+                "version": { "name": "Paper \"1.21.4\"", "protocol": 769 },
+                "players": { "max": 20, "online": 3 },
+                "description": "hello",
+                "previewsChat": false,
+            }))
+            .unwrap(),
+            latency: Duration::from_millis(25),
+        })
+    }
+
+    #[test]
+    fn json_keeps_unknown_fields() {
+        let info = info();
+        let json: serde_json::Value =
+            serde_json::from_str(&json(&Record::new("mc.example.com", &info))).unwrap();
+        assert_eq!(json["latency_ms"], 25.);
+        assert_eq!(json["status"]["previewsChat"], false);
+        assert_eq!(json["status"]["players"]["online"], 3);
+    }
+
+    #[test]
+    fn prometheus_exposition() {
+        let up = info();
+        let down = Err(mccli::Error::Connect {
+            addr: ([10, 0, 0, 2], 25565).into(),
+            source: std::io::ErrorKind::ConnectionRefused.into(),
+        });
+        let records = [Record::new("a:25565", &up), Record::new("b:25565", &down)];
+        let text = prometheus(&records);
+        for line in [
+            "minecraft_up{server=\"a:25565\"} 1",
+            "minecraft_up{server=\"b:25565\"} 0",
+            "minecraft_error{server=\"b:25565\",category=\"unreachable\"} 1",
+            "minecraft_players_online{server=\"a:25565\"} 3",
+            "minecraft_players_max{server=\"a:25565\"} 20",
+            "minecraft_latency_seconds{server=\"a:25565\"} 0.025",
+            "minecraft_protocol_version{server=\"a:25565\",version=\"Paper \\\"1.21.4\\\"\"} 769",
+        ] {
+            assert!(
+                text.lines().any(|l| l == line),
+                "missing {line:?} in:\n{text}"
+            );
+        }
+        assert!(!text.contains("players_online{server=\"b:25565\"}"));
+    }
+}
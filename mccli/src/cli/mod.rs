@@ -0,0 +1,54 @@
+pub mod format;
+pub mod proxy;
+pub mod query;
+pub mod replay;
+pub mod scan;
+pub mod serve;
+pub mod watch;
+
+use clap::Args;
+use mccli::{Limits, Timeouts};
+use std::time::Duration;
+
+#[derive(Args)]
+pub struct TimeoutArgs {
+    /// How long resolving and connecting to a server may take
+    #[arg(long, default_value = "5s", value_parser = humantime::parse_duration)]
+    connect_timeout: Duration,
+    /// How long sending the handshake and requests may take
+    #[arg(long, default_value = "5s", value_parser = humantime::parse_duration)]
+    handshake_timeout: Duration,
+    /// How long each response may take to arrive
+    #[arg(long, default_value = "5s", value_parser = humantime::parse_duration)]
+    read_timeout: Duration,
+}
+
+#[derive(Args)]
+pub struct LimitArgs {
+    /// The largest packet a server may send, in bytes
+    #[arg(long, default_value_t = Limits::PROTOCOL_MAX_PACKET)]
+    max_packet_size: usize,
+    /// The longest string a server may send, in characters
+    #[arg(long, default_value_t = Limits::PROTOCOL_MAX_STRING)]
+    max_string_length: usize,
+}
+
+impl From<LimitArgs> for Limits {
+    fn from(args: LimitArgs) -> Self {
+        Self {
+            max_packet: args.max_packet_size,
+            max_string: args.max_string_length,
+            ..Self::default()
+        }
+    }
+}
+
+impl From<TimeoutArgs> for Timeouts {
+    fn from(args: TimeoutArgs) -> Self {
+        Self {
+            connect: args.connect_timeout,
+            handshake: args.handshake_timeout,
+            read: args.read_timeout,
+        }
+    }
+}
@@ -0,0 +1,109 @@
+use anyhow::Context as _;
+use clap::Args;
+use mccli::{
+    packet::{
+        capture::Recorder,
+        registry::{Decoded, PROTOCOL_VERSION},
+    },
+    proxy::{Event, Proxy},
+    resolve,
+};
+use std::{net::IpAddr, path::PathBuf};
+use tokio::net::TcpListener;
+
+#[derive(Args)]
+pub struct ProxyArgs {
+    /// The server to forward to, as host[:port]. It has to be in offline mode
+    upstream: String,
+    /// The address to listen on
+    #[arg(long, default_value = "127.0.0.1")]
+    bind: IpAddr,
+    /// The port to listen on
+    #[arg(short, long, default_value_t = 25565)]
+    port: u16,
+    /// Only log packets with this name, e.g. LoginStart, can be repeated. Packets that aren't
+    /// decoded are called Unknown
+    #[arg(long, value_name = "PACKET")]
+    only: Vec<String>,
+    /// Don't log packets with this name, e.g. PlayClientboundKeepAlive, can be repeated
+    #[arg(long, value_name = "PACKET")]
+    hide: Vec<String>,
+    /// Record the proxied packets to this file, see `mccli replay`
+    #[arg(long, value_name = "FILE")]
+    record: Option<PathBuf>,
+}
+
+pub async fn run(args: ProxyArgs) -> anyhow::Result<()> {
+    let (upstream, _) = resolve(&args.upstream).await?;
+    let mut proxy = Proxy::new(upstream);
+    let recorder = match &args.record {
+        Some(path) => {
+            Some(Recorder::create(path).with_context(|| format!("creating {}", path.display()))?)
+        }
+        None => None,
+    };
+    if let Some(recorder) = &recorder {
+        proxy = proxy.record_to(recorder.clone());
+    }
+
+    let listener = TcpListener::bind((args.bind, args.port))
+        .await
+        .with_context(|| format!("binding {}:{}", args.bind, args.port))?;
+    println!("Proxying {} to {upstream}", listener.local_addr()?);
+    let shown = move |name: &str| {
+        (args.only.is_empty() || args.only.iter().any(|n| n.eq_ignore_ascii_case(name)))
+            && !args.hide.iter().any(|n| n.eq_ignore_ascii_case(name))
+    };
+    proxy
+        .serve(listener, move |event| {
+            match event {
+                Event::Connected {
+                    peer,
+                    protocol_version: Some(version),
+                    forward_only: true,
+                } => println!(
+                    "{peer}: connected with protocol {version}, only forwarding as packets are \
+                     decoded for protocol {PROTOCOL_VERSION}"
+                ),
+                Event::Connected { peer, .. } => println!("{peer}: connected"),
+                Event::Packet {
+                    peer,
+                    direction,
+                    state,
+                    packet,
+                } => match packet {
+                    Ok(packet) if shown(packet.name()) => {
+                        println!("{peer}: {direction:?} {state:?} {}", describe(&packet))
+                    }
+                    Ok(_) => {}
+                    Err(error) => {
+                        println!("{peer}: {direction:?} {state:?} {}", error.with_sources())
+                    }
+                },
+                Event::Disconnected { peer, result } => match result {
+                    Ok(()) => println!("{peer}: disconnected"),
+                    Err(error) => println!("{peer}: {}", error.with_sources()),
+                },
+            }
+            // the proxy usually ends with ^C, so don't keep anything in the buffer
+            if let Some(recorder) = &recorder
+                && let Err(error) = recorder.flush()
+            {
+                tracing::warn!(?error, "failed to write the capture");
+            }
+        })
+        .await?;
+    Ok(())
+}
+
+/// The packet, without the payloads of unknown packets as they can be huge in Play.
+fn describe(packet: &Decoded<'_>) -> String {
+    match packet {
+        Decoded::Unknown(packet) => format!(
+            "Unknown {{ id: {:#04x}, length: {} }}",
+            i32::from(packet.id()),
+            packet.reader().remaining()
+        ),
+        packet => format!("{packet:?}"),
+    }
+}
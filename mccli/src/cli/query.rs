@@ -0,0 +1,255 @@
+use super::{
+    LimitArgs, TimeoutArgs,
+    format::{self, Format, Record},
+};
+use anyhow::Context as _;
+use clap::{Args, ValueEnum};
+use mccli::{
+    HandshakeOptions, Limits, ServerInfo, Timeouts,
+    error::with_sources,
+    favicon::Favicon,
+    fetch_server_info_with_timeouts,
+    packet::capture::Recorder,
+    resolve_with_timeout,
+    versions::{self, Release},
+};
+use std::{io::IsTerminal as _, path::PathBuf};
+
+#[derive(Args)]
+pub struct QueryArgs {
+    /// The server to query, as host[:port]
+    #[arg(required = true)]
+    addr: Option<String>,
+    /// The hostname sent in the handshake, defaults to the host that was connected to
+    #[arg(long)]
+    host: Option<String>,
+    /// The port sent in the handshake, defaults to the port that was connected to
+    #[arg(long)]
+    port: Option<u16>,
+    /// The protocol version sent in the handshake
+    #[arg(long, default_value_t = HandshakeOptions::DEFAULT_PROTOCOL_VERSION)]
+    protocol: i32,
+    /// Send the protocol version of this release instead, e.g. 1.20.4
+    #[arg(long, conflicts_with = "protocol", value_parser = parse_release)]
+    version: Option<&'static Release>,
+    #[command(flatten)]
+    timeouts: TimeoutArgs,
+    #[command(flatten)]
+    limits: LimitArgs,
+    #[arg(long, value_enum, default_value_t)]
+    format: Format,
+    /// Write the server's favicon to this file, as a png
+    #[arg(long)]
+    save_favicon: Option<PathBuf>,
+    /// Show the server's favicon in the terminal
+    #[arg(long, value_enum, num_args = 0..=1, default_missing_value = "auto")]
+    preview: Option<Preview>,
+    /// Record the packets exchanged with the server to this file, see `mccli replay`. Legacy
+    /// pings aren't recorded
+    #[arg(long, value_name = "FILE")]
+    record: Option<PathBuf>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Preview {
+    /// Pick the best method the terminal supports
+    Auto,
+    /// The kitty graphics protocol
+    Kitty,
+    /// Sixel graphics
+    Sixel,
+    /// Colored half block characters, works on any truecolor terminal
+    Blocks,
+}
+
+impl Preview {
+    fn detect() -> Self {
+        let var = |name| std::env::var(name).unwrap_or_default();
+        if std::env::var_os("KITTY_WINDOW_ID").is_some()
+            || var("TERM") == "xterm-kitty"
+            || ["WezTerm", "ghostty"].contains(&var("TERM_PROGRAM").as_str())
+        {
+            Self::Kitty
+        } else if var("TERM").contains("sixel") || var("TERM_PROGRAM") == "mlterm" {
+            Self::Sixel
+        } else {
+            Self::Blocks
+        }
+    }
+
+    fn render(self, favicon: &Favicon) -> String {
+        match self {
+            Self::Auto => Self::detect().render(favicon),
+            Self::Kitty => favicon.to_kitty(),
+            Self::Sixel => favicon.to_sixel(),
+            Self::Blocks => favicon.to_half_blocks(),
+        }
+    }
+}
+
+pub async fn run(args: QueryArgs) -> anyhow::Result<()> {
+    let QueryArgs {
+        addr,
+        host,
+        port,
+        protocol,
+        version,
+        timeouts,
+        limits,
+        format,
+        save_favicon,
+        preview,
+        record,
+    } = args;
+    let target = addr.expect("required by clap");
+    let protocol = version.map_or(protocol, |r| r.protocol);
+
+    let query = Limits::from(limits).scope(query(&target, host, port, protocol, timeouts.into()));
+    let result = match &record {
+        Some(path) => {
+            let recorder =
+                Recorder::create(path).with_context(|| format!("creating {}", path.display()))?;
+            let result = recorder.clone().scope(query).await;
+            recorder
+                .flush()
+                .with_context(|| format!("writing {}", path.display()))?;
+            result
+        }
+        None => query.await,
+    };
+    if let Ok(info) = &result
+        && info.status.version.protocol != protocol
+    {
+        eprintln!(
+            "warning: the server speaks protocol {}, we sent {}",
+            describe_protocol(info.status.version.protocol),
+            describe_protocol(protocol),
+        );
+    }
+    let favicon = result
+        .as_ref()
+        .ok()
+        .and_then(|info| info.status.favicon.as_deref())
+        .map(Favicon::from_data_uri);
+    if let Some(path) = &save_favicon {
+        match &favicon {
+            Some(Ok(favicon)) => std::fs::write(path, favicon.png())
+                .with_context(|| format!("writing {}", path.display()))?,
+            Some(Err(error)) => anyhow::bail!("not saving the favicon: {}", with_sources(error)),
+            None if result.is_ok() => anyhow::bail!("the server has no favicon"),
+            None => {}
+        }
+    }
+    if format != Format::Text {
+        print!(
+            "{}",
+            format::render(format, &[Record::new(&target, &result)])
+        );
+        return Ok(result.map(drop)?);
+    }
+
+    let ServerInfo {
+        status: info,
+        latency,
+    } = result?;
+    println!("Server is online:");
+    println!("Latency: {}ms", latency.as_millis());
+    println!(
+        "Version: {}, protocol {}",
+        info.version.name,
+        describe_protocol(info.version.protocol)
+    );
+    println!("Players: {}/{}", info.players.online, info.players.max);
+    match &favicon {
+        Some(Ok(_)) => println!("Favicon: valid"),
+        Some(Err(error)) => println!("Favicon: invalid, {}", with_sources(error)),
+        None => println!("Favicon: none"),
+    }
+    if let (Some(preview), Some(Ok(favicon))) = (preview, &favicon) {
+        print!("{}", preview.render(favicon));
+    }
+    for p in info.players.sample {
+        println!("  - {}", p.name);
+    }
+    if let Some(modinfo) = info.modinfo {
+        println!("mod type: {}", modinfo.r#type);
+        if !modinfo.mod_list.is_empty() {
+            println!("Mod list:");
+            for m in modinfo.mod_list {
+                println!("  - {} {}", m.id, m.version)
+            }
+        }
+    }
+    if let Some(forge_data) = info.forge_data {
+        let forge_data = forge_data.decode().unwrap_or_else(|error| {
+            tracing::warn!(?error, "failed to decode forge data");
+            forge_data
+        });
+        println!(
+            "Forge network version: {}, {} channels",
+            forge_data.fml_network_version,
+            forge_data.channels.len()
+        );
+        if !forge_data.mods.is_empty() {
+            println!(
+                "Mod list{}:",
+                if forge_data.truncated {
+                    " (truncated)"
+                } else {
+                    ""
+                }
+            );
+            for m in forge_data.mods {
+                if m.is_server_only() {
+                    println!("  - {} (server only)", m.id)
+                } else {
+                    println!("  - {} {}", m.id, m.marker)
+                }
+            }
+        }
+    }
+    println!("Description:");
+    if std::io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none() {
+        println!("{}", info.description.to_ansi());
+    } else {
+        println!("{}", info.description.plain_text());
+    }
+    Ok(())
+}
+
+fn parse_release(name: &str) -> Result<&'static Release, String> {
+    Release::by_name(name).ok_or_else(|| {
+        let latest = versions::RELEASES.last().expect("there are releases");
+        format!(
+            "unknown release, expected one from 1.7.2 to {}",
+            latest.names.last().expect("releases have names")
+        )
+    })
+}
+
+/// The protocol number and the releases that speak it, e.g. `765 (1.20.3-1.20.4)`.
+fn describe_protocol(protocol: i32) -> String {
+    match Release::by_protocol(protocol) {
+        Some(release) => format!("{protocol} ({release})"),
+        None => format!("{protocol} (unknown release)"),
+    }
+}
+
+async fn query(
+    target: &str,
+    host: Option<String>,
+    port: Option<u16>,
+    protocol: i32,
+    timeouts: Timeouts,
+) -> mccli::Result<ServerInfo> {
+    let (addr, mut handshake) = resolve_with_timeout(target, &timeouts).await?;
+    if let Some(host) = host {
+        handshake.server_address = host;
+    }
+    if let Some(port) = port {
+        handshake.server_port = port;
+    }
+    handshake.protocol_version = protocol;
+
+    fetch_server_info_with_timeouts(addr, &handshake, &timeouts).await
+}
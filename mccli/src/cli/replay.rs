@@ -0,0 +1,96 @@
+use anyhow::Context as _;
+use clap::Args;
+use mccli::packet::{
+    capture::{self, Reader, Record},
+    connection::Connection,
+};
+use std::{net::IpAddr, path::PathBuf, sync::Arc, time::Duration};
+use tokio::net::TcpListener;
+
+#[derive(Args)]
+pub struct ReplayArgs {
+    /// A capture made with `--record`
+    capture: PathBuf,
+    /// Only print or replay the connection with this id
+    #[arg(long, value_name = "ID")]
+    connection: Option<u32>,
+    /// Instead of printing the capture, play the server's side of it to the clients that
+    /// connect on this port, each recorded connection to the next client
+    #[arg(long, value_name = "PORT")]
+    serve: Option<u16>,
+    /// The address to listen on with --serve
+    #[arg(long, default_value = "127.0.0.1", requires = "serve")]
+    bind: IpAddr,
+}
+
+pub async fn run(args: ReplayArgs) -> anyhow::Result<()> {
+    let path = &args.capture;
+    let records = Reader::open(path)
+        .and_then(|reader| reader.collect::<std::io::Result<Vec<_>>>())
+        .with_context(|| format!("reading {}", path.display()))?
+        .into_iter()
+        .filter(|r| args.connection.is_none_or(|id| r.connection == id))
+        .collect::<Vec<_>>();
+    if records.is_empty() && args.connection.is_some() {
+        anyhow::bail!("{} has no such connection", path.display());
+    }
+
+    let Some(port) = args.serve else {
+        print(&records).await;
+        return Ok(());
+    };
+    let connections = capture::connections(records);
+    let listener = TcpListener::bind((args.bind, port))
+        .await
+        .with_context(|| format!("binding {}:{port}", args.bind))?;
+    println!(
+        "Replaying {} connections on {}",
+        connections.len(),
+        listener.local_addr()?
+    );
+    let connections = Arc::new(connections);
+    // clients are given the recorded connections in order, starting over after the last one
+    let mut next = 0;
+    loop {
+        let (socket, peer) = listener.accept().await?;
+        let current = next;
+        next = (next + 1) % connections.len().max(1);
+        let connections = connections.clone();
+        tokio::spawn(async move {
+            let Some(records) = connections.get(current) else {
+                return println!("{peer}: the capture is empty");
+            };
+            println!("{peer}: replaying connection {}", records[0].connection);
+            let mut connection = Connection::server(socket);
+            match capture::replay(&mut connection, records).await {
+                Ok(()) => println!("{peer}: replayed"),
+                Err(error) => println!("{peer}: {}", mccli::Error::from(error).with_sources()),
+            }
+        });
+    }
+}
+
+/// Prints every record decoded, with its time since the first one and its connection.
+async fn print(records: &[Record]) {
+    let start = records.first().map_or(0, |r| r.micros);
+    for record in records {
+        let elapsed = Duration::from_micros(record.micros.saturating_sub(start).max(0) as u64);
+        let packet = match (&record.error, record.decode().await) {
+            (Some(error), _) => format!("invalid frame of {} bytes: {error}", record.payload.len()),
+            (None, Ok(decoded)) => format!("{decoded:?}"),
+            (None, Err(error)) => format!(
+                "packet {:?} of {} bytes: {}",
+                record.packet_id,
+                record.payload.len(),
+                mccli::Error::from(error).with_sources()
+            ),
+        };
+        println!(
+            "{:>12} #{} {:?} {:?} {packet}",
+            format!("{elapsed:.3?}"),
+            record.connection,
+            record.direction,
+            record.state,
+        );
+    }
+}
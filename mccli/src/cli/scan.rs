@@ -0,0 +1,75 @@
+use super::{
+    LimitArgs, TimeoutArgs,
+    format::{self, Format, Record},
+};
+use anyhow::Context as _;
+use clap::Args;
+use mccli::scan::Scanner;
+use std::{
+    io::{self, Read as _},
+    path::PathBuf,
+};
+
+#[derive(Args)]
+pub struct ScanArgs {
+    /// A file with one host[:port] per line, `-` for stdin. Empty lines and lines starting
+    /// with `#` are ignored
+    #[arg(default_value = "-")]
+    targets: PathBuf,
+    /// How many servers to query at the same time
+    #[arg(short = 'j', long, default_value_t = 16)]
+    concurrency: usize,
+    #[command(flatten)]
+    timeouts: TimeoutArgs,
+    #[command(flatten)]
+    limits: LimitArgs,
+    /// Table and prometheus output are printed once every server answered
+    #[arg(long, value_enum, default_value_t)]
+    format: Format,
+}
+
+pub async fn run(args: ScanArgs) -> anyhow::Result<()> {
+    let list = if args.targets.as_os_str() == "-" {
+        let mut list = String::new();
+        io::stdin()
+            .read_to_string(&mut list)
+            .context("reading targets from stdin")?;
+        list
+    } else {
+        std::fs::read_to_string(&args.targets)
+            .with_context(|| format!("reading {}", args.targets.display()))?
+    };
+    let targets = list
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .map(str::to_owned)
+        .collect::<Vec<_>>();
+
+    let mut reports = Scanner::new()
+        .concurrency(args.concurrency)
+        .timeouts(args.timeouts.into())
+        .limits(args.limits.into())
+        .scan(targets);
+    let mut finished = Vec::new();
+    while let Some(report) = reports.recv().await {
+        if format::is_aggregated(args.format) {
+            finished.push(report);
+        } else {
+            let mut record = Record::new(&report.target, &report.result);
+            if let Some(error) = &mut record.error {
+                *error = format!("after {:?}: {error}", report.elapsed);
+            }
+            print!("{}", format::render(args.format, &[record]));
+        }
+    }
+    if !finished.is_empty() {
+        finished.sort_by(|a, b| a.target.cmp(&b.target));
+        let records = finished
+            .iter()
+            .map(|r| Record::new(&r.target, &r.result))
+            .collect::<Vec<_>>();
+        print!("{}", format::render(args.format, &records));
+    }
+    Ok(())
+}
@@ -0,0 +1,110 @@
+use anyhow::Context as _;
+use clap::Args;
+use mccli::{
+    HandshakeOptions,
+    favicon::Favicon,
+    offline_uuid,
+    serve::{Event, Responder},
+    types::{
+        server::{Player, Players, Status, Version},
+        text::TextComponent,
+    },
+};
+use std::{net::IpAddr, path::PathBuf};
+use tokio::net::TcpListener;
+
+#[derive(Args)]
+pub struct ServeArgs {
+    /// The address to listen on
+    #[arg(long, default_value = "0.0.0.0")]
+    bind: IpAddr,
+    /// The port to listen on
+    #[arg(short, long, default_value_t = 25565)]
+    port: u16,
+    /// The description shown in the server list, § formatting codes are passed through
+    #[arg(long, default_value = "A Minecraft Server")]
+    motd: String,
+    /// A 64x64 png shown next to the server
+    #[arg(long)]
+    favicon: Option<PathBuf>,
+    /// A name shown when hovering the player count, can be repeated
+    #[arg(long = "player")]
+    players: Vec<String>,
+    /// The number of players online, defaults to the number of --player
+    #[arg(long)]
+    online: Option<u64>,
+    /// The maximum number of players
+    #[arg(long, default_value_t = 20)]
+    max: u64,
+    /// The version name, shown in place of the player count when the protocol doesn't match
+    /// the client's
+    #[arg(long, default_value = "1.21.4")]
+    version_name: String,
+    /// The protocol version
+    #[arg(long, default_value_t = HandshakeOptions::DEFAULT_PROTOCOL_VERSION)]
+    protocol: i32,
+    /// The reason players that try to join are disconnected with, defaults to the motd
+    #[arg(long)]
+    kick_message: Option<String>,
+}
+
+pub async fn run(args: ServeArgs) -> anyhow::Result<()> {
+    let favicon = match &args.favicon {
+        Some(path) => {
+            let png = std::fs::read(path).with_context(|| format!("reading {}", path.display()))?;
+            let favicon = Favicon::from_png(png)
+                .with_context(|| format!("{} can't be used as a favicon", path.display()))?;
+            Some(favicon.to_data_uri())
+        }
+        None => None,
+    };
+    let players = Players {
+        sample: args
+            .players
+            .iter()
+            .map(|name| Player {
+                id: offline_uuid(name).to_string(),
+                name: name.clone(),
+            })
+            .collect(),
+        ..Players::new(args.max, args.online.unwrap_or(args.players.len() as u64))
+    };
+    let status = Status {
+        favicon,
+        ..Status::new(
+            Version::new(args.version_name, args.protocol),
+            players,
+            TextComponent::text(args.motd),
+        )
+    };
+
+    let mut responder = Responder::new(&status);
+    if let Some(kick_message) = args.kick_message {
+        responder = responder.kick_message(kick_message);
+    }
+
+    let listener = TcpListener::bind((args.bind, args.port))
+        .await
+        .with_context(|| format!("binding {}:{}", args.bind, args.port))?;
+    println!("Listening on {}", listener.local_addr()?);
+    responder
+        .serve(listener, |event| match event {
+            Event::Status { peer, handshake } => println!(
+                "{peer}: status request for {}:{} (protocol {})",
+                handshake.server_address,
+                handshake.server_port,
+                i32::from(handshake.protocol_version),
+            ),
+            Event::LegacyPing { peer } => println!("{peer}: legacy ping"),
+            Event::Login {
+                peer,
+                handshake,
+                username,
+            } => println!(
+                "{peer}: {username} tried to join {}:{}",
+                handshake.server_address, handshake.server_port,
+            ),
+        })
+        .await?;
+    Ok(())
+}
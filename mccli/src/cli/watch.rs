@@ -0,0 +1,81 @@
+use super::{LimitArgs, TimeoutArgs};
+use clap::Args;
+use mccli::watch::{Change, Event, Watcher};
+use serde::Serialize;
+use std::{process::Command, time::Duration};
+
+#[derive(Args)]
+pub struct WatchArgs {
+    /// The server to watch, as host[:port]
+    addr: String,
+    /// How often to query the server
+    #[arg(long, default_value = "30s", value_parser = parse_interval)]
+    interval: Duration,
+    /// Print each change as a line of json instead of text
+    #[arg(long)]
+    json: bool,
+    /// Run this with `sh -c` on every change, which is passed in the MCCLI_EVENT, MCCLI_TARGET,
+    /// MCCLI_MESSAGE and MCCLI_JSON environment variables
+    #[arg(long, value_name = "COMMAND")]
+    exec: Option<String>,
+    #[command(flatten)]
+    timeouts: TimeoutArgs,
+    #[command(flatten)]
+    limits: LimitArgs,
+}
+
+/// A change as a line of json.
+#[derive(Serialize)]
+struct Line<'l> {
+    time: String,
+    target: &'l str,
+    #[serde(flatten)]
+    change: &'l Change,
+}
+
+pub async fn run(args: WatchArgs) -> anyhow::Result<()> {
+    let mut events = Watcher::new(args.interval)
+        .timeouts(args.timeouts.into())
+        .limits(args.limits.into())
+        .watch(args.addr.clone());
+    while let Some(Event { time, change }) = events.recv().await {
+        let time = humantime::format_rfc3339_seconds(time).to_string();
+        let json = serde_json::to_string(&Line {
+            time: time.clone(),
+            target: &args.addr,
+            change: &change,
+        })?;
+        if args.json {
+            println!("{json}");
+        } else {
+            println!("{time} {}: {change}", args.addr);
+        }
+
+        if let Some(hook) = &args.exec {
+            let mut command = Command::new("sh");
+            command
+                .arg("-c")
+                .arg(hook)
+                .env("MCCLI_EVENT", change.name())
+                .env("MCCLI_TARGET", &args.addr)
+                .env("MCCLI_MESSAGE", change.to_string())
+                .env("MCCLI_JSON", json);
+            // run hooks one at a time so they see the changes in order
+            match tokio::task::spawn_blocking(move || command.status()).await? {
+                Ok(status) if status.success() => {}
+                Ok(status) => eprintln!("warning: the hook failed with {status}"),
+                Err(error) => eprintln!("warning: failed to run the hook: {error}"),
+            }
+        }
+    }
+    // watching only stops on its own if it failed
+    anyhow::bail!("watching {} stopped unexpectedly", args.addr)
+}
+
+fn parse_interval(interval: &str) -> Result<Duration, String> {
+    match humantime::parse_duration(interval) {
+        Ok(interval) if interval.is_zero() => Err("the interval can't be zero".to_owned()),
+        Ok(interval) => Ok(interval),
+        Err(error) => Err(error.to_string()),
+    }
+}
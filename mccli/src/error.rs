@@ -0,0 +1,338 @@
+//! What can go wrong talking to a server.
+//!
+//! The codec works on [`io::Error`]s, so [`FramingError`] and [`DecodeError`] travel inside them
+//! until they reach [`Error`], which takes them back out.
+
+use std::{fmt, io, net::SocketAddr, time::Duration};
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum Error {
+    #[error("resolving {host}")]
+    Resolve {
+        host: String,
+        #[source]
+        source: io::Error,
+    },
+    #[error("connecting to {addr}")]
+    Connect {
+        addr: SocketAddr,
+        #[source]
+        source: io::Error,
+    },
+    #[error("{phase} timed out after {limit:?}")]
+    Timeout {
+        phase: &'static str,
+        limit: Duration,
+    },
+    /// The connection failed after it was established, e.g. it was closed or reset.
+    #[error("connection error")]
+    Io(#[source] io::Error),
+    /// The server sent bytes the codec couldn't make sense of, outside of a packet's framing or
+    /// payload, e.g. a string that isn't UTF-8 read straight from a stream.
+    #[error("invalid data")]
+    InvalidData(#[source] io::Error),
+    #[error(transparent)]
+    Framing(FramingError),
+    #[error(transparent)]
+    Decode(DecodeError),
+    /// The status response isn't json or doesn't have the fields a status must have.
+    #[error("invalid status json")]
+    Json(#[from] serde_json::Error),
+    #[error("malformed legacy ping response: {0}")]
+    Legacy(String),
+    /// The server sent a well formed packet that made no sense at that point.
+    #[error("{0}")]
+    Protocol(String),
+    #[error("disconnected: {0}")]
+    Disconnected(String),
+    #[error("the server is in online mode, which is not supported")]
+    OnlineMode,
+    /// The modern ping failed with `modern` and then the legacy ping failed with `legacy`.
+    #[error("legacy ping failed too: {legacy}")]
+    Fallback {
+        #[source]
+        modern: Box<Error>,
+        legacy: Box<Error>,
+    },
+}
+
+/// Broadly what went wrong.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Category {
+    /// The server couldn't be found, didn't accept the connection, or stopped answering.
+    Unreachable,
+    /// The server answered with something that isn't valid protocol.
+    InvalidResponse,
+    /// The server works but turned us away.
+    Rejected,
+}
+
+impl Category {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Unreachable => "unreachable",
+            Self::InvalidResponse => "invalid_response",
+            Self::Rejected => "rejected",
+        }
+    }
+}
+
+impl Error {
+    pub fn category(&self) -> Category {
+        match self {
+            Self::Resolve { .. } | Self::Connect { .. } | Self::Timeout { .. } | Self::Io(_) => {
+                Category::Unreachable
+            }
+            Self::InvalidData(_)
+            | Self::Framing(_)
+            | Self::Decode(_)
+            | Self::Json(_)
+            | Self::Legacy(_)
+            | Self::Protocol(_) => Category::InvalidResponse,
+            Self::Disconnected(_) | Self::OnlineMode => Category::Rejected,
+            Self::Fallback { modern, .. } => modern.category(),
+        }
+    }
+
+    /// Whether the server couldn't be reached at all, as opposed to it answering with junk.
+    pub fn is_unreachable(&self) -> bool {
+        self.category() == Category::Unreachable
+    }
+
+    /// Whether the server answered with something that isn't valid protocol.
+    pub fn is_invalid_response(&self) -> bool {
+        self.category() == Category::InvalidResponse
+    }
+
+    /// Displays the error followed by its sources, e.g.
+    /// `connecting to 10.0.0.2:25565: Connection refused`.
+    pub fn with_sources(&self) -> WithSources<'_> {
+        with_sources(self)
+    }
+}
+
+/// Displays any error followed by its sources, like [`Error::with_sources`] does.
+pub fn with_sources<'e>(error: &'e (dyn std::error::Error + 'e)) -> WithSources<'e> {
+    WithSources(error)
+}
+
+/// See [`with_sources`].
+#[derive(Debug, Clone, Copy)]
+pub struct WithSources<'e>(&'e (dyn std::error::Error + 'e));
+
+impl fmt::Display for WithSources<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)?;
+        let mut source = self.0.source();
+        while let Some(error) = source {
+            write!(f, ": {error}")?;
+            source = error.source();
+        }
+        Ok(())
+    }
+}
+
+/// Takes framing and decode errors back out of the [`io::Error`]s they travelled in. Other
+/// errors about the data itself are [`Error::InvalidData`], the rest [`Error::Io`].
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        let invalid = matches!(
+            error.kind(),
+            io::ErrorKind::InvalidData | io::ErrorKind::InvalidInput
+        );
+        let Some(inner) = error.get_ref() else {
+            return if invalid {
+                Self::InvalidData(error)
+            } else {
+                Self::Io(error)
+            };
+        };
+        if inner.is::<FramingError>() {
+            let inner = error.into_inner().expect("checked above");
+            Self::Framing(*inner.downcast().expect("checked above"))
+        } else if inner.is::<DecodeError>() {
+            let inner = error.into_inner().expect("checked above");
+            Self::Decode(*inner.downcast().expect("checked above"))
+        } else if invalid {
+            Self::InvalidData(error)
+        } else {
+            Self::Io(error)
+        }
+    }
+}
+
+/// The length prefix or compression header of a packet doesn't make sense.
+#[derive(Debug, thiserror::Error)]
+pub enum FramingError {
+    #[error("invalid packet length")]
+    Length(#[source] io::Error),
+    #[error("invalid packet id")]
+    PacketId(#[source] io::Error),
+    #[error("the connection ended in the middle of a {length} byte packet")]
+    Truncated { length: usize },
+    #[error("packet of {length} bytes is over the limit of {max}")]
+    TooLong { length: usize, max: usize },
+    #[error("negative packet length {0}")]
+    NegativeLength(i32),
+    #[error("packet length {length} is shorter than its {header} byte header")]
+    TooShort { length: usize, header: usize },
+    #[error("compressed packet of {length} bytes is below the threshold of {threshold}")]
+    BelowThreshold { length: usize, threshold: usize },
+    #[error("packet inflated to {actual} bytes, expected {expected}")]
+    Inflated { actual: usize, expected: usize },
+    #[error("invalid compressed packet")]
+    Inflate(#[source] io::Error),
+}
+
+impl From<FramingError> for io::Error {
+    fn from(error: FramingError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, error)
+    }
+}
+
+/// A packet's payload couldn't be decoded.
+#[derive(Debug, thiserror::Error)]
+pub struct DecodeError {
+    /// The type that failed to decode.
+    pub ty: &'static str,
+    /// The field of `ty` that failed, for derived types.
+    pub field: Option<&'static str>,
+    /// How far into the payload decoding got. Unknown when not decoding a payload, e.g. when
+    /// reading straight from a stream.
+    pub offset: Option<usize>,
+    #[source]
+    pub source: io::Error,
+}
+
+impl DecodeError {
+    /// Runs `f`, which reads `field` of `ty`. Used by the derived codecs.
+    #[doc(hidden)]
+    pub fn field<T>(
+        ty: &'static str,
+        field: &'static str,
+        f: impl FnOnce() -> io::Result<T>,
+    ) -> io::Result<T> {
+        f().map_err(|e| Self::in_field(e, ty, field))
+    }
+
+    /// Like [`DecodeError::field`] but async.
+    #[doc(hidden)]
+    pub async fn field_async<T>(
+        ty: &'static str,
+        field: &'static str,
+        f: impl Future<Output = io::Result<T>>,
+    ) -> io::Result<T> {
+        f.await.map_err(|e| Self::in_field(e, ty, field))
+    }
+
+    /// Wraps `error`, which happened reading `field` of `ty`, unless it's already a decode error
+    /// of some inner type.
+    fn in_field(error: io::Error, ty: &'static str, field: &'static str) -> io::Error {
+        if error.get_ref().is_some_and(|e| e.is::<Self>()) {
+            return error;
+        }
+        Self {
+            ty,
+            field: Some(field),
+            offset: None,
+            source: error,
+        }
+        .into()
+    }
+
+    /// Wraps `error`, which happened decoding a `T`, or fills in the offset of the decode error
+    /// it already is.
+    pub(crate) fn at<T>(mut error: io::Error, offset: usize) -> io::Error {
+        match error.get_mut().and_then(|e| e.downcast_mut::<Self>()) {
+            Some(decode) => {
+                decode.offset.get_or_insert(offset);
+                error
+            }
+            None => Self {
+                ty: std::any::type_name::<T>(),
+                field: None,
+                offset: Some(offset),
+                source: error,
+            }
+            .into(),
+        }
+    }
+}
+
+impl From<DecodeError> for io::Error {
+    fn from(error: DecodeError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, error)
+    }
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // `type_name` paths, without the modules or elided lifetimes
+        let ty = self
+            .ty
+            .split_inclusive(|c: char| "<>,;()[]& ".contains(c))
+            .map(|part| part.rsplit("::").next().unwrap_or(part))
+            .collect::<String>()
+            .replace("<'_>", "")
+            .replace("'_, ", "");
+        write!(f, "decoding {ty}")?;
+        if let Some(field) = self.field {
+            write!(f, ".{field}")?;
+        }
+        if let Some(offset) = self.offset {
+            write!(f, " at byte {offset}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn categories() {
+        let io = || io::Error::from(io::ErrorKind::ConnectionRefused);
+        assert!(
+            Error::Connect {
+                addr: ([127, 0, 0, 1], 25565).into(),
+                source: io(),
+            }
+            .is_unreachable()
+        );
+        assert!(Error::from(io()).is_unreachable());
+        assert!(
+            Error::from(io::Error::from(FramingError::NegativeLength(-1))).is_invalid_response()
+        );
+        let invalid = Error::from(io::Error::new(io::ErrorKind::InvalidData, "not utf-16"));
+        assert!(matches!(invalid, Error::InvalidData(_)));
+        assert!(invalid.is_invalid_response());
+        assert!(
+            Error::Fallback {
+                modern: Box::new(Error::Protocol("expected pong".into())),
+                legacy: Box::new(Error::from(io())),
+            }
+            .is_invalid_response()
+        );
+        assert_eq!(Error::OnlineMode.category(), Category::Rejected);
+    }
+
+    #[test]
+    fn errors_with_sources() {
+        let error = Error::Connect {
+            addr: ([127, 0, 0, 1], 25565).into(),
+            source: io::Error::other("connection refused"),
+        };
+        assert_eq!(
+            error.with_sources().to_string(),
+            "connecting to 127.0.0.1:25565: connection refused"
+        );
+        assert_eq!(
+            Error::OnlineMode.with_sources().to_string(),
+            Error::OnlineMode.to_string()
+        );
+    }
+}
@@ -0,0 +1,271 @@
+//! The server icon, sent in the status as a `data:image/png;base64,` uri.
+//!
+//! The vanilla client only shows 64x64 PNGs and silently shows the default icon otherwise, so
+//! [`Favicon::from_data_uri`] checks both.
+
+use base64::{Engine as _, prelude::BASE64_STANDARD};
+use std::fmt::Write as _;
+
+const PREFIX: &str = "data:image/png;base64,";
+
+pub const SIZE: u32 = 64;
+
+#[derive(Debug, thiserror::Error)]
+pub enum FaviconError {
+    /// The uri isn't a base64 png data uri.
+    #[error("favicon doesn't start with {PREFIX:?}")]
+    NotADataUri,
+    #[error("favicon isn't valid base64")]
+    Base64(#[source] base64::DecodeError),
+    #[error("favicon isn't a valid png")]
+    Png(#[source] png::DecodingError),
+    #[error("favicon is {width}x{height}, it must be {SIZE}x{SIZE}")]
+    WrongSize { width: u32, height: u32 },
+}
+
+/// A decoded and validated favicon.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Favicon {
+    png: Vec<u8>,
+    /// `SIZE * SIZE` pixels, row by row.
+    rgba: Vec<[u8; 4]>,
+}
+
+impl Favicon {
+    pub fn from_data_uri(uri: &str) -> Result<Self, FaviconError> {
+        let data = uri.strip_prefix(PREFIX).ok_or(FaviconError::NotADataUri)?;
+        // some servers wrap the base64 in lines, which the client accepts
+        let data = data
+            .chars()
+            .filter(|c| !c.is_ascii_whitespace())
+            .collect::<String>();
+        let png = BASE64_STANDARD.decode(data).map_err(FaviconError::Base64)?;
+        Self::from_png(png)
+    }
+
+    pub fn from_png(png: Vec<u8>) -> Result<Self, FaviconError> {
+        let mut decoder = png::Decoder::new(&png[..]);
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+        let mut reader = decoder.read_info().map_err(FaviconError::Png)?;
+        let (width, height) = reader.info().size();
+        if (width, height) != (SIZE, SIZE) {
+            return Err(FaviconError::WrongSize { width, height });
+        }
+        let mut buffer = vec![0; reader.output_buffer_size()];
+        let frame = reader.next_frame(&mut buffer).map_err(FaviconError::Png)?;
+        let buffer = &buffer[..frame.buffer_size()];
+        let rgba = match frame.color_type {
+            png::ColorType::Rgba => buffer
+                .chunks_exact(4)
+                .map(|p| [p[0], p[1], p[2], p[3]])
+                .collect(),
+            png::ColorType::Rgb => buffer
+                .chunks_exact(3)
+                .map(|p| [p[0], p[1], p[2], 0xff])
+                .collect(),
+            png::ColorType::GrayscaleAlpha => buffer
+                .chunks_exact(2)
+                .map(|p| [p[0], p[0], p[0], p[1]])
+                .collect(),
+            png::ColorType::Grayscale => buffer.iter().map(|&g| [g, g, g, 0xff]).collect(),
+            png::ColorType::Indexed => unreachable!("expanded by the decoder"),
+        };
+        Ok(Self { png, rgba })
+    }
+
+    /// The original png file.
+    pub fn png(&self) -> &[u8] {
+        &self.png
+    }
+
+    pub fn to_data_uri(&self) -> String {
+        format!("{PREFIX}{}", BASE64_STANDARD.encode(&self.png))
+    }
+
+    fn pixel(&self, x: u32, y: u32) -> [u8; 4] {
+        self.rgba[(y * SIZE + x) as usize]
+    }
+
+    /// Shows the image with the kitty graphics protocol.
+    pub fn to_kitty(&self) -> String {
+        let data = BASE64_STANDARD.encode(&self.png);
+        let chunks = data.as_bytes().chunks(4096).collect::<Vec<_>>();
+        let mut out = String::new();
+        for (i, chunk) in chunks.iter().enumerate() {
+            let more = u8::from(i + 1 < chunks.len());
+            let chunk = std::str::from_utf8(chunk).expect("base64 is ascii");
+            if i == 0 {
+                let _ = write!(out, "\x1b_Gf=100,a=T,m={more};{chunk}\x1b\\");
+            } else {
+                let _ = write!(out, "\x1b_Gm={more};{chunk}\x1b\\");
+            }
+        }
+        out.push('\n');
+        out
+    }
+
+    /// Shows the image as sixels, quantized to a 6x6x6 color cube.
+    pub fn to_sixel(&self) -> String {
+        const LEVELS: u32 = 6;
+        let index = |[r, g, b, _]: [u8; 4]| {
+            let level = |c: u8| u32::from(c) * (LEVELS - 1) / 255;
+            (level(r) * LEVELS + level(g)) * LEVELS + level(b)
+        };
+
+        // transparent background, 1:1 aspect ratio
+        let mut out = format!("\x1bP0;1;0q\"1;1;{SIZE};{SIZE}");
+        for i in 0..LEVELS.pow(3) {
+            let percent = |level: u32| level * 100 / (LEVELS - 1);
+            let (r, g, b) = (i / LEVELS / LEVELS, i / LEVELS % LEVELS, i % LEVELS);
+            let _ = write!(out, "#{i};2;{};{};{}", percent(r), percent(g), percent(b));
+        }
+        for band in (0..SIZE).step_by(6) {
+            let mut colors = (0..SIZE)
+                .flat_map(|x| (band..(band + 6).min(SIZE)).map(move |y| (x, y)))
+                .map(|(x, y)| self.pixel(x, y))
+                .filter(|p| p[3] >= 0x80)
+                .map(index)
+                .collect::<Vec<_>>();
+            colors.sort_unstable();
+            colors.dedup();
+            for color in colors {
+                let _ = write!(out, "#{color}");
+                for x in 0..SIZE {
+                    let bits = (0..6)
+                        .filter(|dy| band + dy < SIZE)
+                        .filter(|dy| {
+                            let p = self.pixel(x, band + dy);
+                            p[3] >= 0x80 && index(p) == color
+                        })
+                        .fold(0, |bits, dy| bits | 1 << dy);
+                    out.push(char::from(63 + bits as u8));
+                }
+                // back to the start of the band for the next color
+                out.push('$');
+            }
+            out.push('-');
+        }
+        out.push_str("\x1b\\\n");
+        out
+    }
+
+    /// Shows the image with `▀` characters, two pixels per character, in truecolor.
+    pub fn to_half_blocks(&self) -> String {
+        let mut out = String::new();
+        for y in (0..SIZE).step_by(2) {
+            for x in 0..SIZE {
+                let top = self.pixel(x, y);
+                let bottom = self.pixel(x, y + 1);
+                let visible = |p: [u8; 4]| p[3] >= 0x80;
+                match (visible(top), visible(bottom)) {
+                    (true, true) => {
+                        let _ = write!(
+                            out,
+                            "\x1b[38;2;{};{};{};48;2;{};{};{}m▀",
+                            top[0], top[1], top[2], bottom[0], bottom[1], bottom[2]
+                        );
+                    }
+                    (true, false) => {
+                        let _ = write!(out, "\x1b[0;38;2;{};{};{}m▀", top[0], top[1], top[2]);
+                    }
+                    (false, true) => {
+                        let _ = write!(
+                            out,
+                            "\x1b[0;38;2;{};{};{}m▄",
+                            bottom[0], bottom[1], bottom[2]
+                        );
+                    }
+                    (false, false) => out.push_str("\x1b[0m "),
+                }
+            }
+            out.push_str("\x1b[0m\n");
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let mut png = Vec::new();
+        let mut encoder = png::Encoder::new(&mut png, width, height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let pixels = (0..width * height)
+            .flat_map(|i| {
+                [
+                    (i % 256) as u8,
+                    0x80,
+                    0xff,
+                    if i % 7 == 0 { 0 } else { 0xff },
+                ]
+            })
+            .collect::<Vec<_>>();
+        encoder
+            .write_header()
+            .unwrap()
+            .write_image_data(&pixels)
+            .unwrap();
+        png
+    }
+
+    #[test]
+    fn data_uri_roundtrip() {
+        let favicon = Favicon::from_png(png(64, 64)).unwrap();
+        let uri = favicon.to_data_uri();
+        assert!(uri.starts_with("data:image/png;base64,iVBOR"));
+        assert_eq!(Favicon::from_data_uri(&uri).unwrap(), favicon);
+
+        let wrapped = uri
+            .as_bytes()
+            .chunks(76)
+            .map(|l| std::str::from_utf8(l).unwrap())
+            .collect::<Vec<_>>()
+            .join("\n");
+        assert_eq!(Favicon::from_data_uri(&wrapped).unwrap(), favicon);
+        assert_eq!(favicon.pixel(1, 0), [1, 0x80, 0xff, 0xff]);
+    }
+
+    #[test]
+    fn invalid_favicons() {
+        let uri = |png: &[u8]| format!("{PREFIX}{}", BASE64_STANDARD.encode(png));
+        assert!(matches!(
+            Favicon::from_data_uri(&uri(&png(128, 128))),
+            Err(FaviconError::WrongSize {
+                width: 128,
+                height: 128
+            })
+        ));
+        assert!(matches!(
+            Favicon::from_data_uri(&uri(b"GIF89a")),
+            Err(FaviconError::Png(_))
+        ));
+        assert!(matches!(
+            Favicon::from_data_uri("data:image/png;base64,@@@"),
+            Err(FaviconError::Base64(_))
+        ));
+        assert!(matches!(
+            Favicon::from_data_uri("data:image/jpeg;base64,"),
+            Err(FaviconError::NotADataUri)
+        ));
+    }
+
+    #[test]
+    fn previews() {
+        let favicon = Favicon::from_png(png(64, 64)).unwrap();
+
+        let blocks = favicon.to_half_blocks();
+        assert_eq!(blocks.lines().count(), 32);
+
+        let kitty = favicon.to_kitty();
+        assert!(kitty.starts_with("\x1b_Gf=100,a=T,m="));
+        assert!(kitty.trim_end().ends_with("\x1b\\"));
+
+        let sixel = favicon.to_sixel();
+        assert!(sixel.starts_with("\x1bP0;1;0q\"1;1;64;64"));
+        // 64 rows in bands of 6
+        assert_eq!(sixel.matches('-').count(), 11);
+    }
+}
@@ -0,0 +1,247 @@
+//! The server list ping used by servers older than 1.7.
+//!
+//! The client sends `0xFE 0x01` followed by an `MC|PingHost` plugin message and the server
+//! answers with a kick packet (`0xFF`) whose reason is a UTF-16BE string. Since 1.4 that string
+//! is `§1\0<protocol>\0<version>\0<motd>\0<online>\0<max>`, before that it was
+//! `<motd>§<online>§<max>`.
+
+use crate::{
+    Error, HandshakeOptions, Result, ServerInfo, Timeouts, connect, timeout,
+    types::{server, text::TextComponent},
+};
+use std::{net::SocketAddr, time::Instant};
+use tokio::io::{AsyncRead, AsyncReadExt as _, AsyncWriteExt as _};
+
+/// The protocol version sent in the `MC|PingHost` message, 1.6.4.
+const PING_HOST_PROTOCOL: u8 = 78;
+
+pub async fn fetch_server_info(
+    addr: SocketAddr,
+    handshake: &HandshakeOptions,
+    timeouts: &Timeouts,
+) -> Result<ServerInfo> {
+    let request = ping_request(&handshake.server_address, handshake.server_port)?;
+    tracing::info!("connecting to: {addr} (legacy)");
+    let mut socket = connect(addr, timeouts.connect).await?;
+
+    tracing::info!("sending legacy ping");
+    let start = Instant::now();
+    timeout(
+        "sending legacy ping",
+        timeouts.handshake,
+        socket.write_all(&request),
+    )
+    .await?;
+
+    tracing::info!("reading legacy response");
+    let response = timeout(
+        "reading legacy response",
+        timeouts.read,
+        read_response(&mut socket),
+    )
+    .await?;
+    let latency = start.elapsed();
+
+    tracing::debug!(%response, "legacy response");
+
+    let status = parse_response(&response).ok_or_else(|| Error::Legacy(format!("{response:?}")))?;
+
+    Ok(ServerInfo { status, latency })
+}
+
+fn utf16_be(s: &str) -> impl Iterator<Item = u8> + '_ {
+    s.encode_utf16().flat_map(u16::to_be_bytes)
+}
+
+/// The ping, failing if `host` is too long for the length of the message to fit in its field.
+fn ping_request(host: &str, port: u16) -> Result<Vec<u8>> {
+    const CHANNEL: &str = "MC|PingHost";
+
+    let host_len = host.encode_utf16().count();
+    let too_long = || Error::Protocol(format!("host of {host_len} characters is too long"));
+    let host_len = u16::try_from(host_len).map_err(|_| too_long())?;
+    let message_len = host_len
+        .checked_mul(2)
+        .and_then(|n| n.checked_add(7))
+        .ok_or_else(too_long)?;
+    let mut buffer = vec![0xfe, 0x01, 0xfa];
+    buffer.extend((CHANNEL.len() as u16).to_be_bytes());
+    buffer.extend(utf16_be(CHANNEL));
+    buffer.extend(message_len.to_be_bytes());
+    buffer.push(PING_HOST_PROTOCOL);
+    buffer.extend(host_len.to_be_bytes());
+    buffer.extend(utf16_be(host));
+    buffer.extend(i32::from(port).to_be_bytes());
+    Ok(buffer)
+}
+
+async fn read_response<R: AsyncRead + Unpin>(mut r: R) -> Result<std::string::String> {
+    let id = r.read_u8().await?;
+    if id != 0xff {
+        return Err(Error::Legacy(format!(
+            "expected kick packet (0xff), got 0x{id:02x}"
+        )));
+    }
+    let length = r.read_u16().await?;
+    let mut buffer = vec![0; usize::from(length) * 2];
+    r.read_exact(&mut buffer).await?;
+    let units = buffer
+        .chunks_exact(2)
+        .map(|c| u16::from_be_bytes([c[0], c[1]]))
+        .collect::<Vec<_>>();
+    std::string::String::from_utf16(&units).map_err(|e| Error::Legacy(e.to_string()))
+}
+
+/// The kick packet a server answers a legacy ping with, in the 1.4+ format.
+///
+/// The length of the reason is a `u16`, so a motd too long for it to fit is cut short.
+pub(crate) fn encode_response(status: &server::Status) -> Vec<u8> {
+    let fields = [
+        "§1".to_owned(),
+        status.version.protocol.to_string(),
+        status.version.name.clone(),
+        status.players.online.to_string(),
+        status.players.max.to_string(),
+    ];
+    let fixed = fields
+        .iter()
+        .map(|f| f.encode_utf16().count() + 1)
+        .sum::<usize>();
+    let motd = status.description.to_legacy();
+    let motd = truncate_utf16(&motd, usize::from(u16::MAX).saturating_sub(fixed));
+    let [magic, protocol, version, online, max] = &fields;
+    let response = [magic, protocol, version, motd, online, max].join("\0");
+    let response = truncate_utf16(&response, usize::from(u16::MAX));
+
+    let length =
+        u16::try_from(response.encode_utf16().count()).expect("the response was truncated to fit");
+    let mut bytes = vec![0xff];
+    bytes.extend(length.to_be_bytes());
+    bytes.extend(utf16_be(response));
+    bytes
+}
+
+/// The longest prefix of `s` that is at most `max` UTF-16 code units long.
+fn truncate_utf16(s: &str, max: usize) -> &str {
+    let mut units = 0;
+    let end = s
+        .char_indices()
+        .find(|(_, c)| {
+            units += c.len_utf16();
+            units > max
+        })
+        .map_or(s.len(), |(i, _)| i);
+    &s[..end]
+}
+
+fn parse_response(response: &str) -> Option<server::Status> {
+    let (protocol, version, motd, online, max) = match response.strip_prefix("§1\0") {
+        Some(fields) => {
+            let mut fields = fields.split('\0');
+            let protocol = fields.next()?.parse().ok()?;
+            let version = fields.next()?;
+            let motd = fields.next()?;
+            let online = fields.next()?;
+            let max = fields.next()?;
+            (protocol, version, motd, online, max)
+        }
+        None => {
+            let mut fields = response.rsplitn(3, '§');
+            let max = fields.next()?;
+            let online = fields.next()?;
+            let motd = fields.next()?;
+            (0, "", motd, online, max)
+        }
+    };
+
+    Some(server::Status::new(
+        server::Version::new(version, protocol),
+        server::Players::new(max.parse().ok()?, online.parse().ok()?),
+        TextComponent::text(motd),
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn ping_request_layout() {
+        let request = ping_request("localhost", 25565).unwrap();
+        assert_eq!(request[..3], [0xfe, 0x01, 0xfa]);
+        // "MC|PingHost" is 11 characters long
+        assert_eq!(request[3..5], [0x00, 0x0b]);
+        // protocol byte + host length + host + port
+        assert_eq!(request[27..29], [0x00, 7 + 2 * 9]);
+        assert_eq!(request[29], PING_HOST_PROTOCOL);
+        assert_eq!(request[request.len() - 4..], 25565i32.to_be_bytes());
+
+        // the length of the message would wrap
+        let longest = "a".repeat((usize::from(u16::MAX) - 7) / 2);
+        assert!(ping_request(&longest, 25565).is_ok());
+        assert!(ping_request(&format!("{longest}a"), 25565).is_err());
+    }
+
+    #[tokio::test]
+    async fn response_length() {
+        let mut status =
+            parse_response(&["§1", "769", "1.21.4", "hi", "1", "5"].join("\0")).unwrap();
+        let length = |bytes: &[u8]| usize::from(u16::from_be_bytes([bytes[1], bytes[2]]));
+
+        let bytes = encode_response(&status);
+        assert_eq!(length(&bytes) * 2, bytes.len() - 3);
+
+        // the length of the motd would wrap
+        status.description = TextComponent::text("é".repeat(usize::from(u16::MAX)));
+        let bytes = encode_response(&status);
+        assert_eq!(length(&bytes), usize::from(u16::MAX));
+        assert_eq!(length(&bytes) * 2, bytes.len() - 3);
+        let response = read_response(Cursor::new(bytes)).await.unwrap();
+        assert_eq!(parse_response(&response).unwrap().players.max, 5);
+    }
+
+    #[tokio::test]
+    async fn decode_response() {
+        let text = ["§1", "127", "1.6.4", "A Minecraft Server", "3", "20"].join("\0");
+        let mut bytes = vec![0xff];
+        bytes.extend((text.encode_utf16().count() as u16).to_be_bytes());
+        bytes.extend(utf16_be(&text));
+
+        let response = read_response(Cursor::new(bytes)).await.unwrap();
+        assert_eq!(response, text);
+
+        let status = parse_response(&response).unwrap();
+        assert_eq!(status.version.protocol, 127);
+        assert_eq!(status.version.name, "1.6.4");
+        assert_eq!(status.players.online, 3);
+        assert_eq!(status.players.max, 20);
+        assert!(status.description == TextComponent::text("A Minecraft Server"));
+    }
+
+    #[tokio::test]
+    async fn encode_roundtrip() {
+        let status = parse_response(&["§1", "769", "1.21.4", "hi", "1", "5"].join("\0")).unwrap();
+        let response = read_response(Cursor::new(encode_response(&status)))
+            .await
+            .unwrap();
+        let decoded = parse_response(&response).unwrap();
+        assert_eq!(decoded.version.name, "1.21.4");
+        assert_eq!(decoded.players.online, 1);
+        assert_eq!(decoded.description.plain_text(), "hi");
+    }
+
+    #[test]
+    fn parse_beta_response() {
+        let status = parse_response("A §aBeta§r server§0§10").unwrap();
+        assert_eq!(status.players.online, 0);
+        assert_eq!(status.players.max, 10);
+        assert!(status.description == TextComponent::text("A §aBeta§r server"));
+    }
+
+    #[test]
+    fn reject_garbage() {
+        assert!(parse_response("not a ping response").is_none());
+        assert!(parse_response(&["§1", "abc", "1.6.4", "motd", "1", "20"].join("\0")).is_none());
+    }
+}
@@ -1,27 +1,282 @@
-mod packet;
+extern crate self as mccli;
 
-use packet::Packet;
-use std::net::SocketAddr;
+pub mod error;
+pub mod favicon;
+mod legacy;
+mod limits;
+mod login;
+pub mod packet;
+pub mod proxy;
+pub mod scan;
+pub mod serve;
+pub mod srv;
+pub mod testing;
+pub mod versions;
+pub mod watch;
+
+use packet::{Packet, connection::Connection, status::StatusResponse};
+use std::{
+    io,
+    net::{IpAddr, SocketAddr},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
 use tokio::net::TcpStream;
 
-pub use packet::types;
+pub use error::{Error, Result};
+pub use limits::Limits;
+pub use login::{Session, login, login_with, login_with_timeouts, offline_uuid};
+pub use packet::{HandshakeOptions, Intent, types};
+
+#[derive(Debug)]
+pub struct ServerInfo {
+    pub status: types::server::Status,
+    /// Round trip time of the ping/pong exchange that follows the status request.
+    pub latency: Duration,
+}
+
+/// How long each phase of a status query may take before giving up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Timeouts {
+    /// Resolving the address and establishing the connection.
+    pub connect: Duration,
+    /// Sending the handshake and the requests.
+    pub handshake: Duration,
+    /// Each read of a response.
+    pub read: Duration,
+}
+
+impl Default for Timeouts {
+    fn default() -> Self {
+        Self {
+            connect: Duration::from_secs(5),
+            handshake: Duration::from_secs(5),
+            read: Duration::from_secs(5),
+        }
+    }
+}
+
+/// Runs `f`, failing with [`Error::Timeout`] if it takes longer than `limit`.
+pub(crate) async fn timeout<T, E>(
+    phase: &'static str,
+    limit: Duration,
+    f: impl Future<Output = Result<T, E>>,
+) -> Result<T>
+where
+    Error: From<E>,
+{
+    match tokio::time::timeout(limit, f).await {
+        Ok(result) => Ok(result?),
+        Err(_) => Err(Error::Timeout { phase, limit }),
+    }
+}
+
+/// Connects to `addr`, failing with [`Error::Connect`].
+pub(crate) async fn connect(addr: SocketAddr, limit: Duration) -> Result<TcpStream> {
+    timeout("connect", limit, async {
+        TcpStream::connect(addr)
+            .await
+            .map_err(|source| Error::Connect { addr, source })
+    })
+    .await
+}
+
+/// Splits `addr` into host and port, if there is one.
+fn split_host_port(addr: &str) -> (&str, Option<u16>) {
+    if addr.parse::<IpAddr>().is_ok() {
+        return (addr, None);
+    }
+    match addr.rsplit_once(':').map(|(h, p)| (h, p.parse())) {
+        Some((host, Ok(port))) => (
+            host.trim_start_matches('[').trim_end_matches(']'),
+            Some(port),
+        ),
+        _ => (addr, None),
+    }
+}
+
+/// Resolves `addr` the way the vanilla client does.
+///
+/// If `addr` is a hostname without a port, the `_minecraft._tcp` SRV record is followed first,
+/// otherwise the port defaults to 25565. The returned handshake options carry the host and port
+/// that were finally connected to.
+pub async fn resolve(addr: &str) -> Result<(SocketAddr, HandshakeOptions)> {
+    let resolver = srv::Resolver::from_system();
+    resolve_with(resolver.as_ref().ok(), addr).await
+}
+
+/// Like [`resolve`] but giving up after [`Timeouts::connect`].
+pub async fn resolve_with_timeout(
+    addr: &str,
+    timeouts: &Timeouts,
+) -> Result<(SocketAddr, HandshakeOptions)> {
+    timeout("resolving", timeouts.connect, resolve(addr)).await
+}
+
+async fn resolve_with(
+    resolver: Option<&srv::Resolver>,
+    addr: &str,
+) -> Result<(SocketAddr, HandshakeOptions)> {
+    let (host, port) = match split_host_port(addr) {
+        (host, Some(port)) => (host.to_owned(), port),
+        (host, None) if host.parse::<IpAddr>().is_ok() => (host.to_owned(), 25565),
+        (host, None) => {
+            let srv = match resolver {
+                Some(resolver) => resolver.resolve_minecraft(host).await,
+                None => Ok(None),
+            };
+            match srv {
+                Ok(Some(target)) => {
+                    tracing::info!(?target, "following SRV record");
+                    target
+                }
+                Ok(None) => (host.to_owned(), 25565),
+                Err(error) => {
+                    tracing::warn!(?error, "SRV lookup failed");
+                    (host.to_owned(), 25565)
+                }
+            }
+        }
+    };
+    let resolve_error = |source| Error::Resolve {
+        host: format!("{host}:{port}"),
+        source,
+    };
+    let socket = tokio::net::lookup_host((host.as_str(), port))
+        .await
+        .map_err(resolve_error)?
+        .next()
+        .ok_or_else(|| {
+            resolve_error(io::Error::new(
+                io::ErrorKind::NotFound,
+                "no addresses found",
+            ))
+        })?;
+    Ok((socket, HandshakeOptions::new(host, port)))
+}
 
-pub async fn fetch_server_info(addr: SocketAddr) -> anyhow::Result<types::server::Status> {
+/// Queries the server at `addr` using the server list ping, with the default [`Timeouts`].
+///
+/// If the server doesn't speak the modern (1.7+) protocol, the legacy ping is tried instead.
+pub async fn fetch_server_info(
+    addr: SocketAddr,
+    handshake: &HandshakeOptions,
+) -> Result<ServerInfo> {
+    fetch_server_info_with_timeouts(addr, handshake, &Timeouts::default()).await
+}
+
+/// Like [`fetch_server_info`] but giving up on each phase after the given timeouts.
+///
+/// A server that times out isn't retried with the legacy ping, as it isn't going to answer that
+/// either.
+pub async fn fetch_server_info_with_timeouts(
+    addr: SocketAddr,
+    handshake: &HandshakeOptions,
+    timeouts: &Timeouts,
+) -> Result<ServerInfo> {
     tracing::info!("connecting to: {addr}");
-    let mut socket = TcpStream::connect(addr).await?;
+    let socket = connect(addr, timeouts.connect).await?;
 
-    tracing::info!("sending handshake");
-    Packet::handshake(769).await.write(&mut socket).await?;
+    match fetch_modern_server_info(socket, handshake, timeouts).await {
+        Ok(info) => Ok(info),
+        Err(error @ Error::Timeout { .. }) => Err(error),
+        Err(error) => {
+            tracing::warn!(?error, "modern ping failed, falling back to legacy ping");
+            legacy::fetch_server_info(addr, handshake, timeouts)
+                .await
+                .map_err(|legacy| Error::Fallback {
+                    modern: Box::new(error),
+                    legacy: Box::new(legacy),
+                })
+        }
+    }
+}
 
-    tracing::info!("requesting status");
-    Packet::status_request().write(&mut socket).await?;
+async fn fetch_modern_server_info(
+    socket: TcpStream,
+    handshake: &HandshakeOptions,
+    timeouts: &Timeouts,
+) -> Result<ServerInfo> {
+    let mut connection = Connection::client(socket);
+    timeout("handshake", timeouts.handshake, async {
+        tracing::info!("sending handshake");
+        connection
+            .write(&Packet::handshake(handshake).await)
+            .await?;
+        connection.set_state(handshake.intent.state());
+
+        tracing::info!("requesting status");
+        connection.write(&Packet::status_request()).await
+    })
+    .await?;
 
     tracing::info!("reading status");
-    let response = Packet::read(&mut socket).await?;
+    let response = timeout("reading status", timeouts.read, connection.read()).await?;
+
+    if response.id() != 0x00.into() {
+        return Err(Error::Protocol(format!(
+            "expected status response packet, got id {:?}",
+            response.id()
+        )));
+    }
+    let StatusResponse { json } = response.decode_as().await?;
+
+    tracing::debug!(%json, "text");
+
+    let status = serde_json::from_str(&json)?;
 
-    let text = response.reader().next::<types::String>().await?;
+    let latency = ping(&mut connection, timeouts).await?;
+
+    Ok(ServerInfo { status, latency })
+}
+
+async fn ping(connection: &mut Connection<TcpStream>, timeouts: &Timeouts) -> Result<Duration> {
+    let payload = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as i64);
+
+    tracing::info!(%payload, "sending ping");
+    let start = Instant::now();
+    timeout(
+        "sending ping",
+        timeouts.handshake,
+        connection.write(&Packet::ping_request(payload)),
+    )
+    .await?;
+
+    tracing::info!("reading pong");
+    let pong = timeout("reading pong", timeouts.read, connection.read()).await?;
+    let latency = start.elapsed();
+
+    if pong.id() != 0x01.into() {
+        return Err(Error::Protocol(format!(
+            "expected pong packet, got id {:?}",
+            pong.id()
+        )));
+    }
+    let echoed = pong.reader().next::<i64>().await?;
+    if echoed != payload {
+        return Err(Error::Protocol(format!(
+            "pong payload mismatch: sent {payload}, got {echoed}"
+        )));
+    }
+
+    Ok(latency)
+}
 
-    tracing::debug!(%text, "text");
+#[cfg(test)]
+mod test {
+    use super::*;
 
-    Ok(serde_json::from_str(&text)?)
+    #[test]
+    fn split_addresses() {
+        assert_eq!(split_host_port("example.com"), ("example.com", None));
+        assert_eq!(
+            split_host_port("example.com:25566"),
+            ("example.com", Some(25566))
+        );
+        assert_eq!(split_host_port("127.0.0.1"), ("127.0.0.1", None));
+        assert_eq!(split_host_port("127.0.0.1:1234"), ("127.0.0.1", Some(1234)));
+        assert_eq!(split_host_port("::1"), ("::1", None));
+        assert_eq!(split_host_port("[::1]:1234"), ("::1", Some(1234)));
+    }
 }
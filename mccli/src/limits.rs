@@ -0,0 +1,81 @@
+//! How much a peer can make us allocate.
+//!
+//! The codec doesn't take the limits as arguments, it reads them from the task it runs in, so
+//! they reach every nested [`McType`](crate::types::McType). Outside of [`Limits::scope`] the
+//! protocol's own maximums apply.
+
+/// The largest frames and strings the codec accepts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    /// Bytes in a packet as sent, i.e. compressed if compression is on.
+    pub max_packet: usize,
+    /// Bytes in a compressed packet once inflated.
+    pub max_uncompressed: usize,
+    /// Characters, as UTF-16 code units, in a string.
+    pub max_string: usize,
+}
+
+impl Limits {
+    /// The largest packet whose length fits in the 3 bytes the vanilla server allows.
+    pub const PROTOCOL_MAX_PACKET: usize = (1 << 21) - 1;
+    /// What the vanilla server inflates packets up to.
+    pub const PROTOCOL_MAX_UNCOMPRESSED: usize = 1 << 23;
+    /// The longest string in the protocol, the json of a chat message. Most strings are capped
+    /// lower, at 32767 characters or less.
+    pub const PROTOCOL_MAX_STRING: usize = 262144;
+
+    /// The limits of the current task.
+    pub fn current() -> Self {
+        LIMITS.try_with(|limits| *limits).unwrap_or_default()
+    }
+
+    /// Runs `f` with these limits.
+    pub async fn scope<F: Future>(self, f: F) -> F::Output {
+        LIMITS.scope(self, f).await
+    }
+
+    /// Runs `f` with these limits, for the blocking codec.
+    pub fn sync_scope<R>(self, f: impl FnOnce() -> R) -> R {
+        LIMITS.sync_scope(self, f)
+    }
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_packet: Self::PROTOCOL_MAX_PACKET,
+            max_uncompressed: Self::PROTOCOL_MAX_UNCOMPRESSED,
+            max_string: Self::PROTOCOL_MAX_STRING,
+        }
+    }
+}
+
+tokio::task_local! {
+    static LIMITS: Limits;
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::packet::Packet;
+
+    #[tokio::test]
+    async fn scoped_limits_apply_to_the_codec() {
+        let limits = Limits {
+            max_packet: 4,
+            ..Limits::default()
+        };
+        assert_eq!(Limits::current(), Limits::default());
+        assert_eq!(limits.scope(async { Limits::current() }).await, limits);
+        assert_eq!(limits.sync_scope(Limits::current), limits);
+
+        let mut buffer = Vec::new();
+        Packet::ping_request(42).write(&mut buffer).await.unwrap();
+        assert!(Packet::read(&buffer[..]).await.is_ok());
+        let error = limits.scope(Packet::read(&buffer[..])).await.unwrap_err();
+        assert_eq!(
+            crate::Error::from(error).to_string(),
+            "packet of 9 bytes is over the limit of 4"
+        );
+    }
+}
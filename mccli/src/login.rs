@@ -0,0 +1,265 @@
+//! Joining offline-mode servers.
+
+use crate::{
+    Error, HandshakeOptions, Intent, Result, Timeouts,
+    packet::{
+        Packet,
+        connection::Connection,
+        login::{LoginAcknowledged, LoginPluginResponse, LoginStart, LoginSuccess},
+        registry::{Decoded, State},
+    },
+    types::{Uuid, text::TextComponent},
+};
+use md5::{Digest as _, Md5};
+use std::net::SocketAddr;
+use tokio::net::TcpStream;
+
+/// A connection that finished logging in and is now in the Configuration state.
+#[derive(Debug)]
+pub struct Session {
+    pub connection: Connection<TcpStream>,
+    pub profile: LoginSuccess,
+}
+
+/// The uuid offline-mode servers give to `username`: a version 3 uuid of
+/// `OfflinePlayer:<username>`.
+pub fn offline_uuid(username: &str) -> Uuid {
+    let mut bytes: [u8; 16] = Md5::digest(format!("OfflinePlayer:{username}")).into();
+    bytes[6] = bytes[6] & 0x0f | 0x30;
+    bytes[8] = bytes[8] & 0x3f | 0x80;
+    Uuid(u128::from_be_bytes(bytes))
+}
+
+/// Logs in to the offline-mode server at `addr` as `username`.
+pub async fn login(addr: SocketAddr, username: &str) -> Result<Session> {
+    login_with(
+        addr,
+        &HandshakeOptions::new(addr.ip().to_string(), addr.port()),
+        username,
+    )
+    .await
+}
+
+/// Logs in to the offline-mode server at `addr` as `username`, sending `handshake` with the
+/// login intent, with the default [`Timeouts`].
+pub async fn login_with(
+    addr: SocketAddr,
+    handshake: &HandshakeOptions,
+    username: &str,
+) -> Result<Session> {
+    login_with_timeouts(addr, handshake, username, &Timeouts::default()).await
+}
+
+/// Like [`login_with`] but giving up on each phase after the given timeouts.
+pub async fn login_with_timeouts(
+    addr: SocketAddr,
+    handshake: &HandshakeOptions,
+    username: &str,
+    timeouts: &Timeouts,
+) -> Result<Session> {
+    tracing::info!("connecting to: {addr}");
+    let socket = crate::connect(addr, timeouts.connect).await?;
+    let mut connection = Connection::client(socket);
+
+    let handshake = HandshakeOptions {
+        intent: Intent::Login,
+        ..handshake.clone()
+    };
+    crate::timeout("handshake", timeouts.handshake, async {
+        tracing::info!("sending handshake");
+        connection
+            .write(&Packet::handshake(&handshake).await)
+            .await?;
+        connection.set_state(State::Login);
+
+        tracing::info!(%username, "sending login start");
+        connection
+            .send(&LoginStart {
+                name: username.to_owned().into(),
+                uuid: offline_uuid(username),
+            })
+            .await
+    })
+    .await?;
+
+    loop {
+        let packet = crate::timeout(
+            "reading login response",
+            timeouts.read,
+            connection.read_decoded(),
+        )
+        .await?;
+        match packet {
+            Decoded::SetCompression(s) => connection.set_compression(s.threshold.into()),
+            Decoded::LoginPluginRequest(request) => {
+                tracing::debug!(channel = %request.channel, "declining login plugin request");
+                crate::timeout(
+                    "sending login plugin response",
+                    timeouts.handshake,
+                    connection.send(&LoginPluginResponse {
+                        message_id: request.message_id,
+                        successful: false,
+                        data: Vec::new(),
+                    }),
+                )
+                .await?;
+            }
+            Decoded::LoginSuccess(profile) => {
+                tracing::info!(uuid = %profile.uuid, "logged in");
+                crate::timeout(
+                    "acknowledging login",
+                    timeouts.handshake,
+                    connection.send(&LoginAcknowledged),
+                )
+                .await?;
+                connection.set_state(State::Configuration);
+                return Ok(Session {
+                    connection,
+                    profile,
+                });
+            }
+            Decoded::LoginDisconnect(d) => match serde_json::from_str::<TextComponent>(&d.reason) {
+                Ok(reason) => return Err(Error::Disconnected(reason.to_string())),
+                Err(_) => return Err(Error::Disconnected(d.reason.to_string())),
+            },
+            Decoded::EncryptionRequest(_) => return Err(Error::OnlineMode),
+            other => {
+                return Err(Error::Protocol(format!(
+                    "unexpected packet during login: {other:?}"
+                )));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::packet::login::{EncryptionRequest, LoginPluginRequest, Property, SetCompression};
+    use std::time::Duration;
+    use tokio::net::TcpListener;
+
+    #[test]
+    fn offline_uuids() {
+        assert_eq!(
+            offline_uuid("Notch").to_string(),
+            "b50ad385-829d-3141-a216-7e7d7539ba7f"
+        );
+    }
+
+    #[tokio::test]
+    async fn login_reaches_configuration() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let server = tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            let mut server = Connection::server(socket);
+
+            let Decoded::Handshake(handshake) = server.read_decoded().await.unwrap() else {
+                panic!("expected a handshake");
+            };
+            assert_eq!(handshake.intent, Intent::Login);
+            server.set_state(State::Login);
+
+            let Decoded::LoginStart(start) = server.read_decoded().await.unwrap() else {
+                panic!("expected login start");
+            };
+            assert_eq!(&*start.name, "steve");
+            assert_eq!(start.uuid, offline_uuid("steve"));
+
+            server
+                .send(&SetCompression {
+                    threshold: 16.into(),
+                })
+                .await
+                .unwrap();
+            server.set_compression(16);
+
+            server
+                .send(&LoginPluginRequest {
+                    message_id: 7.into(),
+                    channel: "velocity:player_info".to_owned().into(),
+                    data: vec![1],
+                })
+                .await
+                .unwrap();
+            let Decoded::LoginPluginResponse(response) = server.read_decoded().await.unwrap()
+            else {
+                panic!("expected a login plugin response");
+            };
+            assert_eq!(response.message_id, 7.into());
+            assert!(!response.successful);
+
+            server
+                .send(&LoginSuccess {
+                    uuid: start.uuid,
+                    username: start.name,
+                    properties: vec![Property {
+                        name: "textures".to_owned().into(),
+                        value: "e30=".to_owned().into(),
+                        signature: None,
+                    }],
+                })
+                .await
+                .unwrap();
+            assert!(matches!(
+                server.read_decoded().await.unwrap(),
+                Decoded::LoginAcknowledged(_)
+            ));
+        });
+
+        let session = login(addr, "steve").await.unwrap();
+        server.await.unwrap();
+        assert_eq!(session.connection.state(), State::Configuration);
+        assert_eq!(session.connection.compression_threshold(), Some(16));
+        assert_eq!(&*session.profile.username, "steve");
+    }
+
+    #[tokio::test]
+    async fn online_mode_is_reported() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            let mut server = Connection::server(socket);
+            server.read().await.unwrap();
+            server.set_state(State::Login);
+            server.read().await.unwrap();
+            server
+                .send(&EncryptionRequest {
+                    server_id: String::new().into(),
+                    public_key: vec![],
+                    verify_token: vec![],
+                    should_authenticate: true,
+                })
+                .await
+                .unwrap();
+        });
+
+        let error = login(addr, "steve").await.unwrap_err();
+        assert!(error.to_string().contains("online mode"), "{error}");
+    }
+
+    #[tokio::test]
+    async fn silent_servers_time_out() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        tokio::spawn(async move {
+            let (_socket, _) = listener.accept().await.unwrap();
+            std::future::pending::<()>().await;
+        });
+
+        let timeouts = Timeouts {
+            read: Duration::from_millis(50),
+            ..Timeouts::default()
+        };
+        let handshake = HandshakeOptions::new("localhost", addr.port());
+        let error = login_with_timeouts(addr, &handshake, "steve", &timeouts)
+            .await
+            .unwrap_err();
+        assert!(matches!(error, Error::Timeout { .. }), "{error:?}");
+    }
+}
@@ -1,15 +1,30 @@
-mod packet;
+mod cli;
 
-use anyhow::Context;
-use clap::Parser;
-use mccli::fetch_server_info;
-use mccli::types;
-use std::net::ToSocketAddrs;
+use clap::{Parser, Subcommand};
 use tracing_subscriber::{EnvFilter, layer::SubscriberExt as _, util::SubscriberInitExt as _};
 
+/// Queries minecraft servers, by default the status of the server at ADDR.
 #[derive(Parser)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 struct Args {
-    addr: String,
+    #[command(subcommand)]
+    command: Option<Command>,
+    #[command(flatten)]
+    query: cli::query::QueryArgs,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Answers status requests like a server, e.g. as a placeholder during maintenance
+    Serve(cli::serve::ServeArgs),
+    /// Queries many servers at once, printing one line per server as soon as it answers
+    Scan(cli::scan::ScanArgs),
+    /// Queries a server over and over, printing what changed
+    Watch(cli::watch::WatchArgs),
+    /// Forwards connections to an offline-mode server, printing the packets they exchange
+    Proxy(cli::proxy::ProxyArgs),
+    /// Prints the packets in a capture made with `--record`, or plays it back to clients
+    Replay(cli::replay::ReplayArgs),
 }
 
 #[tokio::main(flavor = "current_thread")]
@@ -19,44 +34,13 @@ async fn main() -> anyhow::Result<()> {
         .with(EnvFilter::from_default_env())
         .init();
 
-    let Args { addr } = Args::parse();
-
-    let info = fetch_server_info(
-        addr.to_socket_addrs()
-            .or_else(|_| format!("{addr}:25565").to_socket_addrs())
-            .context("getting socket address")?
-            .next()
-            .unwrap(),
-    )
-    .await?;
-    println!("Server is online:");
-    println!("Version: {}", info.version.name);
-    println!("Players: {}/{}", info.players.online, info.players.max);
-    for p in info.players.sample {
-        println!("  - {}", p.name);
-    }
-    if let Some(modinfo) = info.modinfo {
-        println!("mod type: {}", modinfo.r#type);
-        if !modinfo.mod_list.is_empty() {
-            println!("Mod list:");
-            for m in modinfo.mod_list {
-                println!("  - {}", m)
-            }
-        }
-    }
-    println!("Description:");
-    match info.description {
-        types::server::Description::Text(t) => println!("{t}"),
-        types::server::Description::Colored(colored_text) => {
-            fn print_color_text(c: types::server::ColoredText) {
-                print!("{}", c.text);
-                for e in c.extra {
-                    print_color_text(e)
-                }
-            }
-            print_color_text(colored_text);
-            println!();
-        }
+    let Args { command, query } = Args::parse();
+    match command {
+        None => cli::query::run(query).await,
+        Some(Command::Serve(args)) => cli::serve::run(args).await,
+        Some(Command::Scan(args)) => cli::scan::run(args).await,
+        Some(Command::Watch(args)) => cli::watch::run(args).await,
+        Some(Command::Proxy(args)) => cli::proxy::run(args).await,
+        Some(Command::Replay(args)) => cli::replay::run(args).await,
     }
-    Ok(())
 }
@@ -0,0 +1,566 @@
+//! Recording packets to `.mcap` files and playing them back, e.g. to attach a capture of a
+//! misbehaving server to a bug report.
+//!
+//! A capture is [`MAGIC`], the format [`VERSION`] as a byte, then a [`Record`] per packet until
+//! the end of the file. Records of different connections, e.g. a client's ping from the server
+//! list and its login, are told apart by their [connection id](Record::connection). Packets are recorded decompressed and decrypted, as the
+//! [registry](super::registry) sees them, so a capture can be decoded without knowing when
+//! compression was turned on. Frames that couldn't be read are recorded as they were received.
+//!
+//! [`Connection`]s record to the [`Recorder`] of the task they were created in, each under a
+//! connection id of its own, see [`Recorder::scope`].
+
+use super::{
+    Packet,
+    connection::Connection,
+    registry::{Decoded, Direction, State, TypedPacket as _},
+    status::PingRequest,
+    types::{McType, String, VarInt},
+};
+use std::{
+    borrow::Cow,
+    fmt,
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::Path,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU32, Ordering},
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt as _};
+
+pub const MAGIC: &[u8; 4] = b"MCAP";
+pub const VERSION: u8 = 3;
+
+/// A packet as it was sent or received.
+#[derive(Debug, Clone, PartialEq, Eq, McType)]
+pub struct Record {
+    /// Microseconds since the Unix epoch.
+    pub micros: i64,
+    /// The connection the packet was sent on, see [`Recorder::connection`].
+    pub connection: u32,
+    pub direction: Direction,
+    pub state: State,
+    pub packet_id: VarInt,
+    #[mc(length_prefixed)]
+    pub payload: Vec<u8>,
+    /// Why the frame couldn't be read, for invalid frames. Their `packet_id` is 0 and their
+    /// `payload` the bytes that were read, length prefix included and still compressed.
+    #[mc(optional, owned)]
+    pub error: Option<String<'static>>,
+}
+
+impl Record {
+    pub fn new(connection: u32, direction: Direction, state: State, packet: &Packet<'_>) -> Self {
+        Self {
+            micros: now(),
+            connection,
+            direction,
+            state,
+            packet_id: packet.id(),
+            payload: packet.payload.to_vec(),
+            error: None,
+        }
+    }
+
+    /// A frame that failed with `error` after `bytes` of it were read.
+    pub fn invalid(
+        connection: u32,
+        direction: Direction,
+        state: State,
+        bytes: Vec<u8>,
+        error: &io::Error,
+    ) -> Self {
+        Self {
+            micros: now(),
+            connection,
+            direction,
+            state,
+            packet_id: 0.into(),
+            payload: bytes,
+            error: Some(error.to_string().into()),
+        }
+    }
+
+    pub fn time(&self) -> SystemTime {
+        UNIX_EPOCH + Duration::from_micros(self.micros.max(0) as u64)
+    }
+
+    pub fn packet(&self) -> Packet<'_> {
+        Packet {
+            packet_id: self.packet_id,
+            payload: Cow::Borrowed(&self.payload),
+        }
+    }
+
+    /// Decodes the packet according to the state and direction it was recorded in.
+    pub async fn decode(&self) -> io::Result<Decoded<'_>> {
+        if let Some(error) = &self.error {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("invalid frame: {error}"),
+            ));
+        }
+        self.packet().decode(self.state, self.direction).await
+    }
+}
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_micros() as i64)
+}
+
+/// Writes records of a connection to a capture. Clones write to the same capture, under the
+/// same connection id.
+///
+/// If a record can't be written, e.g. because the disk is full, the recorder logs a warning and
+/// stops recording, as the capture can't be read past a partial record anyway.
+#[derive(Clone)]
+pub struct Recorder {
+    capture: Arc<Capture>,
+    connection: u32,
+}
+
+/// The capture recorders write to.
+struct Capture {
+    w: Mutex<Option<Box<dyn Write + Send>>>,
+    next_connection: AtomicU32,
+}
+
+impl Recorder {
+    /// Starts a capture in `w`, writing the header right away. Records made with this recorder
+    /// are of connection 0.
+    pub fn new(mut w: impl Write + Send + 'static) -> io::Result<Self> {
+        w.write_all(MAGIC)?;
+        w.write_all(&[VERSION])?;
+        Ok(Self {
+            capture: Arc::new(Capture {
+                w: Mutex::new(Some(Box::new(w))),
+                next_connection: AtomicU32::new(1),
+            }),
+            connection: 0,
+        })
+    }
+
+    /// A recorder writing to the same capture under a new connection id.
+    pub fn connection(&self) -> Self {
+        Self {
+            capture: self.capture.clone(),
+            connection: self.capture.next_connection.fetch_add(1, Ordering::Relaxed),
+        }
+    }
+
+    /// Starts a capture in a new file at `path`.
+    pub fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::new(BufWriter::new(File::create(path)?))
+    }
+
+    pub fn record(&self, direction: Direction, state: State, packet: &Packet<'_>) {
+        tracing::trace!(?direction, ?state, id = ?packet.id(), "recording packet");
+        self.write(&Record::new(self.connection, direction, state, packet));
+    }
+
+    /// Records a frame that failed with `error` after `bytes` of it were read.
+    pub fn record_invalid(
+        &self,
+        direction: Direction,
+        state: State,
+        bytes: Vec<u8>,
+        error: &io::Error,
+    ) {
+        tracing::trace!(?direction, ?state, %error, "recording invalid frame");
+        self.write(&Record::invalid(
+            self.connection,
+            direction,
+            state,
+            bytes,
+            error,
+        ));
+    }
+
+    fn write(&self, record: &Record) {
+        let mut w = self.capture.w.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(writer) = &mut *w
+            && let Err(error) = record.write_blocking(writer)
+        {
+            tracing::warn!(
+                ?error,
+                "failed to write the capture, not recording any more"
+            );
+            *w = None;
+        }
+    }
+
+    /// Whether records are still written, which they aren't after one failed.
+    pub fn is_recording(&self) -> bool {
+        self.capture
+            .w
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .is_some()
+    }
+
+    pub fn flush(&self) -> io::Result<()> {
+        match &mut *self.capture.w.lock().unwrap_or_else(|e| e.into_inner()) {
+            Some(w) => w.flush(),
+            None => Ok(()),
+        }
+    }
+
+    /// The recorder of the current task, if there is one.
+    pub fn current() -> Option<Self> {
+        RECORDER.try_with(Self::clone).ok()
+    }
+
+    /// Runs `f` with every [`Connection`] it creates recording to this capture, each under a
+    /// [new connection id](Recorder::connection).
+    pub async fn scope<F: Future>(self, f: F) -> F::Output {
+        RECORDER.scope(self, f).await
+    }
+}
+
+impl fmt::Debug for Recorder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Recorder")
+            .field("connection", &self.connection)
+            .finish_non_exhaustive()
+    }
+}
+
+tokio::task_local! {
+    static RECORDER: Recorder;
+}
+
+/// Reads the records of a capture.
+#[derive(Debug)]
+pub struct Reader<R> {
+    r: R,
+}
+
+impl<R: BufRead + Send> Reader<R> {
+    /// Checks the header of the capture in `r`.
+    pub fn new(mut r: R) -> io::Result<Self> {
+        let mut header = [0; 5];
+        r.read_exact(&mut header)?;
+        if header[..4] != MAGIC[..] {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not a packet capture",
+            ));
+        }
+        if header[4] != VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unsupported capture version {}", header[4]),
+            ));
+        }
+        Ok(Self { r })
+    }
+}
+
+impl Reader<BufReader<File>> {
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::new(BufReader::new(File::open(path)?))
+    }
+}
+
+impl<R: BufRead + Send> Iterator for Reader<R> {
+    type Item = io::Result<Record>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.r.fill_buf() {
+            Ok([]) => None,
+            Ok(_) => Some(Record::read_blocking(&mut self.r)),
+            Err(error) => Some(Err(error)),
+        }
+    }
+}
+
+/// Splits `records` by [connection](Record::connection), in the order the connections first
+/// show up.
+pub fn connections(records: impl IntoIterator<Item = Record>) -> Vec<Vec<Record>> {
+    let mut connections = Vec::<Vec<Record>>::new();
+    for record in records {
+        match connections
+            .iter_mut()
+            .find(|c| c[0].connection == record.connection)
+        {
+            Some(connection) => connection.push(record),
+            None => connections.push(vec![record]),
+        }
+    }
+    connections
+}
+
+/// Plays `records` back over `connection`, writing the ones going out of this end and reading
+/// the ones it expects in return. Only the records of the connection of the first record are
+/// played, see [`connections`] to replay the others.
+///
+/// What the peer actually sends is only checked to have the recorded packet id. Pongs echo the
+/// payload of the ping they answer rather than the recorded one, and Set Compression turns on
+/// compression, so that the peer can follow along. Invalid frames going out are sent as they
+/// were received, and the replay stops at the first one expected in return, as the recorded
+/// connection couldn't go on after it either.
+pub async fn replay<'r, S: AsyncRead + AsyncWrite + Unpin + Send>(
+    connection: &mut Connection<S>,
+    records: impl IntoIterator<Item = &'r Record>,
+) -> io::Result<()> {
+    let mut records = records.into_iter().peekable();
+    let id = records.peek().map(|r| r.connection);
+    let mut ping = None;
+    for record in records.filter(|r| Some(r.connection) == id) {
+        connection.set_state(record.state);
+        if record.error.is_some() {
+            if record.direction == connection.incoming() {
+                break;
+            }
+            // encryption is never turned on by a replay, so writing to the stream is fine
+            connection.get_mut().write_all(&record.payload).await?;
+            continue;
+        }
+        if record.direction == connection.incoming() {
+            let packet = connection.read().await?;
+            if packet.id() != record.packet_id {
+                tracing::warn!(
+                    expected = ?record.packet_id,
+                    got = ?packet.id(),
+                    state = ?record.state,
+                    "the peer diverged from the capture",
+                );
+            }
+            let is_ping =
+                record.state == PingRequest::STATE && packet.id() == PingRequest::ID.into();
+            ping = is_ping.then_some(packet);
+            continue;
+        }
+        let mut packet = record.packet();
+        match record.decode().await {
+            Ok(Decoded::PongResponse(_)) => {
+                if let Some(ping) = ping.take() {
+                    packet.payload = ping.payload;
+                }
+                connection.write(&packet).await?;
+            }
+            Ok(Decoded::SetCompression(s)) => {
+                connection.write(&packet).await?;
+                connection.set_compression(s.threshold.into());
+            }
+            _ => connection.write(&packet).await?,
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        packet::{
+            login::SetCompression,
+            status::{PongResponse, StatusRequest, StatusResponse},
+        },
+        testing::SharedBuffer,
+    };
+    use tokio::io::duplex;
+
+    #[tokio::test]
+    async fn connections_record_to_the_scoped_recorder() {
+        let capture = SharedBuffer::default();
+        let recorder = Recorder::new(capture.clone()).unwrap();
+        let (client, server) = duplex(1 << 16);
+        let mut server = Connection::server(server);
+        let mut client = recorder.scope(async { Connection::client(client) }).await;
+        client.set_state(State::Status);
+        server.set_state(State::Status);
+
+        client.send(&StatusRequest).await.unwrap();
+        server.read().await.unwrap();
+        let response = StatusResponse {
+            json: std::string::String::from("{}").into(),
+        };
+        server.send(&response).await.unwrap();
+        client.read().await.unwrap();
+
+        let bytes = capture.contents();
+        let records = Reader::new(&bytes[..])
+            .unwrap()
+            .collect::<io::Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(records.len(), 2, "only the client records");
+        assert_eq!(records[0].direction, Direction::Serverbound);
+        assert!(matches!(
+            records[0].decode().await.unwrap(),
+            Decoded::StatusRequest(_)
+        ));
+        assert_eq!(records[1].direction, Direction::Clientbound);
+        assert_eq!(records[1].state, State::Status);
+        match records[1].decode().await.unwrap() {
+            Decoded::StatusResponse(r) => assert_eq!(r, response),
+            other => panic!("unexpected packet {other:?}"),
+        }
+        assert!(records[0].micros <= records[1].micros);
+        assert_eq!(records[0].connection, records[1].connection);
+    }
+
+    #[tokio::test]
+    async fn connections_are_told_apart() {
+        let capture = SharedBuffer::default();
+        let recorder = Recorder::new(capture.clone()).unwrap();
+        recorder
+            .scope(async {
+                for _ in 0..2 {
+                    let (client, _server) = duplex(1 << 16);
+                    let mut client = Connection::client(client);
+                    client.set_state(State::Status);
+                    client.send(&StatusRequest).await.unwrap();
+                }
+            })
+            .await;
+
+        let bytes = capture.contents();
+        let records = Reader::new(&bytes[..])
+            .unwrap()
+            .collect::<io::Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(records.len(), 2);
+        assert_ne!(records[0].connection, records[1].connection);
+        let connections = connections(records.clone());
+        assert_eq!(connections, [[records[0].clone()], [records[1].clone()]]);
+    }
+
+    #[tokio::test]
+    async fn invalid_frames_are_recorded() {
+        let capture = SharedBuffer::default();
+        let (mut server, client) = duplex(1 << 16);
+        let mut client = Connection::client(client);
+        client.record_to(Recorder::new(capture.clone()).unwrap());
+        client.set_state(State::Status);
+
+        // a frame of -1 bytes
+        server
+            .write_all(&[0xff, 0xff, 0xff, 0xff, 0x0f])
+            .await
+            .unwrap();
+        client.read().await.unwrap_err();
+        // closing between packets isn't recorded
+        drop(server);
+        client.read().await.unwrap_err();
+
+        let bytes = capture.contents();
+        let records = Reader::new(&bytes[..])
+            .unwrap()
+            .collect::<io::Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].payload, [0xff, 0xff, 0xff, 0xff, 0x0f]);
+        assert!(records[0].error.is_some());
+        assert!(records[0].decode().await.is_err());
+    }
+
+    #[test]
+    fn failing_captures_stop_recording() {
+        // room for the header and nothing else
+        let recorder = Recorder::new(io::Cursor::new([0; 5])).unwrap();
+        assert!(recorder.is_recording());
+        recorder.record(
+            Direction::Serverbound,
+            State::Status,
+            &Packet::status_request(),
+        );
+        assert!(!recorder.is_recording());
+        recorder.flush().unwrap();
+    }
+
+    #[test]
+    fn captures_are_checked() {
+        let error = Reader::new(&b"PCAP\x01"[..]).unwrap_err();
+        assert_eq!(error.to_string(), "not a packet capture");
+        let error = Reader::new(&b"MCAP\x09"[..]).unwrap_err();
+        assert_eq!(error.to_string(), "unsupported capture version 9");
+
+        let mut truncated = b"MCAP\x03".to_vec();
+        let record = Record::new(
+            0,
+            Direction::Clientbound,
+            State::Status,
+            &Packet::status_request(),
+        );
+        record.write_blocking(&mut truncated).unwrap();
+        truncated.pop();
+        let mut reader = Reader::new(&truncated[..]).unwrap();
+        assert!(reader.next().unwrap().is_err());
+    }
+
+    #[tokio::test]
+    async fn replays_follow_the_peer() {
+        let record =
+            |direction, state, packet: Packet<'_>| Record::new(1, direction, state, &packet);
+        let records = [
+            record(
+                Direction::Clientbound,
+                State::Login,
+                Packet::encode(&SetCompression {
+                    threshold: 0.into(),
+                })
+                .await
+                .unwrap(),
+            ),
+            record(
+                Direction::Serverbound,
+                State::Status,
+                Packet::encode(&PingRequest { payload: 1 }).await.unwrap(),
+            ),
+            record(
+                Direction::Clientbound,
+                State::Status,
+                Packet::encode(&PongResponse { payload: 1 }).await.unwrap(),
+            ),
+            // another connection, which isn't replayed
+            Record::new(
+                2,
+                Direction::Clientbound,
+                State::Status,
+                &Packet::encode(&PongResponse { payload: 2 }).await.unwrap(),
+            ),
+            Record::invalid(
+                1,
+                Direction::Clientbound,
+                State::Status,
+                vec![0x03, 0x80, 0x80, 0x80],
+                &io::ErrorKind::UnexpectedEof.into(),
+            ),
+            // ends the replay
+            Record::invalid(
+                1,
+                Direction::Serverbound,
+                State::Status,
+                Vec::new(),
+                &io::ErrorKind::UnexpectedEof.into(),
+            ),
+        ];
+
+        let (client, server) = duplex(1 << 16);
+        let replay = tokio::spawn(async move {
+            let mut server = Connection::server(server);
+            replay(&mut server, &records).await
+        });
+
+        let mut client = Connection::client(client);
+        client.set_state(State::Login);
+        match client.read_decoded().await.unwrap() {
+            Decoded::SetCompression(s) => client.set_compression(s.threshold.into()),
+            other => panic!("unexpected packet {other:?}"),
+        }
+        client.set_state(State::Status);
+        client.send(&PingRequest { payload: 42 }).await.unwrap();
+        match client.read_decoded().await.unwrap() {
+            Decoded::PongResponse(pong) => assert_eq!(pong.payload, 42),
+            other => panic!("unexpected packet {other:?}"),
+        }
+        client.read().await.unwrap_err();
+        replay.await.unwrap().unwrap();
+    }
+}
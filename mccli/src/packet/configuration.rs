@@ -0,0 +1,48 @@
+use super::types::{McType, String, text::TextComponent};
+
+#[derive(Debug, Clone, PartialEq, Eq, McType)]
+pub struct ClientboundPluginMessage {
+    #[mc(owned, max_len = 32767)]
+    pub channel: String<'static>,
+    #[mc(rest)]
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, McType)]
+pub struct Disconnect {
+    pub reason: TextComponent,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, McType)]
+pub struct FinishConfiguration;
+
+#[derive(Debug, Clone, PartialEq, Eq, McType)]
+pub struct ClientboundKeepAlive {
+    pub id: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, McType)]
+pub struct Ping {
+    pub id: i32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, McType)]
+pub struct ServerboundPluginMessage {
+    #[mc(owned, max_len = 32767)]
+    pub channel: String<'static>,
+    #[mc(rest)]
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, McType)]
+pub struct AcknowledgeFinishConfiguration;
+
+#[derive(Debug, Clone, PartialEq, Eq, McType)]
+pub struct ServerboundKeepAlive {
+    pub id: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, McType)]
+pub struct Pong {
+    pub id: i32,
+}
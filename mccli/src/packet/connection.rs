@@ -0,0 +1,274 @@
+use super::{
+    Packet,
+    capture::Recorder,
+    encryption::{EncryptedStream, SharedSecret},
+    registry::{Decoded, Direction, State, TypedPacket},
+};
+use std::{
+    io,
+    pin::Pin,
+    task::{Context, Poll, ready},
+};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+/// A stream of packets, keeping track of the connection's state, compression and encryption.
+#[derive(Debug)]
+pub struct Connection<S> {
+    stream: EncryptedStream<S>,
+    state: State,
+    /// The direction of the packets this end receives.
+    incoming: Direction,
+    compression_threshold: Option<usize>,
+    recorder: Option<Recorder>,
+}
+
+impl<S> Connection<S> {
+    /// The client end of a connection, which receives clientbound packets.
+    pub fn client(stream: S) -> Self {
+        Self {
+            stream: EncryptedStream::plain(stream),
+            state: State::Handshaking,
+            incoming: Direction::Clientbound,
+            compression_threshold: None,
+            recorder: Recorder::current().as_ref().map(Recorder::connection),
+        }
+    }
+
+    /// The server end of a connection, which receives serverbound packets.
+    ///
+    /// Both ends record to the [`Recorder`] of the task they're created in, if any, each under a
+    /// new connection id.
+    pub fn server(stream: S) -> Self {
+        Self {
+            incoming: Direction::Serverbound,
+            ..Self::client(stream)
+        }
+    }
+
+    /// The direction of the packets this end receives.
+    pub fn incoming(&self) -> Direction {
+        self.incoming
+    }
+
+    /// Records every packet read or written from now on to `recorder`, under its connection id.
+    pub fn record_to(&mut self, recorder: Recorder) {
+        self.recorder = Some(recorder);
+    }
+
+    pub fn state(&self) -> State {
+        self.state
+    }
+
+    pub fn set_state(&mut self, state: State) {
+        tracing::debug!(from = ?self.state, to = ?state, "switching state");
+        self.state = state;
+    }
+
+    pub fn compression_threshold(&self) -> Option<usize> {
+        self.compression_threshold
+    }
+
+    /// Enables compression of packets of at least `threshold` bytes, as sent by Set Compression.
+    /// A negative threshold disables compression.
+    pub fn set_compression(&mut self, threshold: i32) {
+        tracing::debug!(%threshold, "setting compression threshold");
+        self.compression_threshold = usize::try_from(threshold).ok();
+    }
+
+    /// Encrypts the connection from now on, as done after Encryption Response.
+    pub fn enable_encryption(&mut self, secret: &SharedSecret) {
+        tracing::debug!("enabling encryption");
+        self.stream.enable(secret);
+    }
+
+    pub fn is_encrypted(&self) -> bool {
+        self.stream.is_encrypted()
+    }
+
+    pub fn get_ref(&self) -> &S {
+        self.stream.get_ref()
+    }
+
+    /// The underlying stream. Anything written to it directly bypasses encryption.
+    pub fn get_mut(&mut self) -> &mut S {
+        self.stream.get_mut()
+    }
+
+    pub fn into_inner(self) -> S {
+        self.stream.into_inner()
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin + Send> Connection<S> {
+    pub async fn read(&mut self) -> io::Result<Packet<'static>> {
+        let Some(recorder) = &self.recorder else {
+            return Packet::read_compressed(&mut self.stream, self.compression_threshold).await;
+        };
+        // keep what was read, to record it if it isn't a valid frame
+        let mut tee = Tee {
+            r: &mut self.stream,
+            read: Vec::new(),
+        };
+        match Packet::read_compressed(&mut tee, self.compression_threshold).await {
+            Ok(packet) => {
+                recorder.record(self.incoming, self.state, &packet);
+                Ok(packet)
+            }
+            // the connection was closed between packets
+            Err(error) if tee.read.is_empty() => Err(error),
+            Err(error) => {
+                recorder.record_invalid(self.incoming, self.state, tee.read, &error);
+                Err(error)
+            }
+        }
+    }
+
+    /// Reads a packet and decodes it according to the current state.
+    pub async fn read_decoded(&mut self) -> io::Result<Decoded<'static>> {
+        self.read().await?.decode(self.state, self.incoming).await
+    }
+
+    pub async fn write(&mut self, packet: &Packet<'_>) -> io::Result<()> {
+        if let Some(recorder) = &self.recorder {
+            recorder.record(self.incoming.opposite(), self.state, packet);
+        }
+        packet
+            .write_compressed(&mut self.stream, self.compression_threshold)
+            .await
+    }
+
+    pub async fn send<T: TypedPacket>(&mut self, body: &T) -> io::Result<()> {
+        debug_assert_eq!(
+            T::STATE,
+            self.state,
+            "sending {} in the wrong state",
+            T::NAME
+        );
+        self.write(&Packet::encode(body).await?).await
+    }
+}
+
+/// Keeps a copy of everything read through it.
+struct Tee<'r, R> {
+    r: &'r mut R,
+    read: Vec<u8>,
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for Tee<'_, R> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let start = buf.filled().len();
+        ready!(Pin::new(&mut *self.r).poll_read(cx, buf))?;
+        self.read.extend_from_slice(&buf.filled()[start..]);
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::packet::{
+        configuration, login,
+        types::{McType as _, VarInt},
+    };
+    use tokio::io::duplex;
+
+    fn plugin_message(len: usize) -> configuration::ClientboundPluginMessage {
+        configuration::ClientboundPluginMessage {
+            channel: std::string::String::from("minecraft:brand").into(),
+            data: (0..len).map(|i| i as u8).collect(),
+        }
+    }
+
+    async fn roundtrip(threshold: i32, len: usize) -> Vec<u8> {
+        let (client, server) = duplex(1 << 16);
+        let mut client = Connection::client(client);
+        let mut server = Connection::server(server);
+        for c in [&mut client, &mut server] {
+            c.set_state(State::Configuration);
+            c.set_compression(threshold);
+        }
+
+        let message = plugin_message(len);
+        server.send(&message).await.unwrap();
+        let mut raw = Vec::new();
+        Packet::encode(&message)
+            .await
+            .unwrap()
+            .write_compressed(&mut raw, server.compression_threshold())
+            .await
+            .unwrap();
+
+        match client.read_decoded().await.unwrap() {
+            Decoded::ConfigurationClientboundPluginMessage(m) => assert_eq!(m, message),
+            other => panic!("unexpected packet {other:?}"),
+        }
+        raw
+    }
+
+    #[tokio::test]
+    async fn below_threshold_is_not_compressed() {
+        let raw = roundtrip(256, 10).await;
+        // length, data length of 0, then the packet id in the clear
+        assert_eq!(raw[1..3], [0x00, 0x01]);
+        assert_eq!(usize::from(raw[0]), raw.len() - 1);
+    }
+
+    #[tokio::test]
+    async fn above_threshold_is_compressed() {
+        let raw = roundtrip(256, 4096).await;
+        let mut cursor = std::io::Cursor::new(&raw);
+        let length = usize::try_from(VarInt::read(&mut cursor).await.unwrap()).unwrap();
+        assert_eq!(length, raw.len() - cursor.position() as usize);
+        assert!(raw.len() < 4096, "{} bytes weren't compressed", raw.len());
+    }
+
+    #[tokio::test]
+    async fn disabled_compression_roundtrip() {
+        let raw = roundtrip(-1, 4096).await;
+        let mut uncompressed = Vec::new();
+        Packet::encode(&plugin_message(4096))
+            .await
+            .unwrap()
+            .write(&mut uncompressed)
+            .await
+            .unwrap();
+        assert_eq!(raw, uncompressed);
+    }
+
+    #[tokio::test]
+    async fn set_compression_is_registered() {
+        let (client, server) = duplex(64);
+        let mut client = Connection::client(client);
+        let mut server = Connection::server(server);
+        client.set_state(State::Login);
+        server.set_state(State::Login);
+        server
+            .send(&login::SetCompression {
+                threshold: 256.into(),
+            })
+            .await
+            .unwrap();
+        match client.read_decoded().await.unwrap() {
+            Decoded::SetCompression(s) => client.set_compression(s.threshold.into()),
+            other => panic!("unexpected packet {other:?}"),
+        }
+        assert_eq!(client.compression_threshold(), Some(256));
+    }
+
+    #[tokio::test]
+    async fn compressed_below_threshold_is_rejected() {
+        let mut raw = Vec::new();
+        Packet::encode(&plugin_message(300))
+            .await
+            .unwrap()
+            .write_compressed(&mut raw, Some(256))
+            .await
+            .unwrap();
+        assert!(Packet::read_compressed(&raw[..], Some(1024)).await.is_err());
+        assert!(Packet::read_compressed(&raw[..], Some(256)).await.is_ok());
+    }
+}
@@ -0,0 +1,350 @@
+//! The AES/CFB8 stream encryption used by online-mode servers after Encryption Response.
+
+use super::login::{EncryptionRequest, EncryptionResponse};
+use aes::{
+    Aes128,
+    cipher::{
+        BlockDecryptMut as _, BlockEncryptMut as _, KeyIvInit as _, generic_array::GenericArray,
+    },
+};
+use rand::{RngCore as _, rngs::OsRng};
+use rsa::{Pkcs1v15Encrypt, RsaPublicKey, pkcs8::DecodePublicKey as _};
+use sha1::{Digest as _, Sha1};
+use std::{
+    fmt, io,
+    pin::Pin,
+    task::{Context, Poll, ready},
+};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+pub type SharedSecret = [u8; 16];
+
+type Encryptor = cfb8::Encryptor<Aes128>;
+type Decryptor = cfb8::Decryptor<Aes128>;
+
+struct Ciphers {
+    encryptor: Encryptor,
+    decryptor: Decryptor,
+}
+
+/// A stream that encrypts everything written and decrypts everything read with AES-128-CFB8,
+/// using the shared secret as both key and IV.
+///
+/// Encryption starts disabled, so the same stream can be used from the start of the connection.
+pub struct EncryptedStream<S> {
+    inner: S,
+    ciphers: Option<Ciphers>,
+    /// Bytes that were already encrypted but not yet written to `inner`.
+    pending: Vec<u8>,
+}
+
+impl<S> fmt::Debug for EncryptedStream<S>
+where
+    S: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EncryptedStream")
+            .field("inner", &self.inner)
+            .field("encrypted", &self.is_encrypted())
+            .field("pending", &self.pending.len())
+            .finish()
+    }
+}
+
+impl<S> EncryptedStream<S> {
+    pub fn new(inner: S, secret: &SharedSecret) -> Self {
+        let mut this = Self::plain(inner);
+        this.enable(secret);
+        this
+    }
+
+    pub fn plain(inner: S) -> Self {
+        Self {
+            inner,
+            ciphers: None,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Encrypts everything from now on.
+    pub fn enable(&mut self, secret: &SharedSecret) {
+        self.ciphers = Some(Ciphers {
+            encryptor: Encryptor::new(secret.into(), secret.into()),
+            decryptor: Decryptor::new(secret.into(), secret.into()),
+        });
+    }
+
+    pub fn is_encrypted(&self) -> bool {
+        self.ciphers.is_some()
+    }
+
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S: AsyncWrite + Unpin> EncryptedStream<S> {
+    fn poll_write_pending(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while !self.pending.is_empty() {
+            let n = ready!(Pin::new(&mut self.inner).poll_write(cx, &self.pending))?;
+            if n == 0 {
+                return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+            }
+            self.pending.drain(..n);
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for EncryptedStream<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        let already_filled = buf.filled().len();
+        ready!(Pin::new(&mut this.inner).poll_read(cx, buf))?;
+        if let Some(ciphers) = &mut this.ciphers {
+            for b in &mut buf.filled_mut()[already_filled..] {
+                ciphers
+                    .decryptor
+                    .decrypt_block_mut(GenericArray::from_mut_slice(std::slice::from_mut(b)));
+            }
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for EncryptedStream<S> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        if this.ciphers.is_none() {
+            return Pin::new(&mut this.inner).poll_write(cx, buf);
+        }
+        // the cipher is stateful, so every byte must be encrypted exactly once: make sure
+        // everything encrypted earlier went out before accepting more.
+        ready!(this.poll_write_pending(cx))?;
+        let Some(ciphers) = &mut this.ciphers else {
+            unreachable!("checked above")
+        };
+        this.pending.extend_from_slice(buf);
+        for b in &mut this.pending {
+            ciphers
+                .encryptor
+                .encrypt_block_mut(GenericArray::from_mut_slice(std::slice::from_mut(b)));
+        }
+        // the bytes are accepted even if the inner stream isn't ready, flushing writes them
+        let _ = this.poll_write_pending(cx)?;
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_write_pending(cx))?;
+        Pin::new(&mut this.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_write_pending(cx))?;
+        Pin::new(&mut this.inner).poll_shutdown(cx)
+    }
+}
+
+/// Answers an Encryption Request with a fresh shared secret, encrypting it and the verify token
+/// with the server's public key.
+pub fn encryption_response(
+    request: &EncryptionRequest,
+) -> io::Result<(EncryptionResponse, SharedSecret)> {
+    let public_key = RsaPublicKey::from_public_key_der(&request.public_key)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+    let mut secret = SharedSecret::default();
+    OsRng.fill_bytes(&mut secret);
+
+    let encrypt = |data: &[u8]| {
+        public_key
+            .encrypt(&mut OsRng, Pkcs1v15Encrypt, data)
+            .map_err(io::Error::other)
+    };
+    Ok((
+        EncryptionResponse {
+            shared_secret: encrypt(&secret)?,
+            verify_token: encrypt(&request.verify_token)?,
+        },
+        secret,
+    ))
+}
+
+/// The server id hash sent to the session server when joining an online-mode server.
+pub fn server_hash(server_id: &str, secret: &SharedSecret, public_key: &[u8]) -> String {
+    let digest = Sha1::new()
+        .chain_update(server_id.as_bytes())
+        .chain_update(secret)
+        .chain_update(public_key)
+        .finalize();
+    hex_digest(digest.into())
+}
+
+/// Formats a digest like Java's `new BigInteger(digest).toString(16)`: as a signed two's
+/// complement number, without leading zeros.
+fn hex_digest(mut digest: [u8; 20]) -> String {
+    let negative = digest[0] & 0x80 != 0;
+    if negative {
+        let mut carry = true;
+        for b in digest.iter_mut().rev() {
+            (*b, carry) = (!*b).overflowing_add(u8::from(carry));
+        }
+    }
+    let hex = digest
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect::<String>();
+    let hex = hex.trim_start_matches('0');
+    format!("{}{hex}", if negative { "-" } else { "" })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::packet::{
+        Packet,
+        connection::Connection,
+        login,
+        registry::{Decoded, State},
+        types::String,
+    };
+    use rsa::{RsaPrivateKey, pkcs8::EncodePublicKey as _};
+    use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _, duplex};
+
+    #[test]
+    fn java_hex_digests() {
+        for (name, hash) in [
+            ("Notch", "4ed1f46bbe04bc756bcb17c0c7ce3e4632f06a48"),
+            ("jeb_", "-7c9d5b0044c130109a5d7b5fb5c317c02b4e28c1"),
+            ("simon", "88e16a1019277b15d58faf0541e11910eb756f6"),
+        ] {
+            assert_eq!(hex_digest(Sha1::digest(name).into()), hash, "{name}");
+        }
+    }
+
+    #[tokio::test]
+    async fn stream_roundtrip() {
+        let secret = *b"0123456789abcdef";
+        let (a, b) = duplex(16);
+        let mut a = EncryptedStream::new(a, &secret);
+        let mut b = EncryptedStream::new(b, &secret);
+
+        let message = (0..=255u8).cycle().take(1000).collect::<Vec<_>>();
+        let writer = {
+            let message = message.clone();
+            tokio::spawn(async move {
+                // small chunks through a small pipe exercise partial writes
+                for chunk in message.chunks(7) {
+                    a.write_all(chunk).await.unwrap();
+                }
+                a.flush().await.unwrap();
+                a
+            })
+        };
+        let mut received = vec![0; message.len()];
+        b.read_exact(&mut received).await.unwrap();
+        writer.await.unwrap();
+        assert_eq!(received, message);
+    }
+
+    #[tokio::test]
+    async fn ciphertext_differs_from_plaintext() {
+        let secret = *b"0123456789abcdef";
+        let mut encrypted = EncryptedStream::new(Vec::new(), &secret);
+        encrypted.write_all(b"hello hello").await.unwrap();
+        let ciphertext = encrypted.into_inner();
+        assert_eq!(ciphertext.len(), 11);
+        assert_ne!(ciphertext, b"hello hello");
+
+        let mut decrypted = Vec::new();
+        EncryptedStream::new(&ciphertext[..], &secret)
+            .read_to_end(&mut decrypted)
+            .await
+            .unwrap();
+        assert_eq!(decrypted, b"hello hello");
+    }
+
+    /// A stand-in for an online-mode server's side of the login.
+    #[tokio::test]
+    async fn login_encryption_handshake() {
+        let private_key = RsaPrivateKey::new(&mut OsRng, 1024).unwrap();
+        let public_key = private_key
+            .to_public_key()
+            .to_public_key_der()
+            .unwrap()
+            .into_vec();
+        let verify_token = vec![1, 2, 3, 4];
+
+        let (client, server) = duplex(4096);
+        let mut client = Connection::client(client);
+        let mut server = Connection::server(server);
+        client.set_state(State::Login);
+        server.set_state(State::Login);
+
+        let request = login::EncryptionRequest {
+            server_id: String::borrowed("").into_owned(),
+            public_key: public_key.clone(),
+            verify_token: verify_token.clone(),
+            should_authenticate: true,
+        };
+        server.send(&request).await.unwrap();
+
+        let Decoded::EncryptionRequest(request) = client.read_decoded().await.unwrap() else {
+            panic!("expected an encryption request");
+        };
+        let (response, client_secret) = encryption_response(&request).unwrap();
+        client.send(&response).await.unwrap();
+        client.enable_encryption(&client_secret);
+
+        let Decoded::EncryptionResponse(response) = server.read_decoded().await.unwrap() else {
+            panic!("expected an encryption response");
+        };
+        let decrypt = |data: &[u8]| private_key.decrypt(Pkcs1v15Encrypt, data).unwrap();
+        assert_eq!(decrypt(&response.verify_token), verify_token);
+        let server_secret = SharedSecret::try_from(decrypt(&response.shared_secret)).unwrap();
+        assert_eq!(server_secret, client_secret);
+        server.enable_encryption(&server_secret);
+
+        assert_eq!(
+            server_hash("", &client_secret, &public_key),
+            server_hash("", &server_secret, &request.public_key),
+        );
+
+        let compression = login::SetCompression {
+            threshold: 256.into(),
+        };
+        server.send(&compression).await.unwrap();
+        let Decoded::SetCompression(received) = client.read_decoded().await.unwrap() else {
+            panic!("expected set compression");
+        };
+        assert_eq!(received, compression);
+
+        client
+            .write(&Packet::encode(&login::LoginAcknowledged).await.unwrap())
+            .await
+            .unwrap();
+        assert!(matches!(
+            server.read_decoded().await.unwrap(),
+            Decoded::LoginAcknowledged(_)
+        ));
+    }
+}
@@ -0,0 +1,11 @@
+use super::types::{McType, String, VarInt};
+use crate::Intent;
+
+#[derive(Debug, Clone, PartialEq, Eq, McType)]
+pub struct Handshake {
+    pub protocol_version: VarInt,
+    #[mc(owned, max_len = 255)]
+    pub server_address: String<'static>,
+    pub server_port: u16,
+    pub intent: Intent,
+}
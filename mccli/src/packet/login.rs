@@ -0,0 +1,85 @@
+use super::types::{McType, String, Uuid, VarInt};
+
+#[derive(Debug, Clone, PartialEq, Eq, McType)]
+pub struct LoginStart {
+    #[mc(owned, max_len = 16)]
+    pub name: String<'static>,
+    pub uuid: Uuid,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, McType)]
+pub struct LoginAcknowledged;
+
+#[derive(Debug, Clone, PartialEq, Eq, McType)]
+pub struct Disconnect {
+    /// A json text component.
+    #[mc(owned)]
+    pub reason: String<'static>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, McType)]
+pub struct LoginSuccess {
+    pub uuid: Uuid,
+    #[mc(owned, max_len = 16)]
+    pub username: String<'static>,
+    #[mc(length_prefixed)]
+    pub properties: Vec<Property>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, McType)]
+pub struct EncryptionRequest {
+    /// Empty since 1.7.
+    #[mc(owned, max_len = 20)]
+    pub server_id: String<'static>,
+    /// The server's RSA public key, DER encoded.
+    #[mc(length_prefixed)]
+    pub public_key: Vec<u8>,
+    #[mc(length_prefixed)]
+    pub verify_token: Vec<u8>,
+    /// Whether the client should join the session server before answering.
+    pub should_authenticate: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, McType)]
+pub struct EncryptionResponse {
+    /// Encrypted with the server's public key.
+    #[mc(length_prefixed)]
+    pub shared_secret: Vec<u8>,
+    /// Encrypted with the server's public key.
+    #[mc(length_prefixed)]
+    pub verify_token: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, McType)]
+pub struct SetCompression {
+    /// Packets of at least this many bytes are compressed, a negative value disables it.
+    pub threshold: VarInt,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, McType)]
+pub struct LoginPluginRequest {
+    pub message_id: VarInt,
+    #[mc(owned, max_len = 32767)]
+    pub channel: String<'static>,
+    #[mc(rest)]
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, McType)]
+pub struct LoginPluginResponse {
+    pub message_id: VarInt,
+    /// `false` if the client didn't understand the request.
+    pub successful: bool,
+    #[mc(rest)]
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, McType)]
+pub struct Property {
+    #[mc(owned, max_len = 64)]
+    pub name: String<'static>,
+    #[mc(owned, max_len = 32767)]
+    pub value: String<'static>,
+    #[mc(optional, owned, max_len = 1024)]
+    pub signature: Option<String<'static>>,
+}
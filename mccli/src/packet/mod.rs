@@ -1,33 +1,109 @@
+mod blocking;
+pub mod capture;
+pub mod configuration;
+pub mod connection;
+pub mod encryption;
+pub mod handshaking;
+pub mod login;
+pub mod play;
+pub mod registry;
+pub mod status;
 pub mod types;
 
+use crate::{
+    Limits,
+    error::{DecodeError, FramingError},
+};
+use flate2::{Compression, read::ZlibDecoder, write::ZlibEncoder};
+use registry::{Decoded, Direction, State, TypedPacket};
 use std::{
     borrow::Cow,
-    io::{self, Cursor},
+    io::{self, Cursor, Read as _, Write as _},
 };
 use tokio::io::{AsyncRead, AsyncReadExt as _, AsyncWrite, AsyncWriteExt as _, BufWriter};
-use types::{McType, String, VarInt};
+use types::{Decode, McType, VarInt};
 
+#[derive(Debug, Clone)]
 pub struct Packet<'p> {
     packet_id: VarInt,
     payload: Cow<'p, [u8]>,
 }
 
+/// What the client intends to do after the handshake.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, McType)]
+pub enum Intent {
+    #[default]
+    Status = 1,
+    Login = 2,
+    Transfer = 3,
+}
+
+impl Intent {
+    /// The state the connection switches to after the handshake.
+    pub fn state(self) -> State {
+        match self {
+            Self::Status => State::Status,
+            Self::Login | Self::Transfer => State::Login,
+        }
+    }
+}
+
+/// The fields of the handshake packet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HandshakeOptions {
+    /// The hostname or IP the client used to connect. Proxies use it to route the connection.
+    pub server_address: std::string::String,
+    pub server_port: u16,
+    pub protocol_version: i32,
+    pub intent: Intent,
+}
+
+impl HandshakeOptions {
+    /// The protocol version of Minecraft 1.21.4.
+    pub const DEFAULT_PROTOCOL_VERSION: i32 = registry::PROTOCOL_VERSION;
+
+    pub fn new(server_address: impl Into<std::string::String>, server_port: u16) -> Self {
+        Self {
+            server_address: server_address.into(),
+            server_port,
+            protocol_version: Self::DEFAULT_PROTOCOL_VERSION,
+            intent: Intent::default(),
+        }
+    }
+}
+
+impl Default for HandshakeOptions {
+    fn default() -> Self {
+        Self::new("localhost", 25565)
+    }
+}
+
 impl Packet<'static> {
-    pub async fn handshake(version: u16) -> Self {
+    /// Creates a packet whose payload is `body`.
+    pub async fn new<T: McType>(packet_id: VarInt, body: &T) -> io::Result<Self> {
         let mut buffer = Vec::new();
-        let mut cursor = Cursor::new(&mut buffer);
-        tracing::trace!("creating handshake packet");
-        VarInt::from(version).write(&mut cursor).await.unwrap();
-        String::borrowed("localhost")
-            .write(&mut cursor)
-            .await
-            .unwrap();
-        25565u16.write(&mut cursor).await.unwrap();
-        VarInt::from(1 /*status*/).write(&mut cursor).await.unwrap();
-        Self {
-            packet_id: VarInt::from(0x00),
+        body.write(Cursor::new(&mut buffer)).await?;
+        Ok(Self {
+            packet_id,
             payload: buffer.into(),
-        }
+        })
+    }
+
+    pub async fn handshake(options: &HandshakeOptions) -> Self {
+        tracing::trace!(?options, "creating handshake packet");
+        Self::encode(&handshaking::Handshake {
+            protocol_version: VarInt::from(options.protocol_version),
+            server_address: options.server_address.clone().into(),
+            server_port: options.server_port,
+            intent: options.intent,
+        })
+        .await
+        .unwrap()
+    }
+
+    /// Creates a packet with the id and payload of a typed packet.
+    pub async fn encode<T: TypedPacket>(body: &T) -> io::Result<Self> {
+        Self::new(T::ID.into(), body).await
     }
 
     pub fn status_request() -> Self {
@@ -36,27 +112,82 @@ impl Packet<'static> {
             payload: Default::default(),
         }
     }
+
+    pub fn ping_request(payload: i64) -> Self {
+        Self {
+            packet_id: 0x01.into(),
+            payload: payload.to_be_bytes().to_vec().into(),
+        }
+    }
 }
 
-impl Packet<'_> {
+impl<'p> Packet<'p> {
+    /// Reads a packet and decodes it with the [registry](registry).
+    pub async fn read_decoded<R: AsyncRead + Unpin + Send>(
+        r: R,
+        state: State,
+        direction: Direction,
+    ) -> io::Result<Decoded<'p>> {
+        Self::read(r).await?.decode(state, direction).await
+    }
+
+    pub async fn decode(self, state: State, direction: Direction) -> io::Result<Decoded<'p>> {
+        Decoded::decode(self, state, direction).await
+    }
+
+    /// Decodes the whole payload as `T`, failing if any bytes are left over.
+    pub async fn decode_as<T: McType>(&self) -> io::Result<T> {
+        let mut reader = self.reader();
+        let t = reader.next::<T>().await?;
+        match reader.remaining() {
+            0 => Ok(t),
+            n => Err(trailing_bytes::<T>(n, reader.position)),
+        }
+    }
+
+    /// Like [`Packet::decode_as`] but synchronous, with strings and byte arrays borrowed from
+    /// the payload.
+    pub fn decode_borrowed<'a, T: Decode<'a>>(&'a self) -> io::Result<T> {
+        let mut reader = self.reader();
+        let t = reader.next_borrowed::<T>()?;
+        match reader.remaining() {
+            0 => Ok(t),
+            n => Err(trailing_bytes::<T>(n, reader.position)),
+        }
+    }
+
     pub async fn read<R: AsyncRead + Unpin + Send>(mut r: R) -> io::Result<Self> {
         tracing::info!("reading length");
-        let length: usize = VarInt::read(&mut r)
-            .await?
-            .try_into()
-            .map_err(io::Error::other)?;
+        let length = read_length(&mut r).await?;
         tracing::info!(%length, "reading packet id");
-        let packet_id = VarInt::read(&mut r).await?;
+        let packet_id = read_packet_id(&mut r).await.map_err(truncated(length))?;
         tracing::info!(%length, ?packet_id, "reading payload");
-        let mut buffer = vec![0; length - packet_id.len()];
-        r.read_exact(&mut buffer[0..length - packet_id.len()])
-            .await?;
+        let mut buffer = vec![
+            0;
+            length
+                .checked_sub(packet_id.len())
+                .ok_or(FramingError::TooShort {
+                    length,
+                    header: packet_id.len(),
+                })?
+        ];
+        r.read_exact(&mut buffer).await.map_err(truncated(length))?;
         Ok(Self {
             packet_id,
             payload: Cow::Owned(buffer),
         })
     }
 
+    /// [`Packet::read`] from a blocking reader.
+    pub fn read_blocking<R: std::io::Read + Send>(mut r: R) -> io::Result<Self> {
+        blocking::block_on(Self::read(blocking::Blocking(&mut r)))
+    }
+
+    /// [`Packet::write`] to a blocking writer.
+    pub fn write_blocking<W: std::io::Write + Send>(&self, mut w: W) -> io::Result<()> {
+        blocking::block_on(self.write(blocking::Blocking(&mut w)))
+    }
+
     #[tracing::instrument(skip_all, fields(self.packet_id = ?self.packet_id))]
     pub async fn write<W: AsyncWrite + Unpin + Send>(&self, w: W) -> io::Result<()> {
         let mut w = BufWriter::new(w);
@@ -72,6 +203,113 @@ impl Packet<'_> {
         Ok(())
     }
 
+    /// Reads a packet in the format used after Set Compression.
+    ///
+    /// With no `threshold` this is the same as [`Packet::read`].
+    pub async fn read_compressed<R: AsyncRead + Unpin + Send>(
+        mut r: R,
+        threshold: Option<usize>,
+    ) -> io::Result<Self> {
+        let Some(threshold) = threshold else {
+            return Self::read(r).await;
+        };
+        let length = read_length(&mut r).await?;
+        let data_length = VarInt::read(&mut r)
+            .await
+            .map_err(framing(FramingError::Length))?;
+        let uncompressed_length = non_negative(data_length)?;
+        let max = Limits::current().max_uncompressed;
+        if uncompressed_length > max {
+            return Err(FramingError::TooLong {
+                length: uncompressed_length,
+                max,
+            }
+            .into());
+        }
+        tracing::trace!(%length, %uncompressed_length, "reading compressed packet");
+        let mut body = vec![
+            0;
+            length
+                .checked_sub(data_length.len())
+                .ok_or(FramingError::TooShort {
+                    length,
+                    header: data_length.len(),
+                })?
+        ];
+        r.read_exact(&mut body).await.map_err(truncated(length))?;
+
+        if uncompressed_length != 0 {
+            if uncompressed_length < threshold {
+                return Err(FramingError::BelowThreshold {
+                    length: uncompressed_length,
+                    threshold,
+                }
+                .into());
+            }
+            let mut inflated = Vec::with_capacity(uncompressed_length);
+            ZlibDecoder::new(&body[..])
+                .take(uncompressed_length as u64 + 1)
+                .read_to_end(&mut inflated)
+                .map_err(FramingError::Inflate)?;
+            if inflated.len() != uncompressed_length {
+                return Err(FramingError::Inflated {
+                    actual: inflated.len(),
+                    expected: uncompressed_length,
+                }
+                .into());
+            }
+            body = inflated;
+        }
+
+        let mut cursor = Cursor::new(&body);
+        let packet_id = read_packet_id(&mut cursor).await?;
+        body.drain(..cursor.position() as usize);
+        Ok(Self {
+            packet_id,
+            payload: Cow::Owned(body),
+        })
+    }
+
+    /// Writes a packet in the format used after Set Compression, compressing it if it's at
+    /// least `threshold` bytes long.
+    ///
+    /// With no `threshold` this is the same as [`Packet::write`].
+    pub async fn write_compressed<W: AsyncWrite + Unpin + Send>(
+        &self,
+        w: W,
+        threshold: Option<usize>,
+    ) -> io::Result<()> {
+        let Some(threshold) = threshold else {
+            return self.write(w).await;
+        };
+        let mut w = BufWriter::new(w);
+        let mut body = Vec::with_capacity(self.packet_id.len() + self.payload.len());
+        self.packet_id.write(&mut body).await?;
+        body.extend_from_slice(&self.payload);
+        let data_length = if body.len() >= threshold {
+            let uncompressed_length = VarInt::try_from(body.len()).map_err(io::Error::other)?;
+            let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(&body)?;
+            body = encoder.finish()?;
+            uncompressed_length
+        } else {
+            VarInt::from(0)
+        };
+        tracing::trace!(?data_length, "writing compressed packet");
+        VarInt::try_from(data_length.len() + body.len())
+            .map_err(io::Error::other)?
+            .write(&mut w)
+            .await?;
+        data_length.write(&mut w).await?;
+        w.write_all(&body).await?;
+        w.flush().await?;
+        Ok(())
+    }
+
+    pub fn id(&self) -> VarInt {
+        self.packet_id
+    }
+
     pub fn reader(&self) -> PacketReader<'_> {
         PacketReader {
             packet: self,
@@ -80,16 +318,383 @@ impl Packet<'_> {
     }
 }
 
+/// Turns invalid data into a framing error, leaving the errors of the underlying stream alone.
+fn framing(error: fn(io::Error) -> FramingError) -> impl Fn(io::Error) -> io::Error {
+    move |e| match e.kind() {
+        io::ErrorKind::InvalidData => error(e).into(),
+        _ => e,
+    }
+}
+
+/// Turns the stream ending in the middle of a packet of `length` bytes into a framing error.
+fn truncated(length: usize) -> impl Fn(io::Error) -> io::Error {
+    move |e| match e.kind() {
+        io::ErrorKind::UnexpectedEof => FramingError::Truncated { length }.into(),
+        _ => e,
+    }
+}
+
+fn non_negative(length: VarInt) -> io::Result<usize> {
+    usize::try_from(length).map_err(|_| FramingError::NegativeLength(length.into()).into())
+}
+
+/// Reads the length of a packet, which has to be within [`Limits::max_packet`].
+async fn read_length<R: AsyncRead + Unpin + Send>(r: R) -> io::Result<usize> {
+    let length = VarInt::read(r)
+        .await
+        .map_err(framing(FramingError::Length))?;
+    let length = non_negative(length)?;
+    let max = Limits::current().max_packet;
+    if length > max {
+        return Err(FramingError::TooLong { length, max }.into());
+    }
+    Ok(length)
+}
+
+/// Reads a whole frame as is, length included, or `None` if the stream ended before it started.
+pub(crate) async fn read_frame<R: AsyncRead + Unpin + Send>(
+    mut r: R,
+) -> io::Result<Option<Vec<u8>>> {
+    let first = match r.read_u8().await {
+        Ok(first) => first,
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    let length = read_length(tokio::io::AsyncReadExt::chain(&[first][..], &mut r)).await?;
+    let mut frame = Vec::with_capacity(length + 3);
+    VarInt::try_from(length)
+        .map_err(io::Error::other)?
+        .write(&mut frame)
+        .await?;
+    let header = frame.len();
+    frame.resize(header + length, 0);
+    r.read_exact(&mut frame[header..])
+        .await
+        .map_err(truncated(length))?;
+    Ok(Some(frame))
+}
+
+async fn read_packet_id<R: AsyncRead + Unpin + Send>(r: R) -> io::Result<VarInt> {
+    VarInt::read(r)
+        .await
+        .map_err(framing(FramingError::PacketId))
+}
+
+fn trailing_bytes<T>(n: usize, offset: usize) -> io::Error {
+    DecodeError::at::<T>(
+        io::Error::new(io::ErrorKind::InvalidData, format!("{n} trailing bytes")),
+        offset,
+    )
+}
+
 pub struct PacketReader<'p> {
     packet: &'p Packet<'p>,
     position: usize,
 }
 
 impl<'t> PacketReader<'t> {
+    /// Decodes the next value straight out of the payload, borrowing from it.
+    pub fn next_borrowed<T: Decode<'t>>(&mut self) -> io::Result<T> {
+        let mut buf = &self.packet.payload[self.position..];
+        let result = T::decode(&mut buf);
+        self.position = self.packet.payload.len() - buf.len();
+        result.map_err(|e| DecodeError::at::<T>(e, self.position))
+    }
+
     pub async fn next<T: McType + 't>(&mut self) -> io::Result<T> {
         let mut cursor = Cursor::new(&self.packet.payload[self.position..]);
-        let t = T::read(&mut cursor).await?;
+        let result = T::read(&mut cursor).await;
         self.position += cursor.position() as usize;
-        Ok(t)
+        result.map_err(|e| DecodeError::at::<T>(e, self.position))
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.packet.payload.len() - self.position
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use types::{String, Uuid};
+
+    #[derive(Debug, PartialEq, McType)]
+    struct Everything<'s> {
+        name: String<'s>,
+        count: VarInt,
+        #[mc(optional)]
+        nickname: Option<String<'s>>,
+        #[mc(optional)]
+        missing: Option<u16>,
+        #[mc(length_prefixed)]
+        scores: Vec<i64>,
+        #[mc(length_prefixed)]
+        key: Vec<u8>,
+        #[mc(owned)]
+        motd: String<'static>,
+        kind: Kind,
+        #[mc(rest)]
+        rest: Vec<u8>,
+    }
+
+    #[derive(Debug, PartialEq, McType)]
+    enum Kind {
+        Empty,
+        Tuple(u8, bool),
+        #[mc(id = 0x10)]
+        Named {
+            #[mc(length_prefixed)]
+            ids: Vec<VarInt>,
+        },
+    }
+
+    async fn roundtrip<T: McType + PartialEq + std::fmt::Debug>(value: T) -> Vec<u8> {
+        let mut buffer = Vec::new();
+        value.write(Cursor::new(&mut buffer)).await.unwrap();
+        let read = T::read(Cursor::new(&buffer)).await.unwrap();
+        assert_eq!(value, read);
+        buffer
+    }
+
+    #[tokio::test]
+    async fn derived_struct_roundtrip() {
+        let bytes = roundtrip(Everything {
+            name: String::borrowed("mccli"),
+            count: 300.into(),
+            nickname: Some(String::borrowed("mc")),
+            missing: None,
+            scores: vec![-1, 0, i64::MAX],
+            key: vec![0xca, 0xfe],
+            motd: String::borrowed("hi").into_owned(),
+            kind: Kind::Tuple(7, true),
+            rest: vec![1, 2, 3],
+        })
+        .await;
+        assert_eq!(bytes[..6], *b"\x05mccli");
+        assert_eq!(bytes[bytes.len() - 6..], [0x01, 7, 1, 1, 2, 3]);
+    }
+
+    #[tokio::test]
+    async fn derived_enum_roundtrip() {
+        assert_eq!(roundtrip(Kind::Empty).await, [0x00]);
+        assert_eq!(
+            roundtrip(Kind::Named {
+                ids: vec![1.into(), 128.into()]
+            })
+            .await,
+            [0x10, 0x02, 0x01, 0x80, 0x01]
+        );
+        assert_eq!(roundtrip(Intent::Login).await, [0x02]);
+        assert!(Intent::read(Cursor::new(&[0x04])).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn borrowed_decoding() {
+        let everything = Everything {
+            name: String::borrowed("mccli"),
+            count: 300.into(),
+            nickname: None,
+            missing: Some(7),
+            scores: vec![1, 2],
+            key: vec![0xca, 0xfe],
+            motd: String::borrowed("hi").into_owned(),
+            kind: Kind::Named {
+                ids: vec![1.into()],
+            },
+            rest: vec![1, 2, 3],
+        };
+        let packet = Packet::new(0x00.into(), &everything).await.unwrap();
+        let decoded = packet.decode_borrowed::<Everything>().unwrap();
+        assert_eq!(decoded, everything);
+        assert_eq!(decoded.name.as_ptr(), packet.payload[1..].as_ptr());
+
+        let mut reader = packet.reader();
+        assert_eq!(reader.next_borrowed::<String>().unwrap(), everything.name);
+        assert_eq!(reader.next_borrowed::<VarInt>().unwrap(), 300.into());
+        assert_eq!(reader.remaining(), packet.payload.len() - 8);
+
+        // types with owned strings are decoded too
+        let success = login::LoginSuccess {
+            uuid: Uuid(1),
+            username: String::borrowed("steve").into_owned(),
+            properties: vec![],
+        };
+        let packet = Packet::encode(&success).await.unwrap();
+        assert_eq!(
+            packet.decode_borrowed::<login::LoginSuccess>().unwrap(),
+            success
+        );
+
+        let truncated = Packet {
+            packet_id: 0x00.into(),
+            payload: packet.payload[..4].to_vec().into(),
+        };
+        assert!(truncated.decode_borrowed::<login::LoginSuccess>().is_err());
+    }
+
+    #[tokio::test]
+    async fn decode_errors_say_where() {
+        use crate::error::{DecodeError, Error, FramingError};
+
+        // a uuid, then a username that claims to be longer than what's left
+        let mut payload = vec![0; 16];
+        payload.extend([0x05, b'a', b'b']);
+        let packet = Packet {
+            packet_id: 0x02.into(),
+            payload: payload.into(),
+        };
+        for error in [
+            packet.decode_as::<login::LoginSuccess>().await.unwrap_err(),
+            packet.decode_borrowed::<login::LoginSuccess>().unwrap_err(),
+        ] {
+            let Error::Decode(DecodeError { field, offset, .. }) = Error::from(error) else {
+                panic!("expected a decode error");
+            };
+            assert_eq!(field, Some("username"));
+            assert!(offset.is_some_and(|o| o >= 17), "{offset:?}");
+        }
+
+        let packet = Packet {
+            packet_id: 0x00.into(),
+            payload: vec![0xff, 0xff, 0xff, 0xff, 0x0f].into(),
+        };
+        let error = Error::from(packet.decode_borrowed::<String>().unwrap_err());
+        assert_eq!(error.to_string(), "decoding String at byte 5");
+        assert_eq!(
+            std::error::Error::source(&error).unwrap().to_string(),
+            "negative length -1"
+        );
+
+        let error = Packet::read_blocking(&[0x80, 0x80, 0x80, 0x80, 0x80, 0x01][..]).unwrap_err();
+        assert!(matches!(
+            Error::from(error),
+            Error::Framing(FramingError::Length(_))
+        ));
+        let error = Packet::read_blocking(&[0x00, 0x00][..]).unwrap_err();
+        assert!(matches!(
+            Error::from(error),
+            Error::Framing(FramingError::TooShort { .. })
+        ));
+        // claims to inflate to 64 bytes, but isn't zlib
+        let error = blocking::block_on(Packet::read_compressed(
+            blocking::Blocking(&[0x04, 0x40, 0xde, 0xad, 0xbe][..]),
+            Some(0),
+        ))
+        .unwrap_err();
+        let error = Error::from(error);
+        assert!(matches!(error, Error::Framing(FramingError::Inflate(_))));
+        assert!(error.is_invalid_response());
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn arbitrary_bytes_never_panic(
+            bytes in proptest::collection::vec(proptest::prelude::any::<u8>(), 0..512),
+        ) {
+            let packets = [
+                Packet::read_blocking(&bytes[..]),
+                blocking::block_on(Packet::read_compressed(
+                    blocking::Blocking(&bytes[..]),
+                    Some(0),
+                )),
+                Ok(Packet {
+                    packet_id: bytes.first().copied().unwrap_or_default().into(),
+                    payload: bytes.get(1..).unwrap_or_default().into(),
+                }),
+            ];
+            for packet in packets.into_iter().flatten() {
+                for state in [
+                    State::Handshaking,
+                    State::Status,
+                    State::Login,
+                    State::Configuration,
+                    State::Play,
+                ] {
+                    for direction in [Direction::Clientbound, Direction::Serverbound] {
+                        let _ = blocking::block_on(packet.clone().decode(state, direction));
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn blocking_codec() {
+        let kind = Kind::Named {
+            ids: vec![1.into(), 128.into()],
+        };
+        let mut buffer = Vec::new();
+        kind.write_blocking(&mut buffer).unwrap();
+        assert_eq!(buffer, [0x10, 0x02, 0x01, 0x80, 0x01]);
+        assert_eq!(Kind::read_blocking(&buffer[..]).unwrap(), kind);
+
+        let packet = Packet::ping_request(42);
+        let mut buffer = Vec::new();
+        packet.write_blocking(&mut buffer).unwrap();
+        let read = Packet::read_blocking(&buffer[..]).unwrap();
+        assert_eq!(read.id(), packet.id());
+        assert_eq!(read.payload, packet.payload);
+
+        assert_eq!(
+            Packet::read_blocking(&buffer[..0]).unwrap_err().kind(),
+            io::ErrorKind::UnexpectedEof
+        );
+        assert!(matches!(
+            crate::Error::from(Packet::read_blocking(&buffer[..3]).unwrap_err()),
+            crate::Error::Framing(crate::error::FramingError::Truncated { length: 9 })
+        ));
+    }
+
+    #[tokio::test]
+    async fn handshake_encoding() {
+        let packet = Packet::handshake(&HandshakeOptions::default()).await;
+        assert_eq!(packet.id(), 0x00.into());
+        assert_eq!(*packet.payload, *b"\x81\x06\x09localhost\x63\xdd\x01");
+    }
+
+    #[tokio::test]
+    async fn decode_with_registry() {
+        let success = login::LoginSuccess {
+            uuid: Uuid(0x0123),
+            username: String::borrowed("steve").into_owned(),
+            properties: vec![login::Property {
+                name: String::borrowed("textures").into_owned(),
+                value: String::borrowed("e30=").into_owned(),
+                signature: None,
+            }],
+        };
+        let packet = Packet::encode(&success).await.unwrap();
+        assert_eq!(packet.id(), 0x02.into());
+
+        let mut buffer = Vec::new();
+        packet.write(&mut buffer).await.unwrap();
+        let decoded = Packet::read_decoded(&buffer[..], State::Login, Direction::Clientbound)
+            .await
+            .unwrap();
+        assert_eq!(decoded.name(), "LoginSuccess");
+        assert!(matches!(decoded, Decoded::LoginSuccess(s) if s == success));
+
+        // the same id means something else in another state
+        let decoded = packet
+            .clone()
+            .decode(State::Status, Direction::Clientbound)
+            .await
+            .unwrap();
+        assert!(matches!(decoded, Decoded::Unknown(p) if p.id() == 0x02.into()));
+    }
+
+    #[tokio::test]
+    async fn decode_rejects_trailing_bytes() {
+        // a pong is a single i64
+        let packet = Packet {
+            packet_id: 0x01.into(),
+            payload: vec![0; 9].into(),
+        };
+        assert!(
+            packet
+                .decode(State::Status, Direction::Clientbound)
+                .await
+                .is_err()
+        );
     }
 }
@@ -0,0 +1,23 @@
+use super::types::{McType, text::TextComponent};
+
+#[derive(Debug, Clone, PartialEq, McType)]
+pub struct Disconnect {
+    pub reason: TextComponent,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, McType)]
+pub struct ClientboundKeepAlive {
+    pub id: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, McType)]
+pub struct ServerboundKeepAlive {
+    pub id: i64,
+}
+
+/// Sends the client back to the Configuration state.
+#[derive(Debug, Clone, PartialEq, Eq, McType)]
+pub struct StartConfiguration;
+
+#[derive(Debug, Clone, PartialEq, Eq, McType)]
+pub struct AcknowledgeConfiguration;
@@ -0,0 +1,141 @@
+//! Maps packet ids to typed packets for protocol version 769 (1.21.4).
+
+use super::{Packet, configuration, handshaking, login, play, status, types::McType};
+use std::io;
+
+/// The protocol version whose packets the registry knows.
+pub const PROTOCOL_VERSION: i32 = 769;
+
+/// The state of a connection, which decides what each packet id means.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, McType)]
+pub enum State {
+    #[default]
+    Handshaking,
+    Status,
+    Login,
+    Configuration,
+    Play,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, McType)]
+pub enum Direction {
+    /// From the server to the client.
+    Clientbound,
+    /// From the client to the server.
+    Serverbound,
+}
+
+impl Direction {
+    pub fn opposite(self) -> Self {
+        match self {
+            Self::Clientbound => Self::Serverbound,
+            Self::Serverbound => Self::Clientbound,
+        }
+    }
+}
+
+/// A packet body with a known id.
+pub trait TypedPacket: McType {
+    const ID: i32;
+    const STATE: State;
+    const DIRECTION: Direction;
+    const NAME: &'static str;
+}
+
+macro_rules! registry {
+    ($(
+        $state:ident $direction:ident {
+            $($id:literal => $variant:ident($ty:ty)),* $(,)?
+        }
+    )*) => {
+        /// A packet decoded according to the [`State`] and [`Direction`] it was received in.
+        #[derive(Debug)]
+        pub enum Decoded<'p> {
+            $($($variant($ty),)*)*
+            /// A packet that isn't in the registry, kept as is.
+            Unknown(Packet<'p>),
+        }
+
+        $($(
+            impl TypedPacket for $ty {
+                const ID: i32 = $id;
+                const STATE: State = State::$state;
+                const DIRECTION: Direction = Direction::$direction;
+                const NAME: &'static str = stringify!($variant);
+            }
+        )*)*
+
+        impl<'p> Decoded<'p> {
+            pub fn name(&self) -> &'static str {
+                match self {
+                    $($(Self::$variant(_) => stringify!($variant),)*)*
+                    Self::Unknown(_) => "Unknown",
+                }
+            }
+
+            pub async fn decode(
+                packet: Packet<'p>,
+                state: State,
+                direction: Direction,
+            ) -> io::Result<Self> {
+                match (state, direction, i32::from(packet.id())) {
+                    $($(
+                        (State::$state, Direction::$direction, $id) => {
+                            packet.decode_as::<$ty>().await.map(Self::$variant)
+                        }
+                    )*)*
+                    _ => Ok(Self::Unknown(packet)),
+                }
+            }
+        }
+    };
+}
+
+registry! {
+    Handshaking Serverbound {
+        0x00 => Handshake(handshaking::Handshake),
+    }
+    Status Clientbound {
+        0x00 => StatusResponse(status::StatusResponse),
+        0x01 => PongResponse(status::PongResponse),
+    }
+    Status Serverbound {
+        0x00 => StatusRequest(status::StatusRequest),
+        0x01 => PingRequest(status::PingRequest),
+    }
+    Login Clientbound {
+        0x00 => LoginDisconnect(login::Disconnect),
+        0x01 => EncryptionRequest(login::EncryptionRequest),
+        0x02 => LoginSuccess(login::LoginSuccess),
+        0x03 => SetCompression(login::SetCompression),
+        0x04 => LoginPluginRequest(login::LoginPluginRequest),
+    }
+    Login Serverbound {
+        0x00 => LoginStart(login::LoginStart),
+        0x01 => EncryptionResponse(login::EncryptionResponse),
+        0x02 => LoginPluginResponse(login::LoginPluginResponse),
+        0x03 => LoginAcknowledged(login::LoginAcknowledged),
+    }
+    Configuration Clientbound {
+        0x01 => ConfigurationClientboundPluginMessage(configuration::ClientboundPluginMessage),
+        0x02 => ConfigurationDisconnect(configuration::Disconnect),
+        0x03 => FinishConfiguration(configuration::FinishConfiguration),
+        0x04 => ConfigurationClientboundKeepAlive(configuration::ClientboundKeepAlive),
+        0x05 => ConfigurationPing(configuration::Ping),
+    }
+    Configuration Serverbound {
+        0x02 => ConfigurationServerboundPluginMessage(configuration::ServerboundPluginMessage),
+        0x03 => AcknowledgeFinishConfiguration(configuration::AcknowledgeFinishConfiguration),
+        0x04 => ConfigurationServerboundKeepAlive(configuration::ServerboundKeepAlive),
+        0x05 => ConfigurationPong(configuration::Pong),
+    }
+    Play Clientbound {
+        0x1d => PlayDisconnect(play::Disconnect),
+        0x27 => PlayClientboundKeepAlive(play::ClientboundKeepAlive),
+        0x70 => StartConfiguration(play::StartConfiguration),
+    }
+    Play Serverbound {
+        0x0e => AcknowledgeConfiguration(play::AcknowledgeConfiguration),
+        0x1a => PlayServerboundKeepAlive(play::ServerboundKeepAlive),
+    }
+}
@@ -0,0 +1,21 @@
+use super::types::{McType, String};
+
+#[derive(Debug, Clone, PartialEq, Eq, McType)]
+pub struct StatusRequest;
+
+#[derive(Debug, Clone, PartialEq, Eq, McType)]
+pub struct PingRequest {
+    pub payload: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, McType)]
+pub struct StatusResponse {
+    /// The [`Status`](crate::types::server::Status) as json.
+    #[mc(owned, max_len = 32767)]
+    pub json: String<'static>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, McType)]
+pub struct PongResponse {
+    pub payload: i64,
+}
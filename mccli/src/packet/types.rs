@@ -1,3 +1,4 @@
+use crate::Limits;
 use std::{
     borrow::Cow,
     fmt,
@@ -6,11 +7,58 @@ use std::{
 };
 use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
 
+pub use mccli_derive::McType;
+
 pub trait McType {
     fn read<R: AsyncRead + Unpin + Send>(r: R) -> impl Future<Output = io::Result<Self>> + Send
     where
         Self: Sized;
     fn write<W: AsyncWrite + Unpin + Send>(&self, w: W) -> impl Future<Output = io::Result<()>>;
+
+    /// [`McType::read`] from a blocking reader, e.g. a byte slice or a [`std::net::TcpStream`].
+    fn read_blocking<R: std::io::Read + Send>(mut r: R) -> io::Result<Self>
+    where
+        Self: Sized,
+    {
+        super::blocking::block_on(Self::read(super::blocking::Blocking(&mut r)))
+    }
+
+    /// [`McType::write`] to a blocking writer.
+    fn write_blocking<W: std::io::Write + Send>(&self, mut w: W) -> io::Result<()> {
+        super::blocking::block_on(self.write(super::blocking::Blocking(&mut w)))
+    }
+}
+
+/// Decodes a value straight out of an in-memory buffer, like a packet's payload, without going
+/// through [`McType::read`]'s async machinery. Strings and byte arrays borrow from the buffer.
+///
+/// Derived along with [`McType`].
+pub trait Decode<'a>: Sized {
+    /// Decodes a value from the start of `buf` and advances it past the value.
+    fn decode(buf: &mut &'a [u8]) -> io::Result<Self>;
+}
+
+/// Splits the first `n` bytes off `buf`.
+fn take<'a>(buf: &mut &'a [u8], n: usize) -> io::Result<&'a [u8]> {
+    if buf.len() < n {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            format!("expected {n} bytes, {} left", buf.len()),
+        ));
+    }
+    let (head, tail) = buf.split_at(n);
+    *buf = tail;
+    Ok(head)
+}
+
+/// Reads `len` bytes without trusting `len` enough to allocate it up front.
+async fn read_bytes<R: AsyncRead + Unpin + Send>(r: &mut R, len: usize) -> io::Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    r.take(len as u64).read_to_end(&mut bytes).await?;
+    if bytes.len() < len {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    Ok(bytes)
 }
 
 macro_rules! VarNum {
@@ -28,6 +76,13 @@ macro_rules! VarNum {
             const SEGMENT_BITS: u8 = 0x7f;
             const CONTINUE_BIT: u8 = 0x80;
 
+            fn too_long() -> io::Error {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    concat!(stringify!($name), " is too long"),
+                )
+            }
+
             #[allow(clippy::len_without_is_empty)]
             pub fn len(&self) -> usize {
                 if self.int < 0 {
@@ -35,7 +90,7 @@ macro_rules! VarNum {
                 } else if self.int == 0 {
                     1
                 } else {
-                    match dbg!(<$int>::BITS - self.int.leading_zeros()) {
+                    match <$int>::BITS - self.int.leading_zeros() {
                         ..8 => 1,
                         8..15 => 2,
                         15..22 => 3,
@@ -70,7 +125,7 @@ macro_rules! VarNum {
                     len += 7;
 
                     if u32::from(len) >= <$int>::BITS {
-                        return Err(io::ErrorKind::InvalidData.into());
+                        return Err(Self::too_long());
                     };
                 }
                 panic!("infinite loop when reading {}", stringify!($name));
@@ -97,6 +152,24 @@ macro_rules! VarNum {
             }
         }
 
+        impl Decode<'_> for $name {
+            fn decode(buf: &mut &[u8]) -> io::Result<Self> {
+                let mut int = 0;
+                for (i, &byte) in buf.iter().enumerate() {
+                    let shift = i * 7;
+                    if shift >= <$int>::BITS as usize {
+                        return Err(Self::too_long());
+                    }
+                    int |= <$int>::from(byte & Self::SEGMENT_BITS) << shift;
+                    if byte & Self::CONTINUE_BIT == 0 {
+                        *buf = &buf[i + 1..];
+                        return Ok(Self { int });
+                    }
+                }
+                Err(io::ErrorKind::UnexpectedEof.into())
+            }
+        }
+
         impl From<u8> for $name {
             fn from(v: u8) -> Self {
                 Self {
@@ -137,6 +210,12 @@ macro_rules! VarNum {
             }
         }
 
+        impl From<$name> for $int {
+            fn from(v: $name) -> Self {
+                v.int
+            }
+        }
+
         impl TryFrom<u64> for $name {
             type Error = <$int as TryFrom<u64>>::Error;
 
@@ -165,6 +244,18 @@ macro_rules! VarNum {
 
 VarNum!(VarInt: i32 | u32);
 
+impl VarInt {
+    /// This as the length prefix of a string or array, which can't be negative.
+    pub fn length(self) -> io::Result<usize> {
+        usize::try_from(self).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("negative length {}", self.int),
+            )
+        })
+    }
+}
+
 impl TryFrom<u32> for VarInt {
     type Error = <i32 as TryFrom<u32>>::Error;
     fn try_from(value: u32) -> Result<Self, Self::Error> {
@@ -198,6 +289,43 @@ impl<'s> String<'s> {
     pub fn borrowed(s: &'s str) -> Self {
         Self(Cow::Borrowed(s))
     }
+
+    pub fn into_owned(self) -> String<'static> {
+        String(Cow::Owned(self.0.into_owned()))
+    }
+
+    /// Fails if this is longer than `max` characters, counted in UTF-16 code units like the
+    /// protocol does.
+    pub fn at_most(self, max: usize) -> io::Result<Self> {
+        // a code unit takes at least a byte
+        if self.0.len() > max && self.0.encode_utf16().count() > max {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("string is longer than {max} characters"),
+            ));
+        }
+        Ok(self)
+    }
+
+    /// Checks the length prefix of a string against [`Limits::max_string`] before anything is
+    /// allocated for it. A code unit takes at most 3 bytes.
+    fn byte_length(prefix: VarInt) -> io::Result<usize> {
+        let length = prefix.length()?;
+        let max = Limits::current().max_string;
+        if length > max.saturating_mul(3) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("string of {length} bytes is longer than {max} characters"),
+            ));
+        }
+        Ok(length)
+    }
+}
+
+impl From<std::string::String> for String<'static> {
+    fn from(s: std::string::String) -> Self {
+        Self(Cow::Owned(s))
+    }
 }
 
 impl Deref for String<'_> {
@@ -209,15 +337,11 @@ impl Deref for String<'_> {
 
 impl McType for String<'_> {
     async fn read<R: AsyncRead + Unpin + Send>(mut r: R) -> io::Result<Self> {
-        let length = VarInt::read(&mut r)
-            .await?
-            .try_into()
-            .map_err(io::Error::other)?;
-        let mut buffer = vec![0; length];
-        r.read_exact(&mut buffer).await?;
-        let s = std::string::String::from_utf8(buffer).map_err(io::Error::other)?;
+        let length = Self::byte_length(VarInt::read(&mut r).await?)?;
+        let s = std::string::String::from_utf8(read_bytes(&mut r, length).await?)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
 
-        Ok(String(Cow::Owned(s)))
+        String(Cow::Owned(s)).at_most(Limits::current().max_string)
     }
 
     async fn write<W: AsyncWrite + Unpin + Send>(&self, mut w: W) -> io::Result<()> {
@@ -231,27 +355,145 @@ impl McType for String<'_> {
     }
 }
 
+impl<'a> Decode<'a> for String<'a> {
+    fn decode(buf: &mut &'a [u8]) -> io::Result<Self> {
+        let length = Self::byte_length(VarInt::decode(buf)?)?;
+        let s = std::str::from_utf8(take(buf, length)?)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        String(Cow::Borrowed(s)).at_most(Limits::current().max_string)
+    }
+}
+
+/// A byte array prefixed by its length as a [`VarInt`].
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Hash, Default)]
+pub struct ByteArray<'b>(Cow<'b, [u8]>);
+
+impl<'b> ByteArray<'b> {
+    pub fn borrowed(bytes: &'b [u8]) -> Self {
+        Self(Cow::Borrowed(bytes))
+    }
+
+    pub fn into_owned(self) -> ByteArray<'static> {
+        ByteArray(Cow::Owned(self.0.into_owned()))
+    }
+
+    pub fn into_vec(self) -> Vec<u8> {
+        self.0.into_owned()
+    }
+}
+
+impl From<Vec<u8>> for ByteArray<'static> {
+    fn from(bytes: Vec<u8>) -> Self {
+        Self(Cow::Owned(bytes))
+    }
+}
+
+impl Deref for ByteArray<'_> {
+    type Target = [u8];
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl McType for ByteArray<'_> {
+    async fn read<R: AsyncRead + Unpin + Send>(mut r: R) -> io::Result<Self> {
+        let length = VarInt::read(&mut r).await?.length()?;
+        Ok(Self(Cow::Owned(read_bytes(&mut r, length).await?)))
+    }
+
+    async fn write<W: AsyncWrite + Unpin + Send>(&self, mut w: W) -> io::Result<()> {
+        VarInt::try_from(self.0.len())
+            .map_err(io::Error::other)?
+            .write(&mut w)
+            .await?;
+        w.write_all(&self.0).await
+    }
+}
+
+impl<'a> Decode<'a> for ByteArray<'a> {
+    fn decode(buf: &mut &'a [u8]) -> io::Result<Self> {
+        let length = VarInt::decode(buf)?.length()?;
+        Ok(Self::borrowed(take(buf, length)?))
+    }
+}
+
+pub mod nbt;
+mod slot;
+pub mod text;
+
+pub use slot::{Component, Slot};
+
 pub mod server {
+    mod forge;
+
+    use super::text::TextComponent;
+    pub use forge::{ForgeChannel, ForgeData, ForgeMod, SERVER_ONLY_MARKER};
     use serde::{Deserialize, Serialize};
 
-    #[derive(Serialize, Deserialize, Debug)]
+    #[derive(Serialize, Deserialize, Debug, Clone)]
     pub struct Status {
         pub version: Version,
         #[serde(rename = "enforcesSecureChat", default)]
         pub enforces_secure_chat: bool,
-        pub description: Description,
+        pub description: TextComponent,
         pub players: Players,
+        #[serde(skip_serializing_if = "Option::is_none")]
         pub favicon: Option<String>,
+        /// Set by Forge servers before 1.13.
+        #[serde(skip_serializing_if = "Option::is_none")]
         pub modinfo: Option<ModInfo>,
+        /// Set by Forge servers since 1.13 and by NeoForge servers.
+        #[serde(rename = "forgeData", skip_serializing_if = "Option::is_none")]
+        pub forge_data: Option<ForgeData>,
+        /// Sent by 1.19 to 1.19.2 servers.
+        #[serde(rename = "previewsChat", skip_serializing_if = "Option::is_none")]
+        pub previews_chat: Option<bool>,
+        /// Sent by servers running the No Chat Reports mod.
+        #[serde(
+            rename = "preventsChatReports",
+            skip_serializing_if = "Option::is_none"
+        )]
+        pub prevents_chat_reports: Option<bool>,
+        /// Fields this struct doesn't know about, kept so the status can be re-serialized whole.
+        #[serde(flatten)]
+        pub extra: serde_json::Map<String, serde_json::Value>,
     }
 
-    #[derive(Serialize, Deserialize, Debug)]
+    impl Status {
+        /// A status with only the fields every server sends.
+        pub fn new(version: Version, players: Players, description: TextComponent) -> Self {
+            Self {
+                version,
+                enforces_secure_chat: false,
+                description,
+                players,
+                favicon: None,
+                modinfo: None,
+                forge_data: None,
+                previews_chat: None,
+                prevents_chat_reports: None,
+                extra: Default::default(),
+            }
+        }
+    }
+
+    #[derive(Serialize, Deserialize, Debug, Clone)]
     pub struct Version {
         pub name: String,
-        pub protocol: u16,
+        /// Proxies that don't know which versions their backends speak send -1.
+        pub protocol: i32,
+    }
+
+    impl Version {
+        pub fn new(name: impl Into<String>, protocol: i32) -> Self {
+            Self {
+                name: name.into(),
+                protocol,
+            }
+        }
     }
 
-    #[derive(Serialize, Deserialize, Debug)]
+    #[derive(Serialize, Deserialize, Debug, Clone)]
     pub struct Players {
         pub max: u64,
         pub online: u64,
@@ -259,35 +501,37 @@ pub mod server {
         pub sample: Vec<Player>,
     }
 
-    #[derive(Serialize, Deserialize, Debug)]
+    impl Players {
+        /// Player counts without a sample.
+        pub fn new(max: u64, online: u64) -> Self {
+            Self {
+                max,
+                online,
+                sample: Vec::new(),
+            }
+        }
+    }
+
+    #[derive(Serialize, Deserialize, Debug, Clone)]
     pub struct Player {
         pub id: String,
         pub name: String,
     }
 
-    #[derive(Serialize, Deserialize, Debug)]
-    #[serde(untagged)]
-    pub enum Description {
-        Text(std::string::String),
-        Colored(ColoredText),
-    }
-
-    #[derive(Serialize, Deserialize, Debug)]
-    pub struct ColoredText {
-        #[serde(default)]
-        pub bold: bool,
-        pub color: Option<String>,
-        #[serde(default)]
-        pub extra: Vec<ColoredText>,
-        pub text: String,
-    }
-
-    #[derive(Serialize, Deserialize, Debug)]
+    #[derive(Serialize, Deserialize, Debug, Clone)]
     pub struct ModInfo {
         #[serde(rename = "modList")]
-        pub mod_list: Vec<String>,
+        pub mod_list: Vec<Mod>,
+        /// `FML` for Forge servers.
         pub r#type: String,
     }
+
+    #[derive(Serialize, Deserialize, Debug, Clone)]
+    pub struct Mod {
+        #[serde(rename = "modid")]
+        pub id: String,
+        pub version: String,
+    }
 }
 
 macro_rules! num {
@@ -307,11 +551,364 @@ macro_rules! num {
                 w.write_all(&self.to_be_bytes()).await
             }
         }
+
+        impl Decode<'_> for $int {
+            fn decode(buf: &mut &[u8]) -> io::Result<Self> {
+                let bytes = take(buf, std::mem::size_of::<Self>())?;
+                Ok(Self::from_be_bytes(bytes.try_into().expect("took the right amount")))
+            }
+        }
         )*
     };
 }
 
-num!(u8, i8, u16, i16, u32, i32, u64, i64);
+num!(u8, i8, u16, i16, u32, i32, u64, i64, u128, f32, f64);
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash, Default)]
+pub struct Uuid(pub u128);
+
+impl fmt::Display for Uuid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let n = self.0;
+        write!(
+            f,
+            "{:08x}-{:04x}-{:04x}-{:04x}-{:012x}",
+            n >> 96,
+            (n >> 80) & 0xffff,
+            (n >> 64) & 0xffff,
+            (n >> 48) & 0xffff,
+            n & 0xffff_ffff_ffff,
+        )
+    }
+}
+
+impl McType for Uuid {
+    async fn read<R: AsyncRead + Unpin + Send>(r: R) -> io::Result<Self> {
+        u128::read(r).await.map(Self)
+    }
+
+    async fn write<W: AsyncWrite + Unpin + Send>(&self, w: W) -> io::Result<()> {
+        self.0.write(w).await
+    }
+}
+
+impl Decode<'_> for Uuid {
+    fn decode(buf: &mut &[u8]) -> io::Result<Self> {
+        u128::decode(buf).map(Self)
+    }
+}
+
+impl McType for bool {
+    async fn read<R: AsyncRead + Unpin + Send>(r: R) -> io::Result<Self> {
+        match u8::read(r).await? {
+            0x00 => Ok(false),
+            0x01 => Ok(true),
+            b => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("invalid boolean: 0x{b:02x}"),
+            )),
+        }
+    }
+
+    async fn write<W: AsyncWrite + Unpin + Send>(&self, w: W) -> io::Result<()> {
+        u8::from(*self).write(w).await
+    }
+}
+
+impl Decode<'_> for bool {
+    fn decode(buf: &mut &[u8]) -> io::Result<Self> {
+        match u8::decode(buf)? {
+            0x00 => Ok(false),
+            0x01 => Ok(true),
+            b => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("invalid boolean: 0x{b:02x}"),
+            )),
+        }
+    }
+}
+
+/// A namespaced key like `minecraft:stone`, the namespace defaults to `minecraft`.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Hash)]
+pub struct Identifier<'s>(String<'s>);
+
+impl<'s> Identifier<'s> {
+    pub fn new(s: String<'s>) -> io::Result<Self> {
+        let (namespace, path) = s.split_once(':').unwrap_or(("minecraft", &s));
+        let valid = |s: &str, extra: &[u8]| {
+            s.bytes().all(|b| {
+                b.is_ascii_lowercase()
+                    || b.is_ascii_digit()
+                    || b"_-.".contains(&b)
+                    || extra.contains(&b)
+            })
+        };
+        if !valid(namespace, b"") || !valid(path, b"/") {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("invalid identifier: {s:?}"),
+            ));
+        }
+        Ok(Self(s))
+    }
+
+    pub fn namespace(&self) -> &str {
+        self.0
+            .split_once(':')
+            .map_or("minecraft", |(namespace, _)| namespace)
+    }
+
+    pub fn path(&self) -> &str {
+        self.0.split_once(':').map_or(&self.0, |(_, path)| path)
+    }
+
+    pub fn into_owned(self) -> Identifier<'static> {
+        Identifier(self.0.into_owned())
+    }
+}
+
+impl fmt::Display for Identifier<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.namespace(), self.path())
+    }
+}
+
+impl McType for Identifier<'_> {
+    async fn read<R: AsyncRead + Unpin + Send>(r: R) -> io::Result<Self> {
+        Self::new(String::read(r).await?)
+    }
+
+    async fn write<W: AsyncWrite + Unpin + Send>(&self, w: W) -> io::Result<()> {
+        self.0.write(w).await
+    }
+}
+
+impl<'a> Decode<'a> for Identifier<'a> {
+    fn decode(buf: &mut &'a [u8]) -> io::Result<Self> {
+        Self::new(String::decode(buf)?)
+    }
+}
+
+/// A block position, packed in a long as 26 bits of x, 26 of z and 12 of y.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, Default)]
+pub struct Position {
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+}
+
+impl Position {
+    fn pack(self) -> i64 {
+        (i64::from(self.x) & 0x3ff_ffff) << 38
+            | (i64::from(self.z) & 0x3ff_ffff) << 12
+            | i64::from(self.y) & 0xfff
+    }
+
+    fn unpack(long: i64) -> Self {
+        Self {
+            x: (long >> 38) as i32,
+            y: (long << 52 >> 52) as i32,
+            z: (long << 26 >> 38) as i32,
+        }
+    }
+}
+
+impl McType for Position {
+    async fn read<R: AsyncRead + Unpin + Send>(r: R) -> io::Result<Self> {
+        i64::read(r).await.map(Self::unpack)
+    }
+
+    async fn write<W: AsyncWrite + Unpin + Send>(&self, w: W) -> io::Result<()> {
+        self.pack().write(w).await
+    }
+}
+
+impl Decode<'_> for Position {
+    fn decode(buf: &mut &[u8]) -> io::Result<Self> {
+        i64::decode(buf).map(Self::unpack)
+    }
+}
+
+/// A rotation in steps of 1/256 of a full turn.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, Default, McType)]
+pub struct Angle(pub u8);
+
+impl Angle {
+    pub fn from_degrees(degrees: f32) -> Self {
+        Self((degrees.rem_euclid(360.) / 360. * 256.) as u8)
+    }
+
+    pub fn degrees(self) -> f32 {
+        f32::from(self.0) * 360. / 256.
+    }
+}
+
+/// A set of bits sent as a length prefixed array of longs, bit `i` being bit `i % 64` of the
+/// `i / 64`th long.
+#[derive(Debug, PartialEq, Eq, Clone, Hash, Default, McType)]
+pub struct BitSet(#[mc(length_prefixed)] pub Vec<i64>);
+
+impl BitSet {
+    pub fn get(&self, i: usize) -> bool {
+        self.0
+            .get(i / 64)
+            .is_some_and(|long| long >> (i % 64) & 1 != 0)
+    }
+
+    pub fn set(&mut self, i: usize, value: bool) {
+        if self.0.len() <= i / 64 {
+            self.0.resize(i / 64 + 1, 0);
+        }
+        let bit = 1 << (i % 64);
+        if value {
+            self.0[i / 64] |= bit;
+        } else {
+            self.0[i / 64] &= !bit;
+        }
+    }
+}
+
+/// A set of `BITS` bits sent as `BITS.div_ceil(8)` bytes, bit `i` being bit `i % 8` of the
+/// `i / 8`th byte.
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub struct FixedBitSet<const BITS: usize>(Vec<u8>);
+
+impl<const BITS: usize> Default for FixedBitSet<BITS> {
+    fn default() -> Self {
+        Self(vec![0; BITS.div_ceil(8)])
+    }
+}
+
+impl<const BITS: usize> FixedBitSet<BITS> {
+    pub fn get(&self, i: usize) -> bool {
+        i < BITS && self.0[i / 8] >> (i % 8) & 1 != 0
+    }
+
+    /// # Panics
+    /// If `i` isn't smaller than `BITS`.
+    pub fn set(&mut self, i: usize, value: bool) {
+        assert!(i < BITS, "bit {i} out of a {BITS} bit set");
+        if value {
+            self.0[i / 8] |= 1 << (i % 8);
+        } else {
+            self.0[i / 8] &= !(1 << (i % 8));
+        }
+    }
+}
+
+impl<const BITS: usize> McType for FixedBitSet<BITS> {
+    async fn read<R: AsyncRead + Unpin + Send>(mut r: R) -> io::Result<Self> {
+        let mut set = Self::default();
+        r.read_exact(&mut set.0).await?;
+        Ok(set)
+    }
+
+    async fn write<W: AsyncWrite + Unpin + Send>(&self, mut w: W) -> io::Result<()> {
+        w.write_all(&self.0).await
+    }
+}
+
+impl<const BITS: usize> Decode<'_> for FixedBitSet<BITS> {
+    fn decode(buf: &mut &[u8]) -> io::Result<Self> {
+        Ok(Self(take(buf, BITS.div_ceil(8))?.to_vec()))
+    }
+}
+
+/// A fixed length array, its elements one after the other.
+impl<T: McType + Send, const N: usize> McType for [T; N] {
+    async fn read<R: AsyncRead + Unpin + Send>(mut r: R) -> io::Result<Self> {
+        let mut elements = Vec::with_capacity(N);
+        for _ in 0..N {
+            elements.push(T::read(&mut r).await?);
+        }
+        Ok(elements
+            .try_into()
+            .unwrap_or_else(|_| unreachable!("read {N} elements")))
+    }
+
+    async fn write<W: AsyncWrite + Unpin + Send>(&self, mut w: W) -> io::Result<()> {
+        for element in self {
+            element.write(&mut w).await?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a, T: Decode<'a>, const N: usize> Decode<'a> for [T; N] {
+    fn decode(buf: &mut &'a [u8]) -> io::Result<Self> {
+        let elements = (0..N)
+            .map(|_| T::decode(buf))
+            .collect::<io::Result<Vec<_>>>()?;
+        Ok(elements
+            .try_into()
+            .unwrap_or_else(|_| unreachable!("decoded {N} elements")))
+    }
+}
+
+/// A prefixed array, its length as a [`VarInt`] followed by its elements. The same as a
+/// `#[mc(length_prefixed)]` field.
+impl<T: McType + Send> McType for Vec<T> {
+    async fn read<R: AsyncRead + Unpin + Send>(mut r: R) -> io::Result<Self> {
+        let len = VarInt::read(&mut r).await?.length()?;
+        let mut elements = Vec::with_capacity(len.min(1024));
+        for _ in 0..len {
+            elements.push(T::read(&mut r).await?);
+        }
+        Ok(elements)
+    }
+
+    async fn write<W: AsyncWrite + Unpin + Send>(&self, mut w: W) -> io::Result<()> {
+        VarInt::try_from(self.len())
+            .map_err(io::Error::other)?
+            .write(&mut w)
+            .await?;
+        for element in self {
+            element.write(&mut w).await?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a, T: Decode<'a>> Decode<'a> for Vec<T> {
+    fn decode(buf: &mut &'a [u8]) -> io::Result<Self> {
+        let len = VarInt::decode(buf)?.length()?;
+        let mut elements = Vec::with_capacity(len.min(buf.len()));
+        for _ in 0..len {
+            elements.push(T::decode(buf)?);
+        }
+        Ok(elements)
+    }
+}
+
+/// A prefixed optional, a boolean followed by the value if it's `true`. The same as an
+/// `#[mc(optional)]` field.
+impl<T: McType + Send> McType for Option<T> {
+    async fn read<R: AsyncRead + Unpin + Send>(mut r: R) -> io::Result<Self> {
+        if bool::read(&mut r).await? {
+            Ok(Some(T::read(r).await?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    async fn write<W: AsyncWrite + Unpin + Send>(&self, mut w: W) -> io::Result<()> {
+        self.is_some().write(&mut w).await?;
+        match self {
+            Some(value) => value.write(w).await,
+            None => Ok(()),
+        }
+    }
+}
+
+impl<'a, T: Decode<'a>> Decode<'a> for Option<T> {
+    fn decode(buf: &mut &'a [u8]) -> io::Result<Self> {
+        if bool::decode(buf)? {
+            T::decode(buf).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+}
 
 #[cfg(test)]
 mod test {
@@ -320,31 +917,41 @@ mod test {
     use std::io::Cursor;
 
     proptest! {
-        // #[tokio::test]
-        // async fn test_var_int(int in i32::MIN..i32::MAX) {
-        //     let var_int = VarInt { int };
-        //     let mut buffer = Vec::<u8>::new();
-        //     let mut cursor = Cursor::new(&mut buffer);
+        #[test]
+        fn test_var_int(int in i32::MIN..=i32::MAX) {
+            let var_int = VarInt { int };
+            let mut buffer = Vec::<u8>::new();
 
-        //     var_int.write(&mut cursor).await;
-        //     let var_int2 = VarInt::read(Cursor::new(&buffer)).unwrap();
-        //     assert_eq!(var_int, var_int2);
+            var_int.write_blocking(&mut buffer).unwrap();
+            let var_int2 = VarInt::read_blocking(&buffer[..]).unwrap();
+            assert_eq!(var_int, var_int2);
+            assert_eq!(VarInt::decode(&mut &buffer[..]).unwrap(), var_int);
 
-        //     assert_eq!(var_int.len(), buffer.len());
-        // }
+            assert_eq!(var_int.len(), buffer.len());
+        }
+
+        #[test]
+        fn test_var_long(int in i64::MIN..=i64::MAX) {
+            let var_long = VarLong { int };
+            let mut buffer = Vec::<u8>::new();
 
-        // #[test]
-        // fn test_var_long(int in i64::MIN..i64::MAX) {
-        //     let var_long = VarLong { int };
-        //     let mut buffer = Vec::<u8>::new();
-        //     let mut cursor = Cursor::new(&mut buffer);
+            var_long.write_blocking(&mut buffer).unwrap();
+            let var_long2 = VarLong::read_blocking(&buffer[..]).unwrap();
+            assert_eq!(var_long, var_long2);
+            assert_eq!(VarLong::decode(&mut &buffer[..]).unwrap(), var_long);
 
-        //     var_long.write(&mut cursor).unwrap();
-        //     let var_long2 = VarLong::read(Cursor::new(&buffer)).unwrap();
-        //     assert_eq!(var_long, var_long2);
+            assert_eq!(var_long.len(), buffer.len());
+        }
 
-        //     assert_eq!(var_long.len(), buffer.len());
-        // }
+        #[test]
+        fn test_string(s in ".*") {
+            let string = String::from(s);
+            let mut buffer = Vec::<u8>::new();
+
+            string.write_blocking(&mut buffer).unwrap();
+            assert_eq!(String::read_blocking(&buffer[..]).unwrap(), string);
+            assert_eq!(String::decode(&mut &buffer[..]).unwrap(), string);
+        }
     }
 
     #[tokio::test]
@@ -416,4 +1023,249 @@ mod test {
             assert_eq!(VarLong { int }.len(), bytes.len());
         }
     }
+
+    /// Writes `value`, checks it's encoded as `bytes` and reads it back with both codecs.
+    fn roundtrip<'a, T>(value: T, bytes: &'a [u8])
+    where
+        T: McType + Decode<'a> + PartialEq + fmt::Debug,
+    {
+        let mut buffer = Vec::new();
+        value.write_blocking(&mut buffer).unwrap();
+        assert_eq!(buffer, bytes, "{value:?} was not encoded correctly");
+        assert_eq!(T::read_blocking(bytes).unwrap(), value);
+        let mut buf = bytes;
+        assert_eq!(T::decode(&mut buf).unwrap(), value);
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn primitives() {
+        roundtrip(true, &[0x01]);
+        roundtrip(1.5f32, &[0x3f, 0xc0, 0, 0]);
+        roundtrip(-2.0f64, &[0xc0, 0, 0, 0, 0, 0, 0, 0]);
+        roundtrip(Uuid(0x0123), &[[0; 14].as_slice(), &[0x01, 0x23]].concat());
+        roundtrip(Angle::from_degrees(-90.), &[192]);
+        assert_eq!(Angle(64).degrees(), 90.);
+        // from wiki.vg
+        roundtrip(
+            Position {
+                x: 18357644,
+                y: 831,
+                z: -20882616,
+            },
+            &[0x46, 0x07, 0x63, 0x2c, 0x15, 0xb4, 0x83, 0x3f],
+        );
+        roundtrip(
+            Position {
+                x: -1,
+                y: -64,
+                z: 0,
+            },
+            &[0xff, 0xff, 0xff, 0xc0, 0, 0, 0x0f, 0xc0],
+        );
+    }
+
+    #[test]
+    fn identifiers() {
+        let id = Identifier::new(String::borrowed("stone")).unwrap();
+        assert_eq!((id.namespace(), id.path()), ("minecraft", "stone"));
+        assert_eq!(id.to_string(), "minecraft:stone");
+        let id = Identifier::new(String::borrowed("mccli:textures/icon.png")).unwrap();
+        assert_eq!((id.namespace(), id.path()), ("mccli", "textures/icon.png"));
+        roundtrip(id, b"\x17mccli:textures/icon.png");
+
+        for invalid in ["Stone", "a/b:c", "minecraft:spaced out"] {
+            assert!(
+                Identifier::new(String::borrowed(invalid)).is_err(),
+                "{invalid}"
+            );
+        }
+        assert!(Identifier::read_blocking(&b"\x03A:b"[..]).is_err());
+    }
+
+    #[test]
+    fn bit_sets() {
+        let mut set = BitSet::default();
+        set.set(1, true);
+        set.set(64, true);
+        assert!(set.get(1) && set.get(64) && !set.get(2) && !set.get(1000));
+        roundtrip(
+            set.clone(),
+            &[
+                [2, 0, 0, 0, 0, 0, 0, 0, 2].as_slice(),
+                &[0, 0, 0, 0, 0, 0, 0, 1],
+            ]
+            .concat(),
+        );
+        set.set(64, false);
+        assert!(!set.get(64));
+
+        let mut fixed = FixedBitSet::<12>::default();
+        fixed.set(0, true);
+        fixed.set(9, true);
+        assert!(fixed.get(9) && !fixed.get(12));
+        roundtrip(fixed, &[0x01, 0x02]);
+    }
+
+    #[test]
+    fn arrays_and_optionals() {
+        roundtrip([1u16, 2], &[0, 1, 0, 2]);
+        roundtrip(
+            vec![VarInt::from(1), VarInt::from(300)],
+            &[2, 1, 0xac, 0x02],
+        );
+        roundtrip(Vec::<i64>::new(), &[0]);
+        roundtrip(Some(7u8), &[1, 7]);
+        roundtrip(None::<u8>, &[0]);
+        assert!(Vec::<u8>::read_blocking(&[5, 1][..]).is_err());
+    }
+
+    #[test]
+    fn slots() {
+        roundtrip(Slot::default(), &[0]);
+        roundtrip(
+            Slot {
+                count: 64,
+                item_id: 1,
+                added_components: Vec::new(),
+                removed_components: vec![VarInt::from(5)],
+            },
+            &[64, 1, 0, 1, 5],
+        );
+        let named = Slot {
+            count: 1,
+            item_id: 1,
+            added_components: vec![Component {
+                id: 5.into(),
+                data: vec![0x08, 0x00, 0x01, b'a'],
+            }],
+            removed_components: Vec::new(),
+        };
+        assert_eq!(named.added_components[0].name(), Some("custom_name"));
+        roundtrip(named.clone(), &[1, 1, 1, 0, 0x05, 0x08, 0x00, 0x01, b'a']);
+
+        // an enchanted item, then a bundle holding a damaged one
+        let enchanted = Slot {
+            count: 1,
+            item_id: 2,
+            added_components: vec![Component {
+                id: 10.into(),
+                data: vec![1, 9, 3, 1],
+            }],
+            removed_components: vec![VarInt::from(3)],
+        };
+        let bundle = Slot {
+            count: 1,
+            item_id: 3,
+            added_components: vec![Component {
+                id: 40.into(),
+                data: vec![2, 1, 4, 1, 0, 3, 7, 0],
+            }],
+            removed_components: Vec::new(),
+        };
+        roundtrip(
+            vec![enchanted, Slot::default(), bundle, named],
+            &[
+                4, // slots
+                1, 2, 1, 1, 10, 1, 9, 3, 1, 3, // enchanted
+                0, // empty
+                1, 3, 1, 0, 40, 2, 1, 4, 1, 0, 3, 7, 0, // bundle
+                1, 1, 1, 0, 0x05, 0x08, 0x00, 0x01, b'a', // named
+            ],
+        );
+
+        // an unknown component, and one cut short
+        assert!(Slot::read_blocking(&[1, 1, 1, 0, 67, 0][..]).is_err());
+        assert!(Slot::decode(&mut &[1, 1, 1, 0, 10, 1, 9][..]).is_err());
+
+        // bundles in bundles
+        let mut nested = vec![0];
+        for _ in 0..32 {
+            nested = [&[1, 3, 1, 0, 40, 1][..], &nested].concat();
+        }
+        assert!(Slot::read_blocking(&nested[..]).is_err());
+    }
+
+    #[test]
+    fn hostile_lengths() {
+        // claims 2 GiB and sends 2 bytes
+        let mut bytes = Vec::new();
+        VarInt::from(i32::MAX).write_blocking(&mut bytes).unwrap();
+        bytes.extend(b"hi");
+        assert!(String::read_blocking(&bytes[..]).is_err());
+        assert!(String::decode(&mut &bytes[..]).is_err());
+        assert!(ByteArray::read_blocking(&bytes[..]).is_err());
+        assert!(Vec::<u8>::read_blocking(&bytes[..]).is_err());
+
+        let mut bytes = Vec::new();
+        String::borrowed("héllo")
+            .write_blocking(&mut bytes)
+            .unwrap();
+        let limits = Limits {
+            max_string: 4,
+            ..Limits::default()
+        };
+        assert!(
+            limits
+                .sync_scope(|| String::decode(&mut &bytes[..]))
+                .is_err()
+        );
+        assert!(
+            limits
+                .sync_scope(|| String::read_blocking(&bytes[..]))
+                .is_err()
+        );
+        let limits = Limits {
+            max_string: 5,
+            ..limits
+        };
+        assert_eq!(
+            limits
+                .sync_scope(|| String::decode(&mut &bytes[..]))
+                .unwrap(),
+            String::borrowed("héllo")
+        );
+
+        // the protocol caps usernames at 16 characters
+        let mut bytes = Vec::new();
+        crate::packet::login::LoginStart {
+            name: String::borrowed("a_very_long_username").into_owned(),
+            uuid: Uuid(0),
+        }
+        .write_blocking(&mut bytes)
+        .unwrap();
+        let error = crate::packet::login::LoginStart::read_blocking(&bytes[..]).unwrap_err();
+        assert_eq!(
+            crate::Error::from(error).to_string(),
+            "decoding LoginStart.name"
+        );
+    }
+
+    #[test]
+    fn status_keeps_modded_and_unknown_fields() {
+        let json = serde_json::json!({
+            "version": { "name": "1.20.1", "protocol": 763 },
+            "players": { "max": 20, "online": 0 },
+            "description": "modded",
+            "preventsChatReports": true,
+            "modinfo": { "type": "FML", "modList": [{ "modid": "mcp", "version": "9.05" }] },
+            "forgeData": {
+                "channels": [],
+                "mods": [{ "modId": "neoforge", "modmarker": "20.4.237" }],
+                "fmlNetworkVersion": 3,
+            },
+            "isModded": true,
+        });
+        let status = serde_json::from_value::<server::Status>(json.clone()).unwrap();
+        assert_eq!(status.prevents_chat_reports, Some(true));
+        assert_eq!(status.previews_chat, None);
+        assert_eq!(status.modinfo.as_ref().unwrap().mod_list[0].id, "mcp");
+        assert_eq!(status.forge_data.as_ref().unwrap().mods[0].id, "neoforge");
+        assert_eq!(status.extra["isModded"], true);
+
+        let reserialized = serde_json::to_value(&status).unwrap();
+        assert_eq!(reserialized["isModded"], true);
+        assert_eq!(reserialized["forgeData"]["mods"], json["forgeData"]["mods"]);
+        assert_eq!(reserialized["preventsChatReports"], true);
+    }
 }
@@ -0,0 +1,339 @@
+//! Named Binary Tag, the format of item data, registries and, since 1.20.3, text components.
+//!
+//! Since 1.20.2 the root tag sent over the network has no name, only its type. Strings are in
+//! Java's modified UTF-8: `\0` takes two bytes and characters outside the BMP are encoded as
+//! surrogate pairs.
+
+use super::{Decode, McType, read_bytes};
+use std::{future::Future, io, pin::Pin};
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt as _};
+
+/// How deep lists and compounds can be nested, lower than vanilla's 512. Every level is a boxed
+/// future and a tag of its own, so this bounds the allocations and work a hostile peer can cause
+/// with a few bytes per level.
+const MAX_DEPTH: usize = 128;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Nbt {
+    /// No tag, sent in place of optional NBT that's absent.
+    End,
+    Byte(i8),
+    Short(i16),
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    ByteArray(Vec<i8>),
+    String(String),
+    /// All elements have the same type.
+    List(Vec<Nbt>),
+    /// Named tags, in the order they were read.
+    Compound(Vec<(String, Nbt)>),
+    IntArray(Vec<i32>),
+    LongArray(Vec<i64>),
+}
+
+impl Nbt {
+    fn id(&self) -> u8 {
+        match self {
+            Self::End => 0,
+            Self::Byte(_) => 1,
+            Self::Short(_) => 2,
+            Self::Int(_) => 3,
+            Self::Long(_) => 4,
+            Self::Float(_) => 5,
+            Self::Double(_) => 6,
+            Self::ByteArray(_) => 7,
+            Self::String(_) => 8,
+            Self::List(_) => 9,
+            Self::Compound(_) => 10,
+            Self::IntArray(_) => 11,
+            Self::LongArray(_) => 12,
+        }
+    }
+
+    /// The tag called `name`, if this is a compound that has one.
+    pub fn get(&self, name: &str) -> Option<&Nbt> {
+        match self {
+            Self::Compound(tags) => tags.iter().find(|(n, _)| n == name).map(|(_, t)| t),
+            _ => None,
+        }
+    }
+
+    fn write_payload(&self, out: &mut Vec<u8>) -> io::Result<()> {
+        match self {
+            Self::End => {}
+            Self::Byte(b) => out.push(*b as u8),
+            Self::Short(s) => out.extend(s.to_be_bytes()),
+            Self::Int(i) => out.extend(i.to_be_bytes()),
+            Self::Long(l) => out.extend(l.to_be_bytes()),
+            Self::Float(f) => out.extend(f.to_be_bytes()),
+            Self::Double(d) => out.extend(d.to_be_bytes()),
+            Self::ByteArray(bytes) => {
+                write_len(bytes.len(), out)?;
+                out.extend(bytes.iter().map(|&b| b as u8));
+            }
+            Self::String(s) => write_string(s, out)?,
+            Self::List(tags) => {
+                let id = tags.first().map_or(0, Nbt::id);
+                if tags.iter().any(|t| t.id() != id) {
+                    return Err(invalid_data("list elements have different types"));
+                }
+                out.push(id);
+                write_len(tags.len(), out)?;
+                for tag in tags {
+                    tag.write_payload(out)?;
+                }
+            }
+            Self::Compound(tags) => {
+                for (name, tag) in tags {
+                    if let Self::End = tag {
+                        return Err(invalid_data("compounds can't hold end tags"));
+                    }
+                    out.push(tag.id());
+                    write_string(name, out)?;
+                    tag.write_payload(out)?;
+                }
+                out.push(0);
+            }
+            Self::IntArray(ints) => {
+                write_len(ints.len(), out)?;
+                out.extend(ints.iter().flat_map(|i| i.to_be_bytes()));
+            }
+            Self::LongArray(longs) => {
+                write_len(longs.len(), out)?;
+                out.extend(longs.iter().flat_map(|l| l.to_be_bytes()));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn invalid_data(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
+fn write_len(len: usize, out: &mut Vec<u8>) -> io::Result<()> {
+    let len = i32::try_from(len).map_err(|_| invalid_data("nbt array too long"))?;
+    out.extend(len.to_be_bytes());
+    Ok(())
+}
+
+fn write_string(s: &str, out: &mut Vec<u8>) -> io::Result<()> {
+    let bytes = to_modified_utf8(s);
+    let len = u16::try_from(bytes.len()).map_err(|_| invalid_data("nbt string too long"))?;
+    out.extend(len.to_be_bytes());
+    out.extend(bytes);
+    Ok(())
+}
+
+fn to_modified_utf8(s: &str) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(s.len());
+    for unit in s.encode_utf16() {
+        match unit {
+            0x0001..=0x007f => bytes.push(unit as u8),
+            0x0000 | 0x0080..=0x07ff => {
+                bytes.push(0xc0 | (unit >> 6) as u8);
+                bytes.push(0x80 | (unit & 0x3f) as u8);
+            }
+            _ => {
+                bytes.push(0xe0 | (unit >> 12) as u8);
+                bytes.push(0x80 | (unit >> 6 & 0x3f) as u8);
+                bytes.push(0x80 | (unit & 0x3f) as u8);
+            }
+        }
+    }
+    bytes
+}
+
+fn from_modified_utf8(bytes: &[u8]) -> io::Result<String> {
+    let mut units = Vec::with_capacity(bytes.len());
+    let mut bytes = bytes.iter();
+    let continuation = |bytes: &mut std::slice::Iter<u8>| match bytes.next() {
+        Some(b) if b & 0xc0 == 0x80 => Ok(u16::from(b & 0x3f)),
+        _ => Err(invalid_data("invalid modified utf-8")),
+    };
+    while let Some(&b) = bytes.next() {
+        let unit = match b {
+            0x00..=0x7f => u16::from(b),
+            _ if b & 0xe0 == 0xc0 => u16::from(b & 0x1f) << 6 | continuation(&mut bytes)?,
+            _ if b & 0xf0 == 0xe0 => {
+                u16::from(b & 0x0f) << 12
+                    | continuation(&mut bytes)? << 6
+                    | continuation(&mut bytes)?
+            }
+            _ => return Err(invalid_data("invalid modified utf-8")),
+        };
+        units.push(unit);
+    }
+    String::from_utf16(&units).map_err(|_| invalid_data("unpaired surrogate in nbt string"))
+}
+
+async fn read_len<R: AsyncRead + Unpin + Send>(r: &mut R) -> io::Result<usize> {
+    usize::try_from(i32::read(r).await?).map_err(|_| invalid_data("negative nbt length"))
+}
+
+async fn read_string<R: AsyncRead + Unpin + Send>(r: &mut R) -> io::Result<String> {
+    let len = u16::read(&mut *r).await?;
+    from_modified_utf8(&read_bytes(r, usize::from(len)).await?)
+}
+
+fn read_payload<R: AsyncRead + Unpin + Send>(
+    r: &mut R,
+    id: u8,
+    depth: usize,
+) -> Pin<Box<dyn Future<Output = io::Result<Nbt>> + Send + '_>> {
+    Box::pin(async move {
+        if depth > MAX_DEPTH {
+            return Err(invalid_data("nbt nested too deep"));
+        }
+        Ok(match id {
+            0 => Nbt::End,
+            1 => Nbt::Byte(i8::read(r).await?),
+            2 => Nbt::Short(i16::read(r).await?),
+            3 => Nbt::Int(i32::read(r).await?),
+            4 => Nbt::Long(i64::read(r).await?),
+            5 => Nbt::Float(f32::read(r).await?),
+            6 => Nbt::Double(f64::read(r).await?),
+            7 => {
+                let len = read_len(r).await?;
+                Nbt::ByteArray(
+                    read_bytes(r, len)
+                        .await?
+                        .into_iter()
+                        .map(|b| b as i8)
+                        .collect(),
+                )
+            }
+            8 => Nbt::String(read_string(r).await?),
+            9 => {
+                let id = u8::read(&mut *r).await?;
+                let len = read_len(r).await?;
+                if id == 0 && len > 0 {
+                    return Err(invalid_data("list of end tags"));
+                }
+                let mut tags = Vec::with_capacity(len.min(1024));
+                for _ in 0..len {
+                    tags.push(read_payload(r, id, depth + 1).await?);
+                }
+                Nbt::List(tags)
+            }
+            10 => {
+                let mut tags = Vec::new();
+                loop {
+                    let id = u8::read(&mut *r).await?;
+                    if id == 0 {
+                        break;
+                    }
+                    let name = read_string(r).await?;
+                    tags.push((name, read_payload(r, id, depth + 1).await?));
+                }
+                Nbt::Compound(tags)
+            }
+            11 => {
+                let len = read_len(r).await?;
+                let mut ints = Vec::with_capacity(len.min(1024));
+                for _ in 0..len {
+                    ints.push(i32::read(&mut *r).await?);
+                }
+                Nbt::IntArray(ints)
+            }
+            12 => {
+                let len = read_len(r).await?;
+                let mut longs = Vec::with_capacity(len.min(1024));
+                for _ in 0..len {
+                    longs.push(i64::read(&mut *r).await?);
+                }
+                Nbt::LongArray(longs)
+            }
+            id => return Err(invalid_data(format!("invalid nbt tag type: {id}"))),
+        })
+    })
+}
+
+impl McType for Nbt {
+    async fn read<R: AsyncRead + Unpin + Send>(mut r: R) -> io::Result<Self> {
+        let id = u8::read(&mut r).await?;
+        read_payload(&mut r, id, 0).await
+    }
+
+    async fn write<W: AsyncWrite + Unpin + Send>(&self, mut w: W) -> io::Result<()> {
+        let mut buffer = vec![self.id()];
+        self.write_payload(&mut buffer)?;
+        w.write_all(&buffer).await
+    }
+}
+
+impl Decode<'_> for Nbt {
+    fn decode(buf: &mut &[u8]) -> io::Result<Self> {
+        Self::read_blocking(buf)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn roundtrip() {
+        let nbt = Nbt::Compound(vec![
+            ("byte".to_owned(), Nbt::Byte(-1)),
+            ("short".to_owned(), Nbt::Short(300)),
+            ("long".to_owned(), Nbt::Long(i64::MIN)),
+            ("float".to_owned(), Nbt::Float(0.5)),
+            ("double".to_owned(), Nbt::Double(-2.25)),
+            ("bytes".to_owned(), Nbt::ByteArray(vec![1, -2, 3])),
+            (
+                "name\0with nul".to_owned(),
+                Nbt::String("ol\u{e1} \u{1f600}".to_owned()),
+            ),
+            ("list".to_owned(), Nbt::List(vec![Nbt::Int(1), Nbt::Int(2)])),
+            ("empty".to_owned(), Nbt::List(vec![])),
+            ("ints".to_owned(), Nbt::IntArray(vec![i32::MAX])),
+            ("longs".to_owned(), Nbt::LongArray(vec![7, 8])),
+            (
+                "nested".to_owned(),
+                Nbt::Compound(vec![("x".to_owned(), Nbt::Int(3))]),
+            ),
+        ]);
+        let mut buffer = Vec::new();
+        nbt.write_blocking(&mut buffer).unwrap();
+        assert_eq!(buffer[0], 10);
+        let mut buf = &buffer[..];
+        assert_eq!(Nbt::decode(&mut buf).unwrap(), nbt);
+        assert!(buf.is_empty());
+        assert_eq!(
+            nbt.get("nested").and_then(|n| n.get("x")),
+            Some(&Nbt::Int(3))
+        );
+    }
+
+    #[test]
+    fn modified_utf8() {
+        assert_eq!(to_modified_utf8("a\0"), b"a\xc0\x80");
+        // a surrogate pair, 3 bytes each
+        assert_eq!(to_modified_utf8("\u{1f600}"), b"\xed\xa0\xbd\xed\xb8\x80");
+        assert_eq!(
+            from_modified_utf8(b"\xed\xa0\xbd\xed\xb8\x80").unwrap(),
+            "\u{1f600}"
+        );
+        assert!(from_modified_utf8(b"\xed\xa0\xbd").is_err());
+        assert!(from_modified_utf8(b"\xc0").is_err());
+    }
+
+    #[test]
+    fn malformed() {
+        // a list of lists nested past the limit
+        let mut deep = Vec::new();
+        for _ in 0..=MAX_DEPTH + 1 {
+            deep.extend([9, 0, 0, 0, 1]);
+        }
+        deep.insert(0, 9);
+        assert!(Nbt::read_blocking(&deep[..]).is_err());
+        // a byte array claiming to be huge
+        assert!(Nbt::read_blocking(&[7, 0x7f, 0xff, 0xff, 0xff, 1][..]).is_err());
+        assert!(Nbt::read_blocking(&[13][..]).is_err());
+        let mixed = Nbt::List(vec![Nbt::Int(1), Nbt::Byte(1)]);
+        assert!(mixed.write_blocking(Vec::new()).is_err());
+    }
+}
@@ -0,0 +1,288 @@
+//! The `forgeData` field that Forge and NeoForge servers add to the status.
+//!
+//! Up to 1.18.1 the mods and channels are listed as json. Since then they are packed in the `d`
+//! field to keep the response small: a binary payload where every UTF-16 code unit carries 15
+//! bits, the first two holding the payload's length.
+
+use serde::{Deserialize, Serialize};
+use std::io;
+
+/// The marker of mods that clients don't need to have, instead of a version.
+pub const SERVER_ONLY_MARKER: &str = "OHNOES\u{1f631}\u{1f631}\u{1f631}\u{1f631}\u{1f631}\u{1f631}\u{1f631}\u{1f631}\u{1f631}\u{1f631}\u{1f631}\u{1f631}\u{1f631}\u{1f631}\u{1f631}\u{1f631}\u{1f631}";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct ForgeData {
+    #[serde(default)]
+    pub channels: Vec<ForgeChannel>,
+    #[serde(default)]
+    pub mods: Vec<ForgeMod>,
+    #[serde(rename = "fmlNetworkVersion")]
+    pub fml_network_version: u32,
+    /// Whether the server left out mods or channels to keep the response small.
+    #[serde(default)]
+    pub truncated: bool,
+    /// The packed mods and channels, see [`ForgeData::decode`].
+    #[serde(rename = "d", skip_serializing_if = "Option::is_none")]
+    pub packed: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ForgeChannel {
+    /// The channel's resource location, e.g. `minecraft:register`.
+    pub res: String,
+    pub version: String,
+    pub required: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ForgeMod {
+    #[serde(rename = "modId")]
+    pub id: String,
+    /// The mod's version or [`SERVER_ONLY_MARKER`].
+    #[serde(rename = "modmarker")]
+    pub marker: String,
+}
+
+impl ForgeMod {
+    pub fn is_server_only(&self) -> bool {
+        self.marker.starts_with("OHNOES")
+    }
+}
+
+impl ForgeData {
+    /// Unpacks the `d` field into `mods`, `channels` and `truncated`. Data without a `d` field is
+    /// returned as is.
+    pub fn decode(&self) -> io::Result<Self> {
+        let Some(packed) = &self.packed else {
+            return Ok(self.clone());
+        };
+        let bytes = unpack(packed)?;
+        let mut payload = Payload {
+            bytes: &bytes,
+            position: 0,
+        };
+
+        let truncated = payload.bool()?;
+        let mut mods = Vec::new();
+        let mut channels = Vec::new();
+        for _ in 0..payload.u16()? {
+            let flags = payload.var_int()?;
+            let server_only = flags & 1 != 0;
+            let id = payload.string()?;
+            let marker = if server_only {
+                SERVER_ONLY_MARKER.to_owned()
+            } else {
+                payload.string()?
+            };
+            for _ in 0..flags >> 1 {
+                let name = payload.string()?;
+                channels.push(ForgeChannel {
+                    res: format!("{id}:{name}"),
+                    version: payload.string()?,
+                    required: payload.bool()?,
+                });
+            }
+            mods.push(ForgeMod { id, marker });
+        }
+        for _ in 0..payload.var_int()? {
+            channels.push(ForgeChannel {
+                res: payload.string()?,
+                version: payload.string()?,
+                required: payload.bool()?,
+            });
+        }
+
+        Ok(Self {
+            channels,
+            mods,
+            fml_network_version: self.fml_network_version,
+            truncated,
+            packed: None,
+        })
+    }
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("malformed forge data: {msg}"),
+    )
+}
+
+/// Turns the 15 bits per code unit encoding back into bytes.
+fn unpack(packed: &str) -> io::Result<Vec<u8>> {
+    let mut units = packed.encode_utf16();
+    let (Some(low), Some(high)) = (units.next(), units.next()) else {
+        return Err(invalid_data("missing length"));
+    };
+    let size = usize::from(low & 0x7fff) | usize::from(high & 0x7fff) << 15;
+
+    let mut bytes = Vec::with_capacity(size.min(packed.len() * 2));
+    let mut buffer = 0u32;
+    let mut bits = 0u32;
+    for unit in units {
+        while bits >= 8 {
+            bytes.push(buffer as u8);
+            buffer >>= 8;
+            bits -= 8;
+        }
+        buffer |= u32::from(unit & 0x7fff) << bits;
+        bits += 15;
+    }
+    while bytes.len() < size && bits > 0 {
+        bytes.push(buffer as u8);
+        buffer >>= 8;
+        bits = bits.saturating_sub(8);
+    }
+    if bytes.len() < size {
+        return Err(invalid_data("truncated"));
+    }
+    bytes.truncate(size);
+    Ok(bytes)
+}
+
+struct Payload<'p> {
+    bytes: &'p [u8],
+    position: usize,
+}
+
+impl Payload<'_> {
+    fn take(&mut self, n: usize) -> io::Result<&[u8]> {
+        let bytes = self
+            .bytes
+            .get(self.position..self.position.saturating_add(n))
+            .ok_or_else(|| invalid_data("truncated"))?;
+        self.position += n;
+        Ok(bytes)
+    }
+
+    fn bool(&mut self) -> io::Result<bool> {
+        Ok(self.take(1)?[0] != 0)
+    }
+
+    fn u16(&mut self) -> io::Result<u16> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn var_int(&mut self) -> io::Result<u32> {
+        let mut value = 0u32;
+        for shift in (0..35).step_by(7) {
+            let b = self.take(1)?[0];
+            value |= u32::from(b & 0x7f) << shift;
+            if b & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(invalid_data("VarInt too long"))
+    }
+
+    fn string(&mut self) -> io::Result<String> {
+        let len = self.var_int()? as usize;
+        String::from_utf8(self.take(len)?.to_vec()).map_err(|_| invalid_data("invalid utf-8"))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Forge's `encodeOptimized`.
+    fn pack(bytes: &[u8]) -> String {
+        let mut units = vec![(bytes.len() & 0x7fff) as u16, (bytes.len() >> 15) as u16];
+        let mut buffer = 0u32;
+        let mut bits = 0u32;
+        for &b in bytes {
+            if bits >= 15 {
+                units.push((buffer & 0x7fff) as u16);
+                buffer >>= 15;
+                bits -= 15;
+            }
+            buffer |= u32::from(b) << bits;
+            bits += 8;
+        }
+        while bits > 0 {
+            units.push((buffer & 0x7fff) as u16);
+            buffer >>= 15;
+            bits = bits.saturating_sub(15);
+        }
+        String::from_utf16(&units).unwrap()
+    }
+
+    fn string(s: &str) -> Vec<u8> {
+        let mut bytes = vec![s.len() as u8];
+        bytes.extend(s.as_bytes());
+        bytes
+    }
+
+    #[test]
+    fn unpack_roundtrip() {
+        for len in [0, 1, 2, 14, 15, 16, 100] {
+            let bytes = (0..len).map(|i| (i * 37) as u8).collect::<Vec<_>>();
+            assert_eq!(unpack(&pack(&bytes)).unwrap(), bytes, "{len}");
+        }
+    }
+
+    #[test]
+    fn decode_packed_mods() {
+        let mut payload = vec![0];
+        payload.extend(2u16.to_be_bytes());
+        // forge, with one channel
+        payload.push(1 << 1);
+        payload.extend(string("forge"));
+        payload.extend(string("52.0.1"));
+        payload.extend(string("tier_sorting"));
+        payload.extend(string("1.0"));
+        payload.push(0);
+        // a server only mod without channels
+        payload.push(1);
+        payload.extend(string("spark"));
+        // one channel that doesn't belong to a mod
+        payload.push(1);
+        payload.extend(string("minecraft:register"));
+        payload.extend(string("FML3"));
+        payload.push(1);
+
+        let data = serde_json::from_value::<ForgeData>(serde_json::json!({
+            "channels": [],
+            "mods": [],
+            "fmlNetworkVersion": 3,
+            "truncated": false,
+            "d": pack(&payload),
+        }))
+        .unwrap()
+        .decode()
+        .unwrap();
+
+        assert!(!data.truncated);
+        assert_eq!(data.mods.len(), 2);
+        assert_eq!(data.mods[0].id, "forge");
+        assert_eq!(data.mods[0].marker, "52.0.1");
+        assert!(data.mods[1].is_server_only());
+        assert_eq!(
+            data.channels,
+            [
+                ForgeChannel {
+                    res: "forge:tier_sorting".to_owned(),
+                    version: "1.0".to_owned(),
+                    required: false,
+                },
+                ForgeChannel {
+                    res: "minecraft:register".to_owned(),
+                    version: "FML3".to_owned(),
+                    required: true,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn truncated_payloads_are_errors() {
+        let data = ForgeData {
+            fml_network_version: 3,
+            packed: Some(pack(&[0, 0, 1, 2])),
+            ..Default::default()
+        };
+        assert!(data.decode().is_err());
+    }
+}
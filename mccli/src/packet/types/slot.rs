@@ -0,0 +1,745 @@
+//! Inventory slots and the data components they add to their item, as of 1.21.4.
+//!
+//! A component is sent as its id followed by data whose layout depends on the id, with nothing
+//! saying how long it is. So the data of every component is read with the layout below, which is
+//! enough to know where it ends, and kept as it was sent.
+
+use super::{Decode, Identifier, McType, String, Uuid, VarInt, nbt::Nbt};
+use std::{
+    future::Future,
+    io,
+    pin::Pin,
+    task::{Context, Poll, ready},
+};
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt as _, ReadBuf};
+
+/// What slots are read from, a trait object as each nested slot reads through a
+/// [`Recording`] of the reader of the slot it's in.
+type Reader<'r> = dyn AsyncRead + Unpin + Send + 'r;
+
+/// How deep slots can be nested in the components of other slots, e.g. a bundle in a shulker
+/// box. Every level is a boxed future and a copy of the bytes of the slots it's in.
+const MAX_DEPTH: usize = 16;
+
+/// The components in the order of their ids.
+const COMPONENTS: [&str; 67] = [
+    "custom_data",
+    "max_stack_size",
+    "max_damage",
+    "damage",
+    "unbreakable",
+    "custom_name",
+    "item_name",
+    "item_model",
+    "lore",
+    "rarity",
+    "enchantments",
+    "can_place_on",
+    "can_break",
+    "attribute_modifiers",
+    "custom_model_data",
+    "hide_additional_tooltip",
+    "hide_tooltip",
+    "repair_cost",
+    "creative_slot_lock",
+    "enchantment_glint_override",
+    "intangible_projectile",
+    "food",
+    "consumable",
+    "use_remainder",
+    "use_cooldown",
+    "damage_resistant",
+    "tool",
+    "enchantable",
+    "equippable",
+    "repairable",
+    "glider",
+    "tooltip_style",
+    "death_protection",
+    "stored_enchantments",
+    "dyed_color",
+    "map_color",
+    "map_id",
+    "map_decorations",
+    "map_post_processing",
+    "charged_projectiles",
+    "bundle_contents",
+    "potion_contents",
+    "suspicious_stew_effects",
+    "writable_book_content",
+    "written_book_content",
+    "trim",
+    "debug_stick_state",
+    "entity_data",
+    "bucket_entity_data",
+    "block_entity_data",
+    "instrument",
+    "ominous_bottle_amplifier",
+    "jukebox_playable",
+    "recipes",
+    "lodestone_tracker",
+    "firework_explosion",
+    "fireworks",
+    "profile",
+    "note_block_sound",
+    "banner_patterns",
+    "base_color",
+    "pot_decorations",
+    "container",
+    "block_state",
+    "bees",
+    "lock",
+    "container_loot",
+];
+
+/// An inventory slot, empty if `count` is 0.
+#[derive(Debug, PartialEq, Eq, Clone, Hash, Default)]
+pub struct Slot {
+    pub count: i32,
+    pub item_id: i32,
+    /// The components that change the item's default data.
+    pub added_components: Vec<Component>,
+    /// The ids of the components the item doesn't have despite its defaults.
+    pub removed_components: Vec<VarInt>,
+}
+
+/// A component a slot adds to its item.
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub struct Component {
+    pub id: VarInt,
+    /// The component's data, as it was sent.
+    pub data: Vec<u8>,
+}
+
+impl Slot {
+    pub fn is_empty(&self) -> bool {
+        self.count <= 0
+    }
+}
+
+impl Component {
+    /// The name of the component, without the `minecraft` namespace.
+    pub fn name(&self) -> Option<&'static str> {
+        name(self.id)
+    }
+}
+
+fn name(id: VarInt) -> Option<&'static str> {
+    COMPONENTS
+        .get(usize::try_from(i32::from(id)).ok()?)
+        .copied()
+}
+
+fn invalid_data(msg: impl Into<std::string::String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
+/// Keeps a copy of everything read through it.
+struct Recording<R> {
+    inner: R,
+    bytes: Vec<u8>,
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for Recording<R> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let filled = buf.filled().len();
+        let this = &mut *self;
+        ready!(Pin::new(&mut this.inner).poll_read(cx, buf))?;
+        this.bytes.extend_from_slice(&buf.filled()[filled..]);
+        Poll::Ready(Ok(()))
+    }
+}
+
+fn read_slot<'r>(
+    r: &'r mut Reader<'_>,
+    depth: usize,
+) -> Pin<Box<dyn Future<Output = io::Result<Slot>> + Send + 'r>> {
+    Box::pin(async move {
+        if depth > MAX_DEPTH {
+            return Err(invalid_data("slots nested too deep"));
+        }
+        let count = i32::from(VarInt::read(&mut *r).await?);
+        if count <= 0 {
+            return Ok(Slot::default());
+        }
+        let item_id = VarInt::read(&mut *r).await?.into();
+        let added = VarInt::read(&mut *r).await?.length()?;
+        let removed = VarInt::read(&mut *r).await?.length()?;
+        let mut added_components = Vec::with_capacity(added.min(COMPONENTS.len()));
+        for _ in 0..added {
+            let id = VarInt::read(&mut *r).await?;
+            let mut data = Recording {
+                inner: &mut *r,
+                bytes: Vec::new(),
+            };
+            skip_component(&mut data, id, depth).await?;
+            added_components.push(Component {
+                id,
+                data: data.bytes,
+            });
+        }
+        let mut removed_components = Vec::with_capacity(removed.min(COMPONENTS.len()));
+        for _ in 0..removed {
+            removed_components.push(VarInt::read(&mut *r).await?);
+        }
+        Ok(Slot {
+            count,
+            item_id,
+            added_components,
+            removed_components,
+        })
+    })
+}
+
+async fn skip<T: McType, R: AsyncRead + Unpin + Send>(r: R) -> io::Result<()> {
+    T::read(r).await.map(drop)
+}
+
+/// Reads past the data of the component `id`. Text components are read as the nbt they're sent
+/// as, slots with [`read_slot`].
+async fn skip_component(r: &mut Reader<'_>, id: VarInt, depth: usize) -> io::Result<()> {
+    let name =
+        name(id).ok_or_else(|| invalid_data(format!("invalid component id: {}", i32::from(id))))?;
+    match name {
+        "custom_data"
+        | "intangible_projectile"
+        | "map_decorations"
+        | "debug_stick_state"
+        | "entity_data"
+        | "bucket_entity_data"
+        | "block_entity_data"
+        | "recipes"
+        | "lock"
+        | "container_loot" => skip::<Nbt, _>(r).await,
+        "max_stack_size"
+        | "max_damage"
+        | "damage"
+        | "rarity"
+        | "repair_cost"
+        | "enchantable"
+        | "map_id"
+        | "map_post_processing"
+        | "ominous_bottle_amplifier"
+        | "base_color" => skip::<VarInt, _>(r).await,
+        "unbreakable" | "enchantment_glint_override" => skip::<bool, _>(r).await,
+        "custom_name" | "item_name" => skip::<Nbt, _>(r).await,
+        "item_model" | "damage_resistant" | "tooltip_style" | "note_block_sound" => {
+            skip::<Identifier, _>(r).await
+        }
+        "lore" => skip::<Vec<Nbt>, _>(r).await,
+        "enchantments" | "stored_enchantments" => skip::<Enchantments, _>(r).await,
+        "can_place_on" | "can_break" => skip::<AdventureModePredicate, _>(r).await,
+        "attribute_modifiers" => skip::<AttributeModifiers, _>(r).await,
+        "custom_model_data" => skip::<CustomModelData, _>(r).await,
+        "hide_additional_tooltip" | "hide_tooltip" | "creative_slot_lock" | "glider" => Ok(()),
+        "food" => skip::<Food, _>(r).await,
+        "consumable" => skip::<Consumable, _>(r).await,
+        "use_remainder" => read_slot(r, depth + 1).await.map(drop),
+        "use_cooldown" => skip::<UseCooldown, _>(r).await,
+        "tool" => skip::<Tool, _>(r).await,
+        "equippable" => skip::<Equippable, _>(r).await,
+        "repairable" => skip::<IdSet, _>(r).await,
+        "death_protection" => skip::<Vec<ConsumeEffect>, _>(r).await,
+        "dyed_color" => skip::<DyedColor, _>(r).await,
+        "map_color" => skip::<i32, _>(r).await,
+        "charged_projectiles" | "bundle_contents" | "container" => {
+            let len = VarInt::read(&mut *r).await?.length()?;
+            for _ in 0..len {
+                read_slot(r, depth + 1).await?;
+            }
+            Ok(())
+        }
+        "potion_contents" => skip::<PotionContents, _>(r).await,
+        "suspicious_stew_effects" => skip::<Vec<StewEffect>, _>(r).await,
+        "writable_book_content" => skip::<Vec<FilterableString>, _>(r).await,
+        "written_book_content" => skip::<WrittenBookContent, _>(r).await,
+        "trim" => skip::<Trim, _>(r).await,
+        "instrument" => skip::<IdOr<Instrument>, _>(r).await,
+        "jukebox_playable" => skip::<JukeboxPlayable, _>(r).await,
+        "lodestone_tracker" => skip::<LodestoneTracker, _>(r).await,
+        "firework_explosion" => skip::<FireworkExplosion, _>(r).await,
+        "fireworks" => skip::<Fireworks, _>(r).await,
+        "profile" => skip::<Profile, _>(r).await,
+        "banner_patterns" => skip::<Vec<BannerLayer>, _>(r).await,
+        "pot_decorations" => skip::<Vec<VarInt>, _>(r).await,
+        "block_state" => skip::<Vec<BlockStateProperty>, _>(r).await,
+        "bees" => skip::<Vec<Bee>, _>(r).await,
+        name => unreachable!("{name} is in COMPONENTS"),
+    }
+}
+
+impl McType for Slot {
+    async fn read<R: AsyncRead + Unpin + Send>(mut r: R) -> io::Result<Self> {
+        read_slot(&mut r, 0).await
+    }
+
+    async fn write<W: AsyncWrite + Unpin + Send>(&self, mut w: W) -> io::Result<()> {
+        if self.is_empty() {
+            return VarInt::from(0).write(w).await;
+        }
+        let len = |len: usize| VarInt::try_from(len).map_err(io::Error::other);
+        VarInt::from(self.count).write(&mut w).await?;
+        VarInt::from(self.item_id).write(&mut w).await?;
+        len(self.added_components.len())?.write(&mut w).await?;
+        len(self.removed_components.len())?.write(&mut w).await?;
+        for component in &self.added_components {
+            component.id.write(&mut w).await?;
+            w.write_all(&component.data).await?;
+        }
+        for id in &self.removed_components {
+            id.write(&mut w).await?;
+        }
+        Ok(())
+    }
+}
+
+impl Decode<'_> for Slot {
+    fn decode(buf: &mut &[u8]) -> io::Result<Self> {
+        Self::read_blocking(buf)
+    }
+}
+
+/// A registry entry, sent as its id plus one or as 0 followed by the entry itself.
+enum IdOr<T> {
+    /// The id plus one.
+    Id(VarInt),
+    Inline(T),
+}
+
+impl<T: McType + Send> McType for IdOr<T> {
+    async fn read<R: AsyncRead + Unpin + Send>(mut r: R) -> io::Result<Self> {
+        match VarInt::read(&mut r).await? {
+            id if i32::from(id) == 0 => Ok(Self::Inline(T::read(r).await?)),
+            id => Ok(Self::Id(id)),
+        }
+    }
+
+    async fn write<W: AsyncWrite + Unpin + Send>(&self, mut w: W) -> io::Result<()> {
+        match self {
+            Self::Id(id) => id.write(w).await,
+            Self::Inline(entry) => {
+                VarInt::from(0).write(&mut w).await?;
+                entry.write(w).await
+            }
+        }
+    }
+}
+
+impl<'a, T: Decode<'a>> Decode<'a> for IdOr<T> {
+    fn decode(buf: &mut &'a [u8]) -> io::Result<Self> {
+        match VarInt::decode(buf)? {
+            id if i32::from(id) == 0 => Ok(Self::Inline(T::decode(buf)?)),
+            id => Ok(Self::Id(id)),
+        }
+    }
+}
+
+/// A set of registry entries, sent as 0 followed by a tag or as the number of entries plus one
+/// followed by their ids.
+enum IdSet<'s> {
+    Tag(Identifier<'s>),
+    Ids(Vec<VarInt>),
+}
+
+impl IdSet<'_> {
+    fn len(prefix: VarInt) -> io::Result<Option<usize>> {
+        match prefix.length()? {
+            0 => Ok(None),
+            len => Ok(Some(len - 1)),
+        }
+    }
+}
+
+impl McType for IdSet<'_> {
+    async fn read<R: AsyncRead + Unpin + Send>(mut r: R) -> io::Result<Self> {
+        let Some(len) = Self::len(VarInt::read(&mut r).await?)? else {
+            return Ok(Self::Tag(Identifier::read(r).await?));
+        };
+        let mut ids = Vec::with_capacity(len.min(1024));
+        for _ in 0..len {
+            ids.push(VarInt::read(&mut r).await?);
+        }
+        Ok(Self::Ids(ids))
+    }
+
+    async fn write<W: AsyncWrite + Unpin + Send>(&self, mut w: W) -> io::Result<()> {
+        match self {
+            Self::Tag(tag) => {
+                VarInt::from(0).write(&mut w).await?;
+                tag.write(w).await
+            }
+            Self::Ids(ids) => {
+                VarInt::try_from(ids.len() + 1)
+                    .map_err(io::Error::other)?
+                    .write(&mut w)
+                    .await?;
+                for id in ids {
+                    id.write(&mut w).await?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl<'a> Decode<'a> for IdSet<'a> {
+    fn decode(buf: &mut &'a [u8]) -> io::Result<Self> {
+        let Some(len) = Self::len(VarInt::decode(buf)?)? else {
+            return Ok(Self::Tag(Identifier::decode(buf)?));
+        };
+        let mut ids = Vec::with_capacity(len.min(buf.len()));
+        for _ in 0..len {
+            ids.push(VarInt::decode(buf)?);
+        }
+        Ok(Self::Ids(ids))
+    }
+}
+
+/// The levels of an effect, followed by those of the effect it hides if there's one, and so on.
+struct EffectDetails(Vec<EffectLevels>);
+
+#[derive(McType)]
+struct EffectLevels {
+    amplifier: VarInt,
+    duration: VarInt,
+    ambient: bool,
+    show_particles: bool,
+    show_icon: bool,
+}
+
+impl McType for EffectDetails {
+    async fn read<R: AsyncRead + Unpin + Send>(mut r: R) -> io::Result<Self> {
+        let mut levels = vec![EffectLevels::read(&mut r).await?];
+        while bool::read(&mut r).await? {
+            levels.push(EffectLevels::read(&mut r).await?);
+        }
+        Ok(Self(levels))
+    }
+
+    async fn write<W: AsyncWrite + Unpin + Send>(&self, mut w: W) -> io::Result<()> {
+        for (i, levels) in self.0.iter().enumerate() {
+            levels.write(&mut w).await?;
+            (i + 1 < self.0.len()).write(&mut w).await?;
+        }
+        Ok(())
+    }
+}
+
+impl Decode<'_> for EffectDetails {
+    fn decode(buf: &mut &[u8]) -> io::Result<Self> {
+        let mut levels = vec![EffectLevels::decode(buf)?];
+        while bool::decode(buf)? {
+            levels.push(EffectLevels::decode(buf)?);
+        }
+        Ok(Self(levels))
+    }
+}
+
+#[derive(McType)]
+struct Effect {
+    id: VarInt,
+    details: EffectDetails,
+}
+
+#[derive(McType)]
+struct SoundEvent<'s> {
+    name: Identifier<'s>,
+    fixed_range: Option<f32>,
+}
+
+#[derive(McType)]
+struct Enchantments {
+    levels: Vec<EnchantmentLevel>,
+    show_in_tooltip: bool,
+}
+
+#[derive(McType)]
+struct EnchantmentLevel {
+    id: VarInt,
+    level: VarInt,
+}
+
+#[derive(McType)]
+struct AdventureModePredicate<'s> {
+    blocks: Vec<BlockPredicate<'s>>,
+    show_in_tooltip: bool,
+}
+
+#[derive(McType)]
+struct BlockPredicate<'s> {
+    blocks: Option<IdSet<'s>>,
+    properties: Option<Vec<PropertyMatcher<'s>>>,
+    nbt: Option<Nbt>,
+}
+
+#[derive(McType)]
+struct PropertyMatcher<'s> {
+    name: String<'s>,
+    value: ValueMatcher<'s>,
+}
+
+/// Sent as whether the value is exact followed by it or by the range.
+#[derive(McType)]
+enum ValueMatcher<'s> {
+    Range {
+        min: Option<String<'s>>,
+        max: Option<String<'s>>,
+    },
+    Exact(String<'s>),
+}
+
+#[derive(McType)]
+struct AttributeModifiers<'s> {
+    modifiers: Vec<AttributeModifier<'s>>,
+    show_in_tooltip: bool,
+}
+
+#[derive(McType)]
+struct AttributeModifier<'s> {
+    attribute: VarInt,
+    id: Identifier<'s>,
+    amount: f64,
+    operation: VarInt,
+    slot: VarInt,
+}
+
+#[derive(McType)]
+struct CustomModelData<'s> {
+    floats: Vec<f32>,
+    flags: Vec<bool>,
+    strings: Vec<String<'s>>,
+    colors: Vec<i32>,
+}
+
+#[derive(McType)]
+struct Food {
+    nutrition: VarInt,
+    saturation: f32,
+    can_always_eat: bool,
+}
+
+#[derive(McType)]
+struct Consumable<'s> {
+    seconds: f32,
+    animation: VarInt,
+    sound: IdOr<SoundEvent<'s>>,
+    has_particles: bool,
+    effects: Vec<ConsumeEffect<'s>>,
+}
+
+#[derive(McType)]
+enum ConsumeEffect<'s> {
+    ApplyEffects {
+        effects: Vec<Effect>,
+        probability: f32,
+    },
+    RemoveEffects(IdSet<'s>),
+    ClearAllEffects,
+    TeleportRandomly {
+        diameter: f32,
+    },
+    PlaySound(IdOr<SoundEvent<'s>>),
+}
+
+#[derive(McType)]
+struct UseCooldown<'s> {
+    seconds: f32,
+    group: Option<Identifier<'s>>,
+}
+
+#[derive(McType)]
+struct Tool<'s> {
+    rules: Vec<ToolRule<'s>>,
+    default_mining_speed: f32,
+    damage_per_block: VarInt,
+}
+
+#[derive(McType)]
+struct ToolRule<'s> {
+    blocks: IdSet<'s>,
+    speed: Option<f32>,
+    correct_for_drops: Option<bool>,
+}
+
+#[derive(McType)]
+struct Equippable<'s> {
+    slot: VarInt,
+    equip_sound: IdOr<SoundEvent<'s>>,
+    model: Option<Identifier<'s>>,
+    camera_overlay: Option<Identifier<'s>>,
+    allowed_entities: Option<IdSet<'s>>,
+    dispensable: bool,
+    swappable: bool,
+    damage_on_hurt: bool,
+}
+
+#[derive(McType)]
+struct DyedColor {
+    rgb: i32,
+    show_in_tooltip: bool,
+}
+
+#[derive(McType)]
+struct PotionContents<'s> {
+    potion: Option<VarInt>,
+    color: Option<i32>,
+    effects: Vec<Effect>,
+    name: Option<String<'s>>,
+}
+
+#[derive(McType)]
+struct StewEffect {
+    id: VarInt,
+    duration: VarInt,
+}
+
+/// A string along with the one shown to players who filter text, if it differs.
+#[derive(McType)]
+struct FilterableString<'s> {
+    raw: String<'s>,
+    filtered: Option<String<'s>>,
+}
+
+/// Like [`FilterableString`], for a text component.
+#[derive(McType)]
+struct FilterableText {
+    raw: Nbt,
+    filtered: Option<Nbt>,
+}
+
+#[derive(McType)]
+struct WrittenBookContent<'s> {
+    title: FilterableString<'s>,
+    author: String<'s>,
+    generation: VarInt,
+    pages: Vec<FilterableText>,
+    resolved: bool,
+}
+
+#[derive(McType)]
+struct Trim<'s> {
+    material: IdOr<TrimMaterial<'s>>,
+    pattern: IdOr<TrimPattern<'s>>,
+    show_in_tooltip: bool,
+}
+
+#[derive(McType)]
+struct TrimMaterial<'s> {
+    asset_name: String<'s>,
+    ingredient: VarInt,
+    overrides: Vec<TrimMaterialOverride<'s>>,
+    description: Nbt,
+}
+
+#[derive(McType)]
+struct TrimMaterialOverride<'s> {
+    armor_material: Identifier<'s>,
+    asset_name: String<'s>,
+}
+
+#[derive(McType)]
+struct TrimPattern<'s> {
+    asset_id: Identifier<'s>,
+    template_item: VarInt,
+    description: Nbt,
+    decal: bool,
+}
+
+#[derive(McType)]
+struct Instrument<'s> {
+    sound: IdOr<SoundEvent<'s>>,
+    use_seconds: f32,
+    range: f32,
+    description: Nbt,
+}
+
+#[derive(McType)]
+struct JukeboxPlayable<'s> {
+    song: JukeboxSongRef<'s>,
+    show_in_tooltip: bool,
+}
+
+/// Sent as whether the song is an entry followed by it or by its name.
+#[derive(McType)]
+enum JukeboxSongRef<'s> {
+    Name(Identifier<'s>),
+    Entry(IdOr<JukeboxSong<'s>>),
+}
+
+#[derive(McType)]
+struct JukeboxSong<'s> {
+    sound: IdOr<SoundEvent<'s>>,
+    description: Nbt,
+    seconds: f32,
+    comparator_output: VarInt,
+}
+
+#[derive(McType)]
+struct LodestoneTracker<'s> {
+    target: Option<GlobalPosition<'s>>,
+    tracked: bool,
+}
+
+#[derive(McType)]
+struct GlobalPosition<'s> {
+    dimension: Identifier<'s>,
+    position: super::Position,
+}
+
+#[derive(McType)]
+struct FireworkExplosion {
+    shape: VarInt,
+    colors: Vec<i32>,
+    fade_colors: Vec<i32>,
+    trail: bool,
+    twinkle: bool,
+}
+
+#[derive(McType)]
+struct Fireworks {
+    flight_duration: VarInt,
+    explosions: Vec<FireworkExplosion>,
+}
+
+#[derive(McType)]
+struct Profile<'s> {
+    name: Option<String<'s>>,
+    id: Option<Uuid>,
+    properties: Vec<ProfileProperty<'s>>,
+}
+
+#[derive(McType)]
+struct ProfileProperty<'s> {
+    name: String<'s>,
+    value: String<'s>,
+    signature: Option<String<'s>>,
+}
+
+#[derive(McType)]
+struct BannerLayer<'s> {
+    pattern: IdOr<BannerPattern<'s>>,
+    color: VarInt,
+}
+
+#[derive(McType)]
+struct BannerPattern<'s> {
+    asset_id: Identifier<'s>,
+    translation_key: String<'s>,
+}
+
+#[derive(McType)]
+struct BlockStateProperty<'s> {
+    name: String<'s>,
+    value: String<'s>,
+}
+
+#[derive(McType)]
+struct Bee {
+    entity_data: Nbt,
+    ticks_in_hive: VarInt,
+    min_ticks_in_hive: VarInt,
+}
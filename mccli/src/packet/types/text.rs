@@ -0,0 +1,1168 @@
+//! Json text components, the rich text used for server descriptions, chat and disconnect reasons.
+//! In play and configuration packets they're sent as [nbt](super::nbt) instead.
+//!
+//! A component is a string, a list (whose first element is the parent of the rest) or an object
+//! with a content (`text`, `translate`, `score`, `selector`, `keybind` or `nbt`), a style and
+//! child components in `extra`. Plain text may also contain legacy `§` formatting codes, which
+//! are applied when rendering.
+
+use super::{Decode, McType, nbt::Nbt};
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de};
+use std::{
+    fmt::{self, Write as _},
+    io,
+};
+use tokio::io::{AsyncRead, AsyncWrite};
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct TextComponent {
+    pub content: Content,
+    pub style: Style,
+    pub extra: Vec<TextComponent>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Content {
+    Text(String),
+    /// A translation key, formatted with `with` when rendered.
+    Translate {
+        key: String,
+        /// Used when the key isn't known.
+        fallback: Option<String>,
+        with: Vec<TextComponent>,
+    },
+    /// A scoreboard value, resolved by the server into `value`.
+    Score {
+        name: String,
+        objective: String,
+        value: Option<String>,
+    },
+    /// An entity selector, resolved by the server before sending.
+    Selector {
+        selector: String,
+        separator: Option<Box<TextComponent>>,
+    },
+    /// The key bound to an action, e.g. `key.jump`.
+    Keybind(String),
+    /// Nbt data, resolved by the server before sending.
+    Nbt {
+        path: String,
+        source: Option<NbtSource>,
+        interpret: Option<bool>,
+        separator: Option<Box<TextComponent>>,
+    },
+}
+
+impl Default for Content {
+    fn default() -> Self {
+        Self::Text(String::new())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NbtSource {
+    Block(String),
+    Entity(String),
+    Storage(String),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(default)]
+pub struct Style {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub color: Option<Color>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bold: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub italic: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub underlined: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub strikethrough: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub obfuscated: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub font: Option<String>,
+    /// Inserted into the chat box when shift-clicked.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub insertion: Option<String>,
+    #[serde(rename = "clickEvent", skip_serializing_if = "Option::is_none")]
+    pub click_event: Option<MaybeKnown<ClickEvent>>,
+    #[serde(rename = "hoverEvent", skip_serializing_if = "Option::is_none")]
+    pub hover_event: Option<MaybeKnown<HoverEvent>>,
+}
+
+impl Style {
+    /// This style, with the fields it doesn't set taken from `parent`.
+    pub fn inherit(&self, parent: &Style) -> Style {
+        Style {
+            color: self.color.clone().or_else(|| parent.color.clone()),
+            bold: self.bold.or(parent.bold),
+            italic: self.italic.or(parent.italic),
+            underlined: self.underlined.or(parent.underlined),
+            strikethrough: self.strikethrough.or(parent.strikethrough),
+            obfuscated: self.obfuscated.or(parent.obfuscated),
+            font: self.font.clone().or_else(|| parent.font.clone()),
+            insertion: self.insertion.clone().or_else(|| parent.insertion.clone()),
+            click_event: self
+                .click_event
+                .clone()
+                .or_else(|| parent.click_event.clone()),
+            hover_event: self
+                .hover_event
+                .clone()
+                .or_else(|| parent.hover_event.clone()),
+        }
+    }
+
+    fn is_bold(&self) -> bool {
+        self.bold == Some(true)
+    }
+
+    fn is_italic(&self) -> bool {
+        self.italic == Some(true)
+    }
+
+    fn is_underlined(&self) -> bool {
+        self.underlined == Some(true)
+    }
+
+    fn is_strikethrough(&self) -> bool {
+        self.strikethrough == Some(true)
+    }
+
+    fn is_obfuscated(&self) -> bool {
+        self.obfuscated == Some(true)
+    }
+
+    /// Only the parts that legacy codes can express.
+    fn legacy_subset(self) -> Style {
+        Style {
+            font: None,
+            insertion: None,
+            click_event: None,
+            hover_event: None,
+            ..self
+        }
+    }
+
+    fn without_formatting(&self) -> Style {
+        Style {
+            bold: None,
+            italic: None,
+            underlined: None,
+            strikethrough: None,
+            obfuscated: None,
+            ..self.clone()
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Color {
+    Named(NamedColor),
+    Rgb(u8, u8, u8),
+    /// The default color.
+    Reset,
+    /// A color that isn't valid, kept as it was sent and rendered as the default color like the
+    /// vanilla client does.
+    Unknown(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NamedColor {
+    Black,
+    DarkBlue,
+    DarkGreen,
+    DarkAqua,
+    DarkRed,
+    DarkPurple,
+    Gold,
+    Gray,
+    DarkGray,
+    Blue,
+    Green,
+    Aqua,
+    Red,
+    LightPurple,
+    Yellow,
+    White,
+}
+
+impl NamedColor {
+    /// In the order of their legacy codes, `0` to `f`.
+    pub const ALL: [Self; 16] = [
+        Self::Black,
+        Self::DarkBlue,
+        Self::DarkGreen,
+        Self::DarkAqua,
+        Self::DarkRed,
+        Self::DarkPurple,
+        Self::Gold,
+        Self::Gray,
+        Self::DarkGray,
+        Self::Blue,
+        Self::Green,
+        Self::Aqua,
+        Self::Red,
+        Self::LightPurple,
+        Self::Yellow,
+        Self::White,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Black => "black",
+            Self::DarkBlue => "dark_blue",
+            Self::DarkGreen => "dark_green",
+            Self::DarkAqua => "dark_aqua",
+            Self::DarkRed => "dark_red",
+            Self::DarkPurple => "dark_purple",
+            Self::Gold => "gold",
+            Self::Gray => "gray",
+            Self::DarkGray => "dark_gray",
+            Self::Blue => "blue",
+            Self::Green => "green",
+            Self::Aqua => "aqua",
+            Self::Red => "red",
+            Self::LightPurple => "light_purple",
+            Self::Yellow => "yellow",
+            Self::White => "white",
+        }
+    }
+
+    pub fn rgb(self) -> (u8, u8, u8) {
+        match self {
+            Self::Black => (0x00, 0x00, 0x00),
+            Self::DarkBlue => (0x00, 0x00, 0xaa),
+            Self::DarkGreen => (0x00, 0xaa, 0x00),
+            Self::DarkAqua => (0x00, 0xaa, 0xaa),
+            Self::DarkRed => (0xaa, 0x00, 0x00),
+            Self::DarkPurple => (0xaa, 0x00, 0xaa),
+            Self::Gold => (0xff, 0xaa, 0x00),
+            Self::Gray => (0xaa, 0xaa, 0xaa),
+            Self::DarkGray => (0x55, 0x55, 0x55),
+            Self::Blue => (0x55, 0x55, 0xff),
+            Self::Green => (0x55, 0xff, 0x55),
+            Self::Aqua => (0x55, 0xff, 0xff),
+            Self::Red => (0xff, 0x55, 0x55),
+            Self::LightPurple => (0xff, 0x55, 0xff),
+            Self::Yellow => (0xff, 0xff, 0x55),
+            Self::White => (0xff, 0xff, 0xff),
+        }
+    }
+
+    /// The legacy formatting code, `0` to `f`.
+    pub fn code(self) -> char {
+        let index = Self::ALL.iter().position(|c| *c == self).unwrap();
+        char::from_digit(index as u32, 16).unwrap()
+    }
+
+    pub fn from_code(code: char) -> Option<Self> {
+        code.to_digit(16).map(|i| Self::ALL[i as usize])
+    }
+
+    /// The named color closest to `rgb`, for outputs that can't show anything else.
+    pub fn closest(rgb: (u8, u8, u8)) -> Self {
+        let distance = |c: Self| {
+            let (r, g, b) = c.rgb();
+            [(r, rgb.0), (g, rgb.1), (b, rgb.2)]
+                .into_iter()
+                .map(|(a, b)| (i32::from(a) - i32::from(b)).pow(2))
+                .sum::<i32>()
+        };
+        Self::ALL.into_iter().min_by_key(|c| distance(*c)).unwrap()
+    }
+}
+
+impl Color {
+    /// The color to render, `None` for the terminal's default.
+    pub fn rgb(&self) -> Option<(u8, u8, u8)> {
+        match *self {
+            Self::Named(named) => Some(named.rgb()),
+            Self::Rgb(r, g, b) => Some((r, g, b)),
+            Self::Reset | Self::Unknown(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Named(named) => f.write_str(named.name()),
+            Self::Rgb(r, g, b) => write!(f, "#{r:02X}{g:02X}{b:02X}"),
+            Self::Reset => f.write_str("reset"),
+            Self::Unknown(color) => f.write_str(color),
+        }
+    }
+}
+
+impl std::str::FromStr for Color {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(hex) = s.strip_prefix('#') {
+            let rgb = u32::from_str_radix(hex, 16)
+                .ok()
+                .filter(|_| hex.len() == 6)
+                .ok_or_else(|| format!("invalid hex color: {s:?}"))?;
+            let [_, r, g, b] = rgb.to_be_bytes();
+            return Ok(Self::Rgb(r, g, b));
+        }
+        if s == "reset" {
+            return Ok(Self::Reset);
+        }
+        NamedColor::ALL
+            .into_iter()
+            .find(|c| c.name() == s)
+            .map(Self::Named)
+            .ok_or_else(|| format!("unknown color: {s:?}"))
+    }
+}
+
+impl Serialize for Color {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Color {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let color = String::deserialize(deserializer)?;
+        Ok(color.parse().unwrap_or(Self::Unknown(color)))
+    }
+}
+
+/// A value that is modelled if it's known, e.g. an event with an action added after 1.21.4,
+/// kept as it was sent otherwise so that it serializes back the same.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum MaybeKnown<T> {
+    Known(T),
+    Unknown(serde_json::Value),
+}
+
+impl<T> MaybeKnown<T> {
+    pub fn known(&self) -> Option<&T> {
+        match self {
+            Self::Known(known) => Some(known),
+            Self::Unknown(_) => None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ClickEvent {
+    pub action: ClickAction,
+    /// A string, except for `change_page` which some servers send as a number.
+    pub value: serde_json::Value,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ClickAction {
+    OpenUrl,
+    OpenFile,
+    RunCommand,
+    SuggestCommand,
+    ChangePage,
+    CopyToClipboard,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct HoverEvent {
+    pub action: HoverAction,
+    /// A component for `show_text`, an object describing the item or entity otherwise.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub contents: Option<serde_json::Value>,
+    /// What `contents` used to be called before 1.16.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<serde_json::Value>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum HoverAction {
+    ShowText,
+    ShowItem,
+    ShowEntity,
+}
+
+impl HoverEvent {
+    /// The text shown by a `show_text` event.
+    pub fn text(&self) -> Option<TextComponent> {
+        if self.action != HoverAction::ShowText {
+            return None;
+        }
+        let contents = self.contents.as_ref().or(self.value.as_ref())?;
+        serde_json::from_value(contents.clone()).ok()
+    }
+}
+
+/// A run of text with a single style, what rendering flattens a component into.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Span {
+    pub style: Style,
+    pub text: String,
+}
+
+impl TextComponent {
+    pub fn text(text: impl Into<String>) -> Self {
+        Self {
+            content: Content::Text(text.into()),
+            ..Default::default()
+        }
+    }
+
+    /// Turns text with `§` formatting codes into components without any.
+    pub fn from_legacy(text: &str) -> Self {
+        let mut spans = Vec::new();
+        legacy_spans(text, &Style::default(), &mut spans);
+        spans.retain(|s| !s.text.is_empty());
+        match &spans[..] {
+            [] => Self::text(""),
+            [span] if span.style == Style::default() => Self::text(span.text.clone()),
+            _ => Self {
+                extra: spans
+                    .into_iter()
+                    .map(|Span { style, text }| Self {
+                        content: Content::Text(text),
+                        style,
+                        extra: Vec::new(),
+                    })
+                    .collect(),
+                ..Self::text("")
+            },
+        }
+    }
+
+    /// Flattens the component into runs of styled text, applying inherited styles, legacy codes
+    /// and translation arguments.
+    pub fn spans(&self) -> Vec<Span> {
+        let mut spans = Vec::new();
+        self.collect_spans(&Style::default(), &mut spans);
+        spans.retain(|s| !s.text.is_empty());
+        spans
+    }
+
+    fn collect_spans(&self, parent: &Style, out: &mut Vec<Span>) {
+        let style = self.style.inherit(parent);
+        match &self.content {
+            Content::Text(text) => legacy_spans(text, &style, out),
+            Content::Translate {
+                key,
+                fallback,
+                with,
+            } => {
+                // without the client's language files the fallback is the best we can do
+                let format = fallback.as_deref().unwrap_or(key);
+                translate(format, with, &style, out);
+            }
+            Content::Score { value, .. } => {
+                legacy_spans(value.as_deref().unwrap_or_default(), &style, out)
+            }
+            Content::Selector { selector, .. } => legacy_spans(selector, &style, out),
+            Content::Keybind(key) => legacy_spans(key, &style, out),
+            Content::Nbt { .. } => {}
+        }
+        for child in &self.extra {
+            child.collect_spans(&style, out);
+        }
+    }
+
+    /// The text without any formatting.
+    pub fn plain_text(&self) -> String {
+        self.spans().into_iter().map(|s| s.text).collect()
+    }
+
+    /// The text with ANSI escape codes for a truecolor terminal.
+    pub fn to_ansi(&self) -> String {
+        let mut out = String::new();
+        for Span { style, text } in self.spans() {
+            out.push_str("\x1b[0");
+            for (enabled, code) in [
+                (style.is_bold(), "1"),
+                (style.is_italic(), "3"),
+                (style.is_underlined(), "4"),
+                (style.is_obfuscated(), "5"),
+                (style.is_strikethrough(), "9"),
+            ] {
+                if enabled {
+                    out.push(';');
+                    out.push_str(code);
+                }
+            }
+            if let Some((r, g, b)) = style.color.as_ref().and_then(Color::rgb) {
+                let _ = write!(out, ";38;2;{r};{g};{b}");
+            }
+            out.push('m');
+            out.push_str(&text);
+        }
+        if !out.is_empty() {
+            out.push_str("\x1b[0m");
+        }
+        out
+    }
+
+    /// The text with legacy `§` formatting codes, for clients that don't understand components.
+    /// Hex colors are approximated.
+    pub fn to_legacy(&self) -> String {
+        let mut out = String::new();
+        let mut current = Style::default();
+        for Span { style, text } in self.spans() {
+            let style = style.legacy_subset();
+            if style != current {
+                match style.color.as_ref().and_then(Color::rgb) {
+                    Some(rgb) => {
+                        out.push('§');
+                        out.push(NamedColor::closest(rgb).code());
+                    }
+                    None => out.push_str("§r"),
+                }
+                for (enabled, code) in [
+                    (style.is_obfuscated(), 'k'),
+                    (style.is_bold(), 'l'),
+                    (style.is_strikethrough(), 'm'),
+                    (style.is_underlined(), 'n'),
+                    (style.is_italic(), 'o'),
+                ] {
+                    if enabled {
+                        out.push('§');
+                        out.push(code);
+                    }
+                }
+                current = style;
+            }
+            out.push_str(&text);
+        }
+        out
+    }
+}
+
+impl fmt::Display for TextComponent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.plain_text())
+    }
+}
+
+impl From<&str> for TextComponent {
+    fn from(text: &str) -> Self {
+        Self::text(text)
+    }
+}
+
+impl From<String> for TextComponent {
+    fn from(text: String) -> Self {
+        Self::text(text)
+    }
+}
+
+/// Splits `text` on `§` codes, which apply on top of `base`.
+fn legacy_spans(text: &str, base: &Style, out: &mut Vec<Span>) {
+    let mut style = base.clone();
+    let mut current = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '§' {
+            current.push(c);
+            continue;
+        }
+        let Some(code) = chars.next().map(|c| c.to_ascii_lowercase()) else {
+            break;
+        };
+        let next = match code {
+            // spigot's hex colors: §x§r§r§g§g§b§b
+            'x' => {
+                let digits = chars
+                    .clone()
+                    .take(12)
+                    .collect::<Vec<_>>()
+                    .chunks(2)
+                    .map(|pair| match pair {
+                        ['§', digit] => digit.to_digit(16),
+                        _ => None,
+                    })
+                    .collect::<Option<Vec<_>>>();
+                match digits.as_deref() {
+                    Some(&[r1, r2, g1, g2, b1, b2]) => {
+                        chars.nth(11);
+                        let component = |high: u32, low: u32| (high << 4 | low) as u8;
+                        Style {
+                            color: Some(Color::Rgb(
+                                component(r1, r2),
+                                component(g1, g2),
+                                component(b1, b2),
+                            )),
+                            ..base.without_formatting()
+                        }
+                    }
+                    _ => continue,
+                }
+            }
+            'r' => base.clone(),
+            'k' => Style {
+                obfuscated: Some(true),
+                ..style.clone()
+            },
+            'l' => Style {
+                bold: Some(true),
+                ..style.clone()
+            },
+            'm' => Style {
+                strikethrough: Some(true),
+                ..style.clone()
+            },
+            'n' => Style {
+                underlined: Some(true),
+                ..style.clone()
+            },
+            'o' => Style {
+                italic: Some(true),
+                ..style.clone()
+            },
+            code => match NamedColor::from_code(code) {
+                // colors reset the formatting codes before them
+                Some(color) => Style {
+                    color: Some(Color::Named(color)),
+                    ..base.without_formatting()
+                },
+                None => continue,
+            },
+        };
+        if next != style {
+            out.push(Span {
+                style: std::mem::replace(&mut style, next),
+                text: std::mem::take(&mut current),
+            });
+        }
+    }
+    out.push(Span {
+        style,
+        text: current,
+    });
+}
+
+/// Substitutes `%s`, `%1$s` and `%%` in `format` with the rendered `args`.
+fn translate(format: &str, args: &[TextComponent], style: &Style, out: &mut Vec<Span>) {
+    let mut next_arg = 0;
+    let mut literal = String::new();
+    let mut rest = format;
+    while let Some(i) = rest.find('%') {
+        literal.push_str(&rest[..i]);
+        rest = &rest[i + 1..];
+        let (index, len) = if rest.starts_with('%') {
+            literal.push('%');
+            rest = &rest[1..];
+            continue;
+        } else if rest.starts_with('s') {
+            next_arg += 1;
+            (next_arg - 1, 1)
+        } else if let Some((digits, _)) = rest.split_once("$s")
+            && !digits.is_empty()
+            && digits.bytes().all(|b| b.is_ascii_digit())
+        {
+            // digits that don't fit are an index past any argument
+            let n = digits.parse::<usize>().unwrap_or(usize::MAX);
+            (n.saturating_sub(1), digits.len() + 2)
+        } else {
+            literal.push('%');
+            continue;
+        };
+        legacy_spans(&std::mem::take(&mut literal), style, out);
+        if let Some(arg) = args.get(index) {
+            arg.collect_spans(style, out);
+        }
+        rest = &rest[len..];
+    }
+    literal.push_str(rest);
+    legacy_spans(&literal, style, out);
+}
+
+#[derive(Serialize, Deserialize)]
+struct Score {
+    name: String,
+    objective: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    value: Option<String>,
+}
+
+/// The object form of a component, as it appears in json.
+#[derive(Serialize, Deserialize, Default)]
+struct Object {
+    #[serde(rename = "type", default, skip_serializing)]
+    _kind: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    text: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    translate: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    fallback: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    with: Option<Vec<TextComponent>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    score: Option<Score>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    selector: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    separator: Option<Box<TextComponent>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    keybind: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    nbt: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    interpret: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    block: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    entity: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    storage: Option<String>,
+    #[serde(flatten)]
+    style: Style,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    extra: Vec<TextComponent>,
+}
+
+impl Object {
+    fn into_component(self) -> Result<TextComponent, &'static str> {
+        let content = if let Some(text) = self.text {
+            Content::Text(text)
+        } else if let Some(key) = self.translate {
+            Content::Translate {
+                key,
+                fallback: self.fallback,
+                with: self.with.unwrap_or_default(),
+            }
+        } else if let Some(Score {
+            name,
+            objective,
+            value,
+        }) = self.score
+        {
+            Content::Score {
+                name,
+                objective,
+                value,
+            }
+        } else if let Some(selector) = self.selector {
+            Content::Selector {
+                selector,
+                separator: self.separator,
+            }
+        } else if let Some(key) = self.keybind {
+            Content::Keybind(key)
+        } else if let Some(path) = self.nbt {
+            Content::Nbt {
+                path,
+                source: match (self.block, self.entity, self.storage) {
+                    (Some(block), _, _) => Some(NbtSource::Block(block)),
+                    (_, Some(entity), _) => Some(NbtSource::Entity(entity)),
+                    (_, _, Some(storage)) => Some(NbtSource::Storage(storage)),
+                    _ => None,
+                },
+                interpret: self.interpret,
+                separator: self.separator,
+            }
+        } else {
+            return Err("text component without content");
+        };
+        Ok(TextComponent {
+            content,
+            style: self.style,
+            extra: self.extra,
+        })
+    }
+
+    fn from_component(component: &TextComponent) -> Self {
+        let mut object = Object {
+            style: component.style.clone(),
+            extra: component.extra.clone(),
+            ..Default::default()
+        };
+        match &component.content {
+            Content::Text(text) => object.text = Some(text.clone()),
+            Content::Translate {
+                key,
+                fallback,
+                with,
+            } => {
+                object.translate = Some(key.clone());
+                object.fallback = fallback.clone();
+                object.with = (!with.is_empty()).then(|| with.clone());
+            }
+            Content::Score {
+                name,
+                objective,
+                value,
+            } => {
+                object.score = Some(Score {
+                    name: name.clone(),
+                    objective: objective.clone(),
+                    value: value.clone(),
+                })
+            }
+            Content::Selector {
+                selector,
+                separator,
+            } => {
+                object.selector = Some(selector.clone());
+                object.separator = separator.clone();
+            }
+            Content::Keybind(key) => object.keybind = Some(key.clone()),
+            Content::Nbt {
+                path,
+                source,
+                interpret,
+                separator,
+            } => {
+                object.nbt = Some(path.clone());
+                object.interpret = *interpret;
+                object.separator = separator.clone();
+                match source {
+                    Some(NbtSource::Block(b)) => object.block = Some(b.clone()),
+                    Some(NbtSource::Entity(e)) => object.entity = Some(e.clone()),
+                    Some(NbtSource::Storage(s)) => object.storage = Some(s.clone()),
+                    None => {}
+                }
+            }
+        }
+        object
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Repr {
+    Text(String),
+    Number(serde_json::Number),
+    Bool(bool),
+    List(Vec<TextComponent>),
+    Object(Box<Object>),
+}
+
+impl<'de> Deserialize<'de> for TextComponent {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        match Repr::deserialize(deserializer)? {
+            Repr::Text(text) => Ok(Self::text(text)),
+            Repr::Number(n) => Ok(Self::text(n.to_string())),
+            Repr::Bool(b) => Ok(Self::text(b.to_string())),
+            Repr::List(list) => {
+                let mut list = list.into_iter();
+                let mut parent = list.next().unwrap_or_default();
+                parent.extra.extend(list);
+                Ok(parent)
+            }
+            Repr::Object(object) => object.into_component().map_err(de::Error::custom),
+        }
+    }
+}
+
+impl Serialize for TextComponent {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match &self.content {
+            Content::Text(text) if self.style == Style::default() && self.extra.is_empty() => {
+                serializer.serialize_str(text)
+            }
+            _ => Object::from_component(self).serialize(serializer),
+        }
+    }
+}
+
+/// Text components are sent as nbt since 1.20.3, with the same structure as the json.
+impl McType for TextComponent {
+    async fn read<R: AsyncRead + Unpin + Send>(r: R) -> io::Result<Self> {
+        Self::from_nbt(&Nbt::read(r).await?)
+    }
+
+    async fn write<W: AsyncWrite + Unpin + Send>(&self, w: W) -> io::Result<()> {
+        self.to_nbt().write(w).await
+    }
+}
+
+impl Decode<'_> for TextComponent {
+    fn decode(buf: &mut &[u8]) -> io::Result<Self> {
+        Self::from_nbt(&Nbt::decode(buf)?)
+    }
+}
+
+impl TextComponent {
+    pub fn from_nbt(nbt: &Nbt) -> io::Result<Self> {
+        serde_json::from_value(nbt_to_json(nbt))
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    pub fn to_nbt(&self) -> Nbt {
+        json_to_nbt(&serde_json::to_value(self).expect("components are always serializable"))
+    }
+}
+
+/// Booleans are bytes in nbt, and lists can't mix types so elements of mixed lists are wrapped
+/// in compounds with a single empty key.
+fn nbt_to_json(nbt: &Nbt) -> serde_json::Value {
+    use serde_json::Value;
+    match nbt {
+        Nbt::End => Value::Null,
+        Nbt::Byte(b @ (0 | 1)) => Value::Bool(*b == 1),
+        Nbt::Byte(b) => (*b).into(),
+        Nbt::Short(s) => (*s).into(),
+        Nbt::Int(i) => (*i).into(),
+        Nbt::Long(l) => (*l).into(),
+        Nbt::Float(f) => (*f).into(),
+        Nbt::Double(d) => (*d).into(),
+        Nbt::String(s) => s.as_str().into(),
+        Nbt::ByteArray(bytes) => bytes.as_slice().into(),
+        Nbt::IntArray(ints) => ints.as_slice().into(),
+        Nbt::LongArray(longs) => longs.as_slice().into(),
+        Nbt::List(tags) => tags
+            .iter()
+            .map(|tag| match tag {
+                Nbt::Compound(wrapped) if matches!(&wrapped[..], [(key, _)] if key.is_empty()) => {
+                    nbt_to_json(&wrapped[0].1)
+                }
+                tag => nbt_to_json(tag),
+            })
+            .collect(),
+        Nbt::Compound(tags) => tags
+            .iter()
+            .map(|(name, tag)| (name.clone(), nbt_to_json(tag)))
+            .collect(),
+    }
+}
+
+fn json_to_nbt(json: &serde_json::Value) -> Nbt {
+    use serde_json::Value;
+    match json {
+        Value::Null => Nbt::End,
+        Value::Bool(b) => Nbt::Byte(i8::from(*b)),
+        Value::Number(n) => match n.as_i64() {
+            Some(i) => i32::try_from(i).map_or(Nbt::Long(i), Nbt::Int),
+            None => Nbt::Double(n.as_f64().unwrap_or_default()),
+        },
+        Value::String(s) => Nbt::String(s.clone()),
+        Value::Array(values) => {
+            let mut tags = values.iter().map(json_to_nbt).collect::<Vec<_>>();
+            if tags
+                .windows(2)
+                .any(|w| std::mem::discriminant(&w[0]) != std::mem::discriminant(&w[1]))
+            {
+                tags = tags
+                    .into_iter()
+                    .map(|tag| Nbt::Compound(vec![(String::new(), tag)]))
+                    .collect();
+            }
+            Nbt::List(tags)
+        }
+        Value::Object(object) => Nbt::Compound(
+            object
+                .iter()
+                .filter(|(_, v)| !v.is_null())
+                .map(|(k, v)| (k.clone(), json_to_nbt(v)))
+                .collect(),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    fn parse(json: serde_json::Value) -> TextComponent {
+        serde_json::from_value(json).unwrap()
+    }
+
+    #[test]
+    fn nbt_roundtrip() {
+        let component = parse(json!({
+            "text": "hi ",
+            "bold": true,
+            "extra": ["plain", { "translate": "chat.type.text", "with": ["a", { "text": "b" }] }],
+        }));
+        let nbt = component.to_nbt();
+        assert_eq!(nbt.get("bold"), Some(&Nbt::Byte(1)));
+        // strings and compounds can't share a list
+        let Some(Nbt::List(extra)) = nbt.get("extra") else {
+            panic!("{nbt:?}");
+        };
+        assert!(
+            extra
+                .iter()
+                .all(|e| matches!(e, Nbt::Compound(c) if c.len() == 1 && c[0].0.is_empty()))
+        );
+
+        let mut buffer = Vec::new();
+        component.write_blocking(&mut buffer).unwrap();
+        assert_eq!(
+            TextComponent::read_blocking(&buffer[..]).unwrap(),
+            component
+        );
+        assert_eq!(
+            TextComponent::decode(&mut &buffer[..])
+                .unwrap()
+                .plain_text(),
+            "hi plainchat.type.text"
+        );
+
+        // plain text is a bare string tag
+        let mut buffer = Vec::new();
+        TextComponent::text("hi")
+            .write_blocking(&mut buffer)
+            .unwrap();
+        assert_eq!(buffer, b"\x08\x00\x02hi");
+    }
+
+    #[test]
+    fn parse_shapes() {
+        assert_eq!(parse(json!("hi")), TextComponent::text("hi"));
+        assert_eq!(parse(json!(3)), TextComponent::text("3"));
+
+        let list = parse(json!(["a", { "text": "b", "bold": true }]));
+        assert_eq!(list.plain_text(), "ab");
+        assert_eq!(list.extra[0].style.bold, Some(true));
+
+        let object = parse(json!({
+            "text": "",
+            "extra": [
+                { "text": "click", "color": "#FF8800", "underlined": true,
+                  "clickEvent": { "action": "open_url", "value": "https://example.com" },
+                  "hoverEvent": { "action": "show_text", "contents": { "text": "go", "italic": true } } },
+                { "keybind": "key.jump" },
+                { "score": { "name": "steve", "objective": "kills", "value": "3" } },
+                { "selector": "@p" },
+                { "nbt": "Health", "entity": "@s" },
+            ],
+        }));
+        let click = &object.extra[0];
+        assert_eq!(click.style.color, Some(Color::Rgb(0xff, 0x88, 0x00)));
+        assert_eq!(
+            click
+                .style
+                .click_event
+                .as_ref()
+                .and_then(MaybeKnown::known)
+                .unwrap()
+                .action,
+            ClickAction::OpenUrl
+        );
+        let hover = click
+            .style
+            .hover_event
+            .as_ref()
+            .and_then(MaybeKnown::known)
+            .unwrap()
+            .text()
+            .unwrap();
+        assert_eq!(hover.style.italic, Some(true));
+        assert_eq!(object.extra[1].content, Content::Keybind("key.jump".into()));
+        assert!(matches!(
+            &object.extra[4].content,
+            Content::Nbt { source: Some(NbtSource::Entity(e)), .. } if e == "@s"
+        ));
+        assert_eq!(object.plain_text(), "clickkey.jump3@p");
+    }
+
+    #[test]
+    fn serialize_roundtrip() {
+        let json = json!({
+            "translate": "chat.type.text",
+            "with": [{ "text": "steve", "color": "gold" }, "hello"],
+            "obfuscated": false,
+            "extra": ["!"],
+        });
+        let component = parse(json.clone());
+        assert_eq!(serde_json::to_value(&component).unwrap(), json);
+        assert_eq!(serde_json::to_value(TextComponent::text("x")).unwrap(), "x");
+    }
+
+    #[test]
+    fn unknown_events_roundtrip() {
+        let json = json!({
+            "text": "new",
+            "clickEvent": { "action": "show_dialog", "dialog": "minecraft:server_links" },
+            "hoverEvent": { "action": "show_achievement", "value": "achievement.openInventory" },
+            "extra": [{ "text": "url", "clickEvent": { "action": "open_url", "url": "https://example.com" } }],
+        });
+        let component = parse(json.clone());
+        assert!(matches!(
+            component.style.click_event,
+            Some(MaybeKnown::Unknown(_))
+        ));
+        assert_eq!(serde_json::to_value(&component).unwrap(), json);
+    }
+
+    #[test]
+    fn translate_arguments() {
+        let component = parse(json!({
+            "translate": "missing.key",
+            "fallback": "<%s> %2$s and %1$s, 100%%",
+            "with": ["a", "b"],
+        }));
+        assert_eq!(component.plain_text(), "<a> b and a, 100%");
+        let component = parse(json!({
+            "translate": "missing.key",
+            "fallback": "%02$s-%01$s, %+1$s",
+            "with": ["a", "b"],
+        }));
+        assert_eq!(component.plain_text(), "b-a, %+1$s");
+        assert_eq!(
+            parse(json!({ "translate": "multiplayer.disconnect.kicked" })).plain_text(),
+            "multiplayer.disconnect.kicked"
+        );
+    }
+
+    #[test]
+    fn legacy_codes() {
+        let component = TextComponent::text("§6§lGold§r plain §x§f§f§0§0§8§8hex");
+        let spans = component.spans();
+        assert_eq!(spans.len(), 3);
+        assert_eq!(spans[0].text, "Gold");
+        assert_eq!(spans[0].style.color, Some(Color::Named(NamedColor::Gold)));
+        assert_eq!(spans[0].style.bold, Some(true));
+        assert_eq!(spans[1].text, " plain ");
+        assert_eq!(spans[1].style, Style::default());
+        assert_eq!(spans[2].style.color, Some(Color::Rgb(0xff, 0x00, 0x88)));
+        assert_eq!(component.plain_text(), "Gold plain hex");
+
+        // a color resets the formatting before it
+        let spans = TextComponent::text("§l§cred").spans();
+        assert_eq!(spans[0].style.bold, None);
+
+        assert_eq!(TextComponent::from_legacy("§aA§lB").to_legacy(), "§aA§a§lB");
+    }
+
+    #[test]
+    fn ansi() {
+        let component = parse(json!({
+            "text": "a",
+            "color": "red",
+            "extra": [{ "text": "b", "bold": true, "color": "#010203" }],
+        }));
+        assert_eq!(
+            component.to_ansi(),
+            "\x1b[0;38;2;255;85;85ma\x1b[0;1;38;2;1;2;3mb\x1b[0m"
+        );
+        assert_eq!(TextComponent::text("").to_ansi(), "");
+    }
+
+    #[test]
+    fn statuses_with_unknown_colors_parse() {
+        let status: crate::types::server::Status = serde_json::from_value(json!({
+            "version": { "name": "1.21.4", "protocol": 769 },
+            "players": { "max": 20, "online": 0 },
+            "description": {
+                "text": "a",
+                "color": "pink",
+                "extra": [{ "text": "b", "color": "#12345" }],
+            },
+        }))
+        .unwrap();
+        let description = &status.description;
+        assert_eq!(description.style.color, Some(Color::Unknown("pink".into())));
+        assert_eq!(description.to_ansi(), "\x1b[0ma\x1b[0mb\x1b[0m");
+        assert_eq!(description.plain_text(), "ab");
+        assert_eq!(
+            serde_json::to_value(description).unwrap()["color"],
+            json!("pink")
+        );
+    }
+}
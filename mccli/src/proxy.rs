@@ -0,0 +1,591 @@
+//! Sitting between a client and a server, decoding what they send each other, e.g. to debug
+//! plugins with a real game client.
+//!
+//! Frames are forwarded exactly as they were received and decoded on the side, so the proxy
+//! stays transparent for packets it doesn't know or can't decode. Each direction follows the
+//! packets that switch its [`State`] and compression is followed once Set Compression is seen.
+//! Only offline-mode servers can be proxied, as encryption would need the proxy to log in
+//! itself. Legacy pings are passed through without being decoded.
+//!
+//! Clients of another protocol version than the [registry's](PROTOCOL_VERSION) are only
+//! forwarded past the status, as the registry would decode their packets as the wrong ones.
+
+use crate::{
+    Error, Result, Timeouts,
+    packet::{
+        Packet,
+        capture::Recorder,
+        handshaking::Handshake,
+        login::{LoginSuccess, SetCompression},
+        read_frame,
+        registry::{Decoded, Direction, PROTOCOL_VERSION, State, TypedPacket as _},
+    },
+};
+use std::{io, net::SocketAddr, sync::Arc, time::Duration};
+use tokio::{
+    io::{AsyncRead, AsyncWrite, AsyncWriteExt as _},
+    net::{TcpListener, TcpStream},
+    sync::Mutex,
+    task::JoinSet,
+};
+
+/// Something that happened on a proxied connection.
+#[derive(Debug)]
+pub enum Event {
+    /// A client connected, the server accepted the connection and the client sent its
+    /// handshake.
+    Connected {
+        peer: SocketAddr,
+        /// The protocol version of the handshake, `None` for legacy pings and handshakes that
+        /// couldn't be decoded.
+        protocol_version: Option<i32>,
+        /// Whether packets past the status are only forwarded and shown as
+        /// [`Decoded::Unknown`], as the client speaks another protocol version than
+        /// [`PROTOCOL_VERSION`].
+        forward_only: bool,
+    },
+    /// A packet is about to be forwarded, decoded in the state its direction was in. Events of
+    /// a connection come in the order the packets were sent.
+    Packet {
+        peer: SocketAddr,
+        direction: Direction,
+        state: State,
+        packet: Result<Box<Decoded<'static>>>,
+    },
+    /// The connection ended, cleanly or not.
+    Disconnected {
+        peer: SocketAddr,
+        result: Result<()>,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct Proxy {
+    upstream: SocketAddr,
+    connect_timeout: Duration,
+    recorder: Option<Recorder>,
+}
+
+impl Proxy {
+    /// A proxy forwarding every connection to `upstream`.
+    pub fn new(upstream: SocketAddr) -> Self {
+        Self {
+            upstream,
+            connect_timeout: Timeouts::default().connect,
+            recorder: None,
+        }
+    }
+
+    /// How long connecting to the server may take, by default that of [`Timeouts`].
+    pub fn connect_timeout(self, connect_timeout: Duration) -> Self {
+        Self {
+            connect_timeout,
+            ..self
+        }
+    }
+
+    /// Records every forwarded packet to `recorder`, each connection under a connection id of its
+    /// own.
+    pub fn record_to(self, recorder: Recorder) -> Self {
+        Self {
+            recorder: Some(recorder),
+            ..self
+        }
+    }
+
+    /// Proxies every connection accepted by `listener`, forever, calling `on_event` as they
+    /// go.
+    pub async fn serve<F>(self, listener: TcpListener, on_event: F) -> io::Result<()>
+    where
+        F: Fn(Event) + Send + Sync + 'static,
+    {
+        let this = Arc::new(self);
+        let on_event = Arc::new(on_event);
+        tracing::info!(addr = %listener.local_addr()?, upstream = %this.upstream, "proxying");
+        loop {
+            let (socket, peer) = match listener.accept().await {
+                Ok(accepted) => accepted,
+                Err(error) => {
+                    tracing::warn!(?error, "failed to accept a connection");
+                    continue;
+                }
+            };
+            let this = this.clone();
+            let on_event = on_event.clone();
+            tokio::spawn(async move {
+                let result = this
+                    .proxy(socket, peer, {
+                        let on_event = on_event.clone();
+                        move |event| on_event(event)
+                    })
+                    .await;
+                on_event(Event::Disconnected { peer, result });
+            });
+        }
+    }
+
+    /// Proxies a single connection until either end closes it.
+    pub async fn proxy<F>(&self, client: TcpStream, peer: SocketAddr, on_event: F) -> Result<()>
+    where
+        F: Fn(Event) + Send + Sync + 'static,
+    {
+        let mut upstream = crate::connect(self.upstream, self.connect_timeout).await?;
+
+        let mut first = [0];
+        if client.peek(&mut first).await? == 1 && first[0] == 0xfe {
+            on_event(Event::Connected {
+                peer,
+                protocol_version: None,
+                forward_only: false,
+            });
+            tracing::info!(%peer, "passing legacy ping through");
+            let mut client = client;
+            tokio::io::copy_bidirectional(&mut client, &mut upstream).await?;
+            return Ok(());
+        }
+
+        let (mut client_r, client_w) = client.into_split();
+        let (upstream_r, upstream_w) = upstream.into_split();
+        let handshake = read_frame(&mut client_r).await?;
+        let protocol_version = match &handshake {
+            Some(frame) => Packet::read_compressed(&frame[..], None)
+                .await
+                .and_then(|packet| packet.decode_borrowed::<Handshake>())
+                .ok()
+                .map(|handshake| i32::from(handshake.protocol_version)),
+            None => None,
+        };
+        let forward_only = protocol_version.is_some_and(|v| v != PROTOCOL_VERSION);
+        if forward_only {
+            tracing::info!(%peer, ?protocol_version, "only forwarding another protocol version");
+        }
+        on_event(Event::Connected {
+            peer,
+            protocol_version,
+            forward_only,
+        });
+
+        let shared = Arc::new(Shared {
+            peer,
+            tracker: Mutex::new(Tracker {
+                forward_only,
+                ..Tracker::default()
+            }),
+            recorder: self.recorder.as_ref().map(Recorder::connection),
+            on_event,
+        });
+        let mut directions = JoinSet::new();
+        directions.spawn(shared.clone().forward(
+            Direction::Serverbound,
+            handshake,
+            client_r,
+            upstream_w,
+        ));
+        directions.spawn(shared.forward(Direction::Clientbound, None, upstream_r, client_w));
+
+        // once an end is closed cleanly let the other finish what it was sending, an error ends
+        // both when `directions` is dropped
+        while let Some(result) = directions.join_next().await {
+            result.unwrap_or_else(|e| std::panic::resume_unwind(e.into_panic()))?;
+        }
+        Ok(())
+    }
+}
+
+/// The states of both directions of a connection, following the packets that switch them.
+#[derive(Debug, Default)]
+struct Tracker {
+    serverbound: State,
+    clientbound: State,
+    compression_threshold: Option<usize>,
+    /// Whether packets past the status are only forwarded, see [`Event::Connected`].
+    forward_only: bool,
+    /// Whether the server sent Login Success, on forward-only connections.
+    logged_in: bool,
+}
+
+impl Tracker {
+    fn state(&self, direction: Direction) -> State {
+        match direction {
+            Direction::Serverbound => self.serverbound,
+            Direction::Clientbound => self.clientbound,
+        }
+    }
+
+    /// Whether packets are decoded in `state`. The handshake and the status are the same in
+    /// every protocol version.
+    fn decodes(&self, state: State) -> bool {
+        !self.forward_only || matches!(state, State::Handshaking | State::Status)
+    }
+
+    /// Follows compression on forward-only connections, whose states stay those of the
+    /// handshake. Set Compression and Login Success have kept their ids since compression came
+    /// in 1.8.
+    fn follow_undecoded(&mut self, direction: Direction, packet: &Packet<'_>) -> Result<()> {
+        if direction != Direction::Clientbound || self.clientbound != State::Login || self.logged_in
+        {
+            return Ok(());
+        }
+        match i32::from(packet.id()) {
+            SetCompression::ID => {
+                let s = packet.decode_borrowed::<SetCompression>()?;
+                self.compression_threshold = usize::try_from(i32::from(s.threshold)).ok();
+            }
+            LoginSuccess::ID => self.logged_in = true,
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Switches states after `packet`. A direction switches as soon as it sends the packet
+    /// that ends its state, so the reply can be decoded before the switch is acknowledged.
+    fn follow(&mut self, packet: &Decoded<'_>) -> Result<()> {
+        match packet {
+            Decoded::Handshake(handshake) => {
+                self.serverbound = handshake.intent.state();
+                self.clientbound = handshake.intent.state();
+            }
+            Decoded::SetCompression(s) => {
+                self.compression_threshold = usize::try_from(i32::from(s.threshold)).ok();
+            }
+            Decoded::EncryptionRequest(_) => return Err(Error::OnlineMode),
+            Decoded::LoginSuccess(_) | Decoded::StartConfiguration(_) => {
+                self.clientbound = State::Configuration;
+            }
+            Decoded::LoginAcknowledged(_) | Decoded::AcknowledgeConfiguration(_) => {
+                self.serverbound = State::Configuration;
+            }
+            Decoded::FinishConfiguration(_) => self.clientbound = State::Play,
+            Decoded::AcknowledgeFinishConfiguration(_) => self.serverbound = State::Play,
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+/// What both directions of a connection share.
+struct Shared<F> {
+    peer: SocketAddr,
+    tracker: Mutex<Tracker>,
+    recorder: Option<Recorder>,
+    on_event: F,
+}
+
+impl<F: Fn(Event) + Send + Sync + 'static> Shared<F> {
+    /// Forwards the packets going in `direction` from `r` to `w`, starting with the `first`
+    /// frame if it was already read.
+    async fn forward<R, W>(
+        self: Arc<Self>,
+        direction: Direction,
+        mut first: Option<Vec<u8>>,
+        mut r: R,
+        mut w: W,
+    ) -> Result<()>
+    where
+        R: AsyncRead + Unpin + Send,
+        W: AsyncWrite + Unpin + Send,
+    {
+        loop {
+            let frame = match first.take() {
+                Some(frame) => frame,
+                None => match read_frame(&mut r).await? {
+                    Some(frame) => frame,
+                    None => break,
+                },
+            };
+            // the tracker has to be up to date before the frame that may switch the other
+            // direction's state is forwarded, as the other end could answer right away
+            let (state, packet) = {
+                let mut tracker = self.tracker.lock().await;
+                let state = tracker.state(direction);
+                let packet = match self
+                    .packet(&frame, direction, state, tracker.compression_threshold)
+                    .await
+                {
+                    Ok(packet) if tracker.decodes(state) => packet.decode(state, direction).await,
+                    Ok(packet) => {
+                        tracker.follow_undecoded(direction, &packet)?;
+                        Ok(Decoded::Unknown(packet))
+                    }
+                    Err(error) => Err(error),
+                };
+                if let Ok(packet) = &packet {
+                    tracker.follow(packet)?;
+                }
+                (state, packet)
+            };
+            (self.on_event)(Event::Packet {
+                peer: self.peer,
+                direction,
+                state,
+                packet: packet.map(Box::new).map_err(Error::from),
+            });
+            w.write_all(&frame).await?;
+        }
+        w.shutdown().await?;
+        Ok(())
+    }
+
+    /// Reads the packet in `frame` and records it.
+    async fn packet(
+        &self,
+        frame: &[u8],
+        direction: Direction,
+        state: State,
+        compression_threshold: Option<usize>,
+    ) -> io::Result<Packet<'static>> {
+        let packet = match Packet::read_compressed(frame, compression_threshold).await {
+            Ok(packet) => packet,
+            Err(error) => {
+                if let Some(recorder) = &self.recorder {
+                    recorder.record_invalid(direction, state, frame.to_vec(), &error);
+                }
+                return Err(error);
+            }
+        };
+        if let Some(recorder) = &self.recorder {
+            recorder.record(direction, state, &packet);
+        }
+        Ok(packet)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        HandshakeOptions, Intent, fetch_server_info, login, login_with, offline_uuid,
+        packet::{
+            configuration::{AcknowledgeFinishConfiguration, FinishConfiguration},
+            connection::Connection,
+            login::{LoginAcknowledged, LoginStart},
+            play::{AcknowledgeConfiguration, StartConfiguration},
+        },
+        serve::Responder,
+        types::{
+            server::{Players, Status, Version},
+            text::TextComponent,
+        },
+    };
+    use tokio::sync::mpsc;
+
+    fn handshake(intent: Intent) -> Decoded<'static> {
+        Decoded::Handshake(Handshake {
+            protocol_version: 769.into(),
+            server_address: std::string::String::from("localhost").into(),
+            server_port: 25565,
+            intent,
+        })
+    }
+
+    #[test]
+    fn states_follow_the_packets() {
+        let mut tracker = Tracker::default();
+        tracker.follow(&handshake(Intent::Transfer)).unwrap();
+        assert_eq!(
+            (tracker.serverbound, tracker.clientbound),
+            (State::Login, State::Login)
+        );
+
+        let steps = [
+            (
+                Decoded::SetCompression(SetCompression {
+                    threshold: 256.into(),
+                }),
+                (State::Login, State::Login),
+            ),
+            (
+                Decoded::LoginSuccess(LoginSuccess {
+                    uuid: offline_uuid("steve"),
+                    username: std::string::String::from("steve").into(),
+                    properties: Vec::new(),
+                }),
+                (State::Login, State::Configuration),
+            ),
+            (
+                Decoded::LoginAcknowledged(LoginAcknowledged),
+                (State::Configuration, State::Configuration),
+            ),
+            (
+                Decoded::FinishConfiguration(FinishConfiguration),
+                (State::Configuration, State::Play),
+            ),
+            (
+                Decoded::AcknowledgeFinishConfiguration(AcknowledgeFinishConfiguration),
+                (State::Play, State::Play),
+            ),
+            (
+                Decoded::StartConfiguration(StartConfiguration),
+                (State::Play, State::Configuration),
+            ),
+            (
+                Decoded::AcknowledgeConfiguration(AcknowledgeConfiguration),
+                (State::Configuration, State::Configuration),
+            ),
+        ];
+        for (packet, states) in steps {
+            tracker.follow(&packet).unwrap();
+            assert_eq!(
+                (tracker.serverbound, tracker.clientbound),
+                states,
+                "after {packet:?}"
+            );
+        }
+        assert_eq!(tracker.compression_threshold, Some(256));
+    }
+
+    async fn spawn(upstream: SocketAddr) -> (SocketAddr, mpsc::UnboundedReceiver<Event>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (tx, rx) = mpsc::unbounded_channel();
+        tokio::spawn(Proxy::new(upstream).serve(listener, move |event| {
+            let _ = tx.send(event);
+        }));
+        (addr, rx)
+    }
+
+    /// The packets of the next connection, until it ends.
+    async fn packets(
+        events: &mut mpsc::UnboundedReceiver<Event>,
+    ) -> Vec<(Direction, State, &'static str)> {
+        let mut packets = Vec::new();
+        loop {
+            match events.recv().await.unwrap() {
+                Event::Connected { .. } => {}
+                Event::Packet {
+                    direction,
+                    state,
+                    packet,
+                    ..
+                } => packets.push((direction, state, packet.unwrap().name())),
+                Event::Disconnected { result, .. } => {
+                    result.unwrap();
+                    return packets;
+                }
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn proxies_status_requests() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let upstream = listener.local_addr().unwrap();
+        let status = Status::new(
+            Version::new("Proxied", 769),
+            Players::new(20, 0),
+            TextComponent::text("behind a proxy"),
+        );
+        tokio::spawn(Responder::new(&status).serve(listener, |_| {}));
+        let (addr, mut events) = spawn(upstream).await;
+
+        let info = fetch_server_info(addr, &HandshakeOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(info.status.version.name, "Proxied");
+        assert_eq!(
+            packets(&mut events).await,
+            [
+                (Direction::Serverbound, State::Handshaking, "Handshake"),
+                (Direction::Serverbound, State::Status, "StatusRequest"),
+                (Direction::Clientbound, State::Status, "StatusResponse"),
+                (Direction::Serverbound, State::Status, "PingRequest"),
+                (Direction::Clientbound, State::Status, "PongResponse"),
+            ]
+        );
+    }
+
+    /// An offline-mode server that logs a player in, compressing from Set Compression on.
+    async fn login_server() -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            let mut server = Connection::server(socket);
+            server.read().await.unwrap();
+            server.set_state(State::Login);
+            let Decoded::LoginStart(start) = server.read_decoded().await.unwrap() else {
+                panic!("expected login start");
+            };
+            server
+                .send(&SetCompression {
+                    threshold: 8.into(),
+                })
+                .await
+                .unwrap();
+            server.set_compression(8);
+            server
+                .send(&LoginSuccess {
+                    uuid: start.uuid,
+                    username: start.name,
+                    properties: Vec::new(),
+                })
+                .await
+                .unwrap();
+            server.read_decoded().await.unwrap();
+        });
+        addr
+    }
+
+    #[tokio::test]
+    async fn follows_compression() {
+        let (addr, mut events) = spawn(login_server().await).await;
+
+        let session = login(addr, "steve").await.unwrap();
+        assert_eq!(session.connection.compression_threshold(), Some(8));
+        drop(session);
+        assert_eq!(
+            packets(&mut events).await,
+            [
+                (Direction::Serverbound, State::Handshaking, "Handshake"),
+                (Direction::Serverbound, State::Login, "LoginStart"),
+                (Direction::Clientbound, State::Login, "SetCompression"),
+                (Direction::Clientbound, State::Login, "LoginSuccess"),
+                (Direction::Serverbound, State::Login, "LoginAcknowledged"),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn only_forwards_other_protocol_versions() {
+        let (addr, mut events) = spawn(login_server().await).await;
+
+        let handshake = HandshakeOptions {
+            protocol_version: 767,
+            ..HandshakeOptions::new("localhost", addr.port())
+        };
+        drop(login_with(addr, &handshake, "steve").await.unwrap());
+        assert!(matches!(
+            events.recv().await.unwrap(),
+            Event::Connected {
+                protocol_version: Some(767),
+                forward_only: true,
+                ..
+            }
+        ));
+        let mut packets = Vec::new();
+        loop {
+            match events.recv().await.unwrap() {
+                Event::Packet {
+                    direction, packet, ..
+                } => packets.push(match *packet.unwrap() {
+                    Decoded::Unknown(packet) => (direction, i32::from(packet.id())),
+                    Decoded::Handshake(_) => (direction, Handshake::ID),
+                    other => panic!("decoded {other:?}"),
+                }),
+                Event::Disconnected { result, .. } => break result.unwrap(),
+                Event::Connected { .. } => unreachable!(),
+            }
+        }
+        // the handshake, then undecoded packets that are split right even once compressed
+        assert_eq!(
+            packets,
+            [
+                (Direction::Serverbound, Handshake::ID),
+                (Direction::Serverbound, LoginStart::ID),
+                (Direction::Clientbound, SetCompression::ID),
+                (Direction::Clientbound, LoginSuccess::ID),
+                (Direction::Serverbound, LoginAcknowledged::ID),
+            ]
+        );
+    }
+}
@@ -0,0 +1,119 @@
+//! Querying many servers at once.
+//!
+//! ```no_run
+//! # async fn example() {
+//! use mccli::scan::Scanner;
+//!
+//! let mut reports = Scanner::new()
+//!     .concurrency(32)
+//!     .scan(vec!["mc.example.com".to_owned(), "10.0.0.2:25566".to_owned()]);
+//! while let Some(report) = reports.recv().await {
+//!     println!("{}: {}", report.target, report.result.is_ok());
+//! }
+//! # }
+//! ```
+
+use crate::{
+    Limits, Result, ServerInfo, Timeouts, fetch_server_info_with_timeouts, resolve_with_timeout,
+};
+use std::{sync::Arc, time::Duration};
+use tokio::{
+    sync::{Semaphore, mpsc},
+    time::Instant,
+};
+
+/// The outcome of querying one target.
+#[derive(Debug)]
+pub struct Report {
+    /// The address as it was given to [`Scanner::scan`].
+    pub target: String,
+    pub result: Result<ServerInfo>,
+    /// How long the whole query took, including resolving the address.
+    pub elapsed: Duration,
+}
+
+#[derive(Debug, Clone)]
+pub struct Scanner {
+    concurrency: usize,
+    timeouts: Timeouts,
+    limits: Limits,
+}
+
+impl Default for Scanner {
+    fn default() -> Self {
+        Self {
+            concurrency: 16,
+            timeouts: Timeouts::default(),
+            limits: Limits::default(),
+        }
+    }
+}
+
+impl Scanner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// How many servers are queried at the same time, at least 1. Defaults to 16.
+    pub fn concurrency(self, concurrency: usize) -> Self {
+        Self {
+            concurrency: concurrency.max(1),
+            ..self
+        }
+    }
+
+    pub fn timeouts(self, timeouts: Timeouts) -> Self {
+        Self { timeouts, ..self }
+    }
+
+    /// How much each server may send. Defaults to the protocol's maximums.
+    pub fn limits(self, limits: Limits) -> Self {
+        Self { limits, ..self }
+    }
+
+    /// Queries every target, which are resolved like [`resolve`](crate::resolve) does. Reports are sent as soon
+    /// as each query finishes, so they don't come in the order of `targets`.
+    ///
+    /// Must be called from within a tokio runtime. Dropping the receiver stops the scan.
+    pub fn scan<I>(self, targets: I) -> mpsc::Receiver<Report>
+    where
+        I: IntoIterator<Item = String>,
+        I::IntoIter: Send + 'static,
+    {
+        let (tx, rx) = mpsc::channel(self.concurrency);
+        let semaphore = Arc::new(Semaphore::new(self.concurrency));
+        let timeouts = self.timeouts;
+        let limits = self.limits;
+        let targets = targets.into_iter();
+        tokio::spawn(async move {
+            for target in targets {
+                let Ok(permit) = semaphore.clone().acquire_owned().await else {
+                    break;
+                };
+                if tx.is_closed() {
+                    break;
+                }
+                let tx = tx.clone();
+                tokio::spawn(async move {
+                    let start = Instant::now();
+                    let result = limits.scope(probe(&target, &timeouts)).await;
+                    let _ = tx
+                        .send(Report {
+                            target,
+                            result,
+                            elapsed: start.elapsed(),
+                        })
+                        .await;
+                    drop(permit);
+                });
+            }
+        });
+        rx
+    }
+}
+
+/// Resolves `target` and queries it.
+pub(crate) async fn probe(target: &str, timeouts: &Timeouts) -> Result<ServerInfo> {
+    let (addr, handshake) = resolve_with_timeout(target, timeouts).await?;
+    fetch_server_info_with_timeouts(addr, &handshake, timeouts).await
+}
@@ -0,0 +1,290 @@
+//! Answering server list pings like a server would, e.g. as a placeholder while the real server
+//! is down for maintenance.
+//!
+//! Status requests get the configured [`Status`], pings are ponged, legacy (pre 1.7) pings get
+//! the same status in the legacy format and players trying to join are disconnected with a kick
+//! message.
+
+use crate::{
+    Error, Intent, Result, Timeouts, legacy,
+    packet::{
+        Packet,
+        connection::Connection,
+        handshaking::Handshake,
+        login::Disconnect,
+        registry::{Decoded, Direction, State},
+        status::{PongResponse, StatusResponse},
+    },
+    types::{server::Status, text::TextComponent},
+};
+use std::{io, net::SocketAddr, sync::Arc, time::Duration};
+use tokio::{
+    io::{AsyncReadExt as _, AsyncWriteExt as _},
+    net::{TcpListener, TcpStream},
+};
+
+/// Someone that talked to the [`Responder`].
+#[derive(Debug, Clone)]
+pub enum Event {
+    /// A status request was answered.
+    Status {
+        peer: SocketAddr,
+        handshake: Handshake,
+    },
+    /// A legacy ping was answered.
+    LegacyPing { peer: SocketAddr },
+    /// A player tried to join and was disconnected.
+    Login {
+        peer: SocketAddr,
+        handshake: Handshake,
+        username: std::string::String,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct Responder {
+    status_json: std::string::String,
+    legacy_response: Vec<u8>,
+    kick_message: TextComponent,
+    read_timeout: Duration,
+}
+
+impl Responder {
+    pub fn new(status: &Status) -> Self {
+        Self {
+            status_json: serde_json::to_string(status).expect("status is always serializable"),
+            legacy_response: legacy::encode_response(status),
+            kick_message: status.description.clone(),
+            read_timeout: Timeouts::default().read,
+        }
+    }
+
+    /// The reason players that try to join are disconnected with, by default the description.
+    pub fn kick_message(self, kick_message: impl Into<TextComponent>) -> Self {
+        Self {
+            kick_message: kick_message.into(),
+            ..self
+        }
+    }
+
+    /// How long to wait for each packet from a client before dropping it, by default
+    /// [`Timeouts::read`].
+    pub fn read_timeout(self, read_timeout: Duration) -> Self {
+        Self {
+            read_timeout,
+            ..self
+        }
+    }
+
+    /// Answers every connection accepted by `listener`, forever, calling `on_event` after each
+    /// one that was answered.
+    pub async fn serve<F>(self, listener: TcpListener, on_event: F) -> io::Result<()>
+    where
+        F: Fn(Event) + Send + Sync + 'static,
+    {
+        let this = Arc::new(self);
+        let on_event = Arc::new(on_event);
+        tracing::info!(addr = %listener.local_addr()?, "answering status requests");
+        loop {
+            let (socket, peer) = match listener.accept().await {
+                Ok(accepted) => accepted,
+                Err(error) => {
+                    tracing::warn!(?error, "failed to accept a connection");
+                    continue;
+                }
+            };
+            let this = this.clone();
+            let on_event = on_event.clone();
+            tokio::spawn(async move {
+                match this.respond(socket, peer).await {
+                    Ok(event) => on_event(event),
+                    Err(error) => tracing::debug!(%peer, ?error, "connection failed"),
+                }
+            });
+        }
+    }
+
+    /// Answers a single connection, giving up on clients that take longer than the
+    /// [read timeout](Responder::read_timeout) to send a packet.
+    pub async fn respond(&self, mut socket: TcpStream, peer: SocketAddr) -> Result<Event> {
+        let limit = self.read_timeout;
+        let Some(handshake) =
+            crate::timeout("reading handshake", limit, read_handshake(&mut socket)).await?
+        else {
+            tracing::info!(%peer, "answering legacy ping");
+            socket.write_all(&self.legacy_response).await?;
+            return Ok(Event::LegacyPing { peer });
+        };
+
+        let mut connection = Connection::server(socket);
+        match handshake.intent {
+            Intent::Status => {
+                connection.set_state(State::Status);
+                tracing::info!(%peer, host = %handshake.server_address, "answering status request");
+                loop {
+                    match crate::timeout("reading status request", limit, connection.read_decoded())
+                        .await
+                    {
+                        Ok(Decoded::StatusRequest(_)) => {
+                            connection
+                                .send(&StatusResponse {
+                                    json: self.status_json.clone().into(),
+                                })
+                                .await?;
+                        }
+                        Ok(Decoded::PingRequest(ping)) => {
+                            connection
+                                .send(&PongResponse {
+                                    payload: ping.payload,
+                                })
+                                .await?;
+                            break;
+                        }
+                        Ok(other) => {
+                            return Err(Error::Protocol(format!(
+                                "unexpected packet during status: {}",
+                                other.name()
+                            )));
+                        }
+                        // clients that only want the status close the connection early
+                        Err(Error::Io(e)) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+                        Err(e) => return Err(e),
+                    }
+                }
+                Ok(Event::Status { peer, handshake })
+            }
+            Intent::Login | Intent::Transfer => {
+                connection.set_state(State::Login);
+                let Decoded::LoginStart(start) =
+                    crate::timeout("reading login start", limit, connection.read_decoded()).await?
+                else {
+                    return Err(Error::Protocol("expected login start".to_owned()));
+                };
+                tracing::info!(%peer, username = %start.name, "disconnecting player");
+                connection
+                    .send(&Disconnect {
+                        reason: serde_json::to_string(&self.kick_message)
+                            .expect("text components are always serializable")
+                            .into(),
+                    })
+                    .await?;
+                Ok(Event::Login {
+                    peer,
+                    handshake,
+                    username: start.name.to_string(),
+                })
+            }
+        }
+    }
+}
+
+/// Reads the handshake that starts every modern connection, or `None` if the client sent a
+/// legacy ping instead.
+pub(crate) async fn read_handshake(socket: &mut TcpStream) -> io::Result<Option<Handshake>> {
+    let first = socket.read_u8().await?;
+    if first == 0xfe {
+        return Ok(None);
+    }
+    match Packet::read((&[first][..]).chain(&mut *socket))
+        .await?
+        .decode(State::Handshaking, Direction::Serverbound)
+        .await?
+    {
+        Decoded::Handshake(handshake) => Ok(Some(handshake)),
+        other => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("expected a handshake, got {}", other.name()),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        HandshakeOptions, fetch_server_info, login,
+        types::server::{Players, Version},
+    };
+    use tokio::sync::mpsc;
+
+    fn status() -> Status {
+        Status::new(
+            Version::new("Maintenance", 769),
+            Players::new(20, 0),
+            TextComponent::text("back soon"),
+        )
+    }
+
+    async fn spawn(responder: Responder) -> (SocketAddr, mpsc::UnboundedReceiver<Event>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (tx, rx) = mpsc::unbounded_channel();
+        tokio::spawn(responder.serve(listener, move |event| {
+            let _ = tx.send(event);
+        }));
+        (addr, rx)
+    }
+
+    #[tokio::test]
+    async fn answers_status_and_ping() {
+        let (addr, mut events) = spawn(Responder::new(&status())).await;
+
+        let info = fetch_server_info(addr, &HandshakeOptions::new("example.com", 25565))
+            .await
+            .unwrap();
+        assert_eq!(info.status.version.name, "Maintenance");
+        assert_eq!(info.status.description.plain_text(), "back soon");
+
+        let Some(Event::Status { handshake, .. }) = events.recv().await else {
+            panic!("expected a status event");
+        };
+        assert_eq!(&*handshake.server_address, "example.com");
+    }
+
+    #[tokio::test]
+    async fn answers_legacy_ping() {
+        let (addr, mut events) = spawn(Responder::new(&status())).await;
+
+        let info =
+            legacy::fetch_server_info(addr, &HandshakeOptions::default(), &Default::default())
+                .await
+                .unwrap();
+        assert_eq!(info.status.version.name, "Maintenance");
+        assert_eq!(info.status.players.max, 20);
+        assert!(matches!(
+            events.recv().await,
+            Some(Event::LegacyPing { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn kicks_players() {
+        let (addr, mut events) =
+            spawn(Responder::new(&status()).kick_message("under maintenance")).await;
+
+        let error = login(addr, "steve").await.unwrap_err();
+        assert!(error.to_string().contains("under maintenance"), "{error}");
+
+        let Some(Event::Login { username, .. }) = events.recv().await else {
+            panic!("expected a login event");
+        };
+        assert_eq!(username, "steve");
+    }
+
+    #[tokio::test]
+    async fn drops_silent_clients() {
+        let responder = Responder::new(&status()).read_timeout(Duration::from_millis(50));
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        // one that sends nothing, one that stops in the middle of the handshake
+        for sent in [&[][..], &[0x10, 0x00]] {
+            let mut client = TcpStream::connect(addr).await.unwrap();
+            client.write_all(sent).await.unwrap();
+            let (socket, peer) = listener.accept().await.unwrap();
+
+            let error = responder.respond(socket, peer).await.unwrap_err();
+            assert!(matches!(error, Error::Timeout { .. }), "{error:?}");
+        }
+    }
+}
@@ -0,0 +1,414 @@
+//! A minimal DNS client, just enough to follow `_minecraft._tcp` SRV records.
+
+use rand::Rng;
+use std::{
+    io,
+    net::{Ipv4Addr, Ipv6Addr, SocketAddr},
+    time::Duration,
+};
+use tokio::net::UdpSocket;
+
+const TYPE_SRV: u16 = 33;
+const CLASS_IN: u16 = 1;
+const FLAG_RECURSION_DESIRED: u16 = 0x0100;
+const FLAG_RESPONSE: u16 = 0x8000;
+const RCODE_MASK: u16 = 0x000f;
+const RCODE_NXDOMAIN: u16 = 3;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SrvRecord {
+    pub priority: u16,
+    pub weight: u16,
+    pub port: u16,
+    pub target: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct Resolver {
+    nameserver: SocketAddr,
+    timeout: Duration,
+}
+
+impl Resolver {
+    pub fn new(nameserver: SocketAddr) -> Self {
+        Self {
+            nameserver,
+            timeout: Duration::from_secs(5),
+        }
+    }
+
+    /// Uses the first nameserver in `/etc/resolv.conf`.
+    pub fn from_system() -> io::Result<Self> {
+        let conf = std::fs::read_to_string("/etc/resolv.conf")?;
+        conf.lines()
+            .filter_map(|l| l.trim().strip_prefix("nameserver"))
+            .find_map(|ns| ns.trim().parse().ok())
+            .map(|ip| Self::new(SocketAddr::new(ip, 53)))
+            .ok_or_else(|| {
+                io::Error::new(io::ErrorKind::NotFound, "no nameserver in /etc/resolv.conf")
+            })
+    }
+
+    pub fn with_timeout(self, timeout: Duration) -> Self {
+        Self { timeout, ..self }
+    }
+
+    pub async fn lookup_srv(&self, name: &str) -> io::Result<Vec<SrvRecord>> {
+        // random, so that responses can't be spoofed without seeing the query
+        let id = rand::random::<u16>();
+
+        let bind = if self.nameserver.is_ipv4() {
+            SocketAddr::from((Ipv4Addr::UNSPECIFIED, 0))
+        } else {
+            SocketAddr::from((Ipv6Addr::UNSPECIFIED, 0))
+        };
+        let socket = UdpSocket::bind(bind).await?;
+        socket.connect(self.nameserver).await?;
+
+        tracing::debug!(%name, nameserver = %self.nameserver, "querying SRV record");
+        socket.send(&query(id, name)?).await?;
+
+        let mut buffer = [0; 1232];
+        loop {
+            let len = tokio::time::timeout(self.timeout, socket.recv(&mut buffer))
+                .await
+                .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "dns query timed out"))??;
+            let response = &buffer[..len];
+            // ignore stray datagrams that don't answer our query
+            if response.get(..2) == Some(&id.to_be_bytes()) {
+                return parse_response(response);
+            }
+        }
+    }
+
+    /// Follows the `_minecraft._tcp.<host>` record, returning the preferred target and port.
+    ///
+    /// The target is picked among the records of the lowest priority, at random according to
+    /// their weights, see [`pick`].
+    pub async fn resolve_minecraft(&self, host: &str) -> io::Result<Option<(String, u16)>> {
+        let records = self.lookup_srv(&format!("_minecraft._tcp.{host}")).await?;
+        Ok(pick(&records, &mut rand::thread_rng())
+            .map(|r| (r.target.trim_end_matches('.').to_owned(), r.port)))
+    }
+}
+
+/// Picks the record to connect to as RFC 2782 says: among the records of the lowest priority,
+/// each is picked with a probability proportional to its weight, and records of weight 0 only
+/// have a small chance of being picked.
+pub fn pick<'r>(records: &'r [SrvRecord], rng: &mut impl Rng) -> Option<&'r SrvRecord> {
+    let priority = records.iter().map(|r| r.priority).min()?;
+    let mut candidates = records
+        .iter()
+        .filter(|r| r.priority == priority)
+        .collect::<Vec<_>>();
+    // the RFC puts records of weight 0 first, so that they only win a draw of 0
+    candidates.sort_by_key(|r| r.weight != 0);
+    let total = candidates.iter().map(|r| u32::from(r.weight)).sum::<u32>();
+    let mut draw = rng.gen_range(0..=total);
+    candidates.into_iter().find(|r| {
+        if u32::from(r.weight) >= draw {
+            return true;
+        }
+        draw -= u32::from(r.weight);
+        false
+    })
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("malformed dns response: {msg}"),
+    )
+}
+
+fn query(id: u16, name: &str) -> io::Result<Vec<u8>> {
+    let mut buffer = Vec::with_capacity(18 + name.len());
+    buffer.extend(id.to_be_bytes());
+    buffer.extend(FLAG_RECURSION_DESIRED.to_be_bytes());
+    // one question, no answers, authority or additional records
+    buffer.extend([0, 1, 0, 0, 0, 0, 0, 0]);
+    for label in name.trim_end_matches('.').split('.') {
+        if label.is_empty() || label.len() > 63 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid domain name: {name:?}"),
+            ));
+        }
+        buffer.push(label.len() as u8);
+        buffer.extend(label.as_bytes());
+    }
+    buffer.push(0);
+    buffer.extend(TYPE_SRV.to_be_bytes());
+    buffer.extend(CLASS_IN.to_be_bytes());
+    Ok(buffer)
+}
+
+struct Message<'m> {
+    bytes: &'m [u8],
+    position: usize,
+}
+
+impl Message<'_> {
+    fn u16(&mut self) -> io::Result<u16> {
+        let bytes = self
+            .bytes
+            .get(self.position..self.position + 2)
+            .ok_or_else(|| invalid_data("truncated"))?;
+        self.position += 2;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn skip(&mut self, n: usize) -> io::Result<()> {
+        if self.position + n > self.bytes.len() {
+            return Err(invalid_data("truncated"));
+        }
+        self.position += n;
+        Ok(())
+    }
+
+    /// Reads a possibly compressed domain name.
+    fn name(&mut self) -> io::Result<String> {
+        let mut name = String::new();
+        let mut position = self.position;
+        let mut resume_at = None;
+        // guards against pointers that form a loop
+        let mut jumps = 0;
+        loop {
+            let len = *self
+                .bytes
+                .get(position)
+                .ok_or_else(|| invalid_data("truncated"))?;
+            match len {
+                0 => {
+                    position += 1;
+                    break;
+                }
+                len if len & 0xc0 == 0xc0 => {
+                    let low = *self
+                        .bytes
+                        .get(position + 1)
+                        .ok_or_else(|| invalid_data("truncated"))?;
+                    resume_at.get_or_insert(position + 2);
+                    position = usize::from(u16::from_be_bytes([len & 0x3f, low]));
+                    jumps += 1;
+                    if jumps > 64 {
+                        return Err(invalid_data("compression loop"));
+                    }
+                }
+                len => {
+                    let label = self
+                        .bytes
+                        .get(position + 1..position + 1 + usize::from(len))
+                        .ok_or_else(|| invalid_data("truncated"))?;
+                    if !name.is_empty() {
+                        name.push('.');
+                    }
+                    name.push_str(&String::from_utf8_lossy(label));
+                    position += 1 + usize::from(len);
+                }
+            }
+        }
+        self.position = resume_at.unwrap_or(position);
+        Ok(name)
+    }
+}
+
+fn parse_response(bytes: &[u8]) -> io::Result<Vec<SrvRecord>> {
+    let mut message = Message { bytes, position: 2 };
+    let flags = message.u16()?;
+    if flags & FLAG_RESPONSE == 0 {
+        return Err(invalid_data("not a response"));
+    }
+    match flags & RCODE_MASK {
+        0 => {}
+        RCODE_NXDOMAIN => return Ok(Vec::new()),
+        rcode => {
+            return Err(io::Error::other(format!(
+                "dns server returned error code {rcode}"
+            )));
+        }
+    }
+    let questions = message.u16()?;
+    let answers = message.u16()?;
+    message.skip(4)?;
+
+    for _ in 0..questions {
+        message.name()?;
+        message.skip(4)?;
+    }
+
+    let mut records = Vec::new();
+    for _ in 0..answers {
+        message.name()?;
+        let kind = message.u16()?;
+        let class = message.u16()?;
+        message.skip(4)?;
+        let length = usize::from(message.u16()?);
+        let end = message.position + length;
+        if end > bytes.len() {
+            return Err(invalid_data("truncated"));
+        }
+        if kind == TYPE_SRV && class == CLASS_IN {
+            // the record can't go past its data, though its name may point before it
+            let mut data = Message {
+                bytes: &bytes[..end],
+                position: message.position,
+            };
+            records.push(SrvRecord {
+                priority: data.u16()?,
+                weight: data.u16()?,
+                port: data.u16()?,
+                target: data.name()?,
+            });
+        }
+        message.position = end;
+    }
+    Ok(records)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Answers every query with the given records, compressing the owner name.
+    async fn stub_resolver(records: Vec<SrvRecord>) -> SocketAddr {
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let addr = socket.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut buffer = [0; 512];
+            while let Ok((len, peer)) = socket.recv_from(&mut buffer).await {
+                let request = &buffer[..len];
+                let mut response = request[..2].to_vec();
+                response.extend((FLAG_RESPONSE | FLAG_RECURSION_DESIRED).to_be_bytes());
+                response.extend([0, 1]);
+                response.extend((records.len() as u16).to_be_bytes());
+                response.extend([0, 0, 0, 0]);
+                response.extend(&request[12..]);
+                for r in &records {
+                    // pointer to the name in the question section
+                    response.extend([0xc0, 12]);
+                    response.extend(TYPE_SRV.to_be_bytes());
+                    response.extend(CLASS_IN.to_be_bytes());
+                    response.extend(300u32.to_be_bytes());
+                    let mut target = Vec::new();
+                    for label in r.target.split('.') {
+                        target.push(label.len() as u8);
+                        target.extend(label.as_bytes());
+                    }
+                    target.push(0);
+                    response.extend((6 + target.len() as u16).to_be_bytes());
+                    response.extend(r.priority.to_be_bytes());
+                    response.extend(r.weight.to_be_bytes());
+                    response.extend(r.port.to_be_bytes());
+                    response.extend(target);
+                }
+                socket.send_to(&response, peer).await.unwrap();
+            }
+        });
+        addr
+    }
+
+    fn record(priority: u16, weight: u16, port: u16, target: &str) -> SrvRecord {
+        SrvRecord {
+            priority,
+            weight,
+            port,
+            target: target.into(),
+        }
+    }
+
+    #[tokio::test]
+    async fn lookup_srv() {
+        let records = vec![
+            record(10, 5, 25566, "mc1.example.com"),
+            record(20, 5, 25567, "mc2.example.com"),
+        ];
+        let resolver = Resolver::new(stub_resolver(records.clone()).await);
+
+        let answer = resolver
+            .lookup_srv("_minecraft._tcp.example.com")
+            .await
+            .unwrap();
+        assert_eq!(answer, records);
+    }
+
+    #[tokio::test]
+    async fn resolve_picks_lowest_priority() {
+        let resolver = Resolver::new(
+            stub_resolver(vec![
+                record(20, 100, 1, "backup.example.com"),
+                record(10, 50, 2, "primary.example.com"),
+            ])
+            .await,
+        );
+
+        let target = resolver.resolve_minecraft("example.com").await.unwrap();
+        assert_eq!(target, Some(("primary.example.com".into(), 2)));
+    }
+
+    #[test]
+    fn picks_are_weighted() {
+        use rand::{SeedableRng, rngs::StdRng};
+
+        let records = [
+            record(20, 100, 1, "backup.example.com"),
+            record(10, 0, 2, "rarely.example.com"),
+            record(10, 1, 3, "light.example.com"),
+            record(10, 3, 4, "heavy.example.com"),
+        ];
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut picks = [0; 5];
+        for _ in 0..4000 {
+            picks[usize::from(pick(&records, &mut rng).unwrap().port)] += 1;
+        }
+        assert_eq!(picks[1], 0);
+        // only wins a draw of 0, one in 5
+        assert!(picks[2] < 1200, "{picks:?}");
+        assert!(picks[3] > 400 && picks[4] > 2 * picks[3], "{picks:?}");
+
+        let zeros = [record(10, 0, 1, "a"), record(10, 0, 2, "b")];
+        assert_eq!(pick(&zeros, &mut rng).unwrap().port, 1);
+        assert_eq!(pick(&[], &mut rng), None);
+    }
+
+    #[test]
+    fn records_stay_within_their_data() {
+        let mut response = query(7, "example.com").unwrap();
+        response[2..4].copy_from_slice(&FLAG_RESPONSE.to_be_bytes());
+        response[7] = 1;
+        response.extend([0xc0, 12]);
+        response.extend(TYPE_SRV.to_be_bytes());
+        response.extend(CLASS_IN.to_be_bytes());
+        response.extend(300u32.to_be_bytes());
+        // claims 4 bytes of data, the target name spilling out of them
+        response.extend(4u16.to_be_bytes());
+        response.extend([0, 10, 0, 5, 0x63, 0xdd, 1, b'a', 0]);
+        assert!(parse_response(&response).is_err());
+    }
+
+    #[tokio::test]
+    async fn no_records() {
+        let resolver = Resolver::new(stub_resolver(vec![]).await);
+
+        let target = resolver.resolve_minecraft("example.com").await.unwrap();
+        assert_eq!(target, None);
+    }
+
+    #[test]
+    fn nxdomain_is_empty() {
+        let mut response = query(7, "_minecraft._tcp.example.com").unwrap();
+        response[2..4].copy_from_slice(&(FLAG_RESPONSE | RCODE_NXDOMAIN).to_be_bytes());
+        assert_eq!(parse_response(&response).unwrap(), vec![]);
+    }
+
+    #[test]
+    fn compression_loop_is_rejected() {
+        let mut response = query(7, "example.com").unwrap();
+        response[2..4].copy_from_slice(&FLAG_RESPONSE.to_be_bytes());
+        response[7] = 1;
+        // an answer whose name points at itself
+        let at = response.len() as u8;
+        response.extend([0xc0, at]);
+        assert!(parse_response(&response).is_err());
+    }
+}
@@ -0,0 +1,314 @@
+//! Test helpers, mainly a fake server on a loopback socket, to test clients against specific
+//! server behaviours.
+//!
+//! ```no_run
+//! # async fn example() -> std::io::Result<()> {
+//! use mccli::testing::{Behaviour, MockServer};
+//!
+//! let server = MockServer::builder()
+//!     .status_json(r#"{"version":{"name":"1.21.4","protocol":769},"players":{"max":20,"online":0},"description":"hi"}"#)
+//!     .behaviour(Behaviour::NoPong)
+//!     .spawn()
+//!     .await?;
+//! let result = mccli::fetch_server_info(server.addr(), &Default::default()).await;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{
+    legacy,
+    packet::{
+        Packet,
+        connection::Connection,
+        handshaking::Handshake,
+        registry::{Decoded, State},
+        status::{PongResponse, StatusResponse},
+        types::{McType as _, VarInt},
+    },
+    serve,
+    types::server::Status,
+};
+use std::{
+    io::{self, Write},
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+use tokio::{
+    io::AsyncWriteExt as _,
+    net::{TcpListener, TcpStream},
+    task::{JoinHandle, JoinSet},
+};
+
+/// What the server does after receiving a modern handshake.
+#[derive(Debug, Clone, Default)]
+pub enum Behaviour {
+    /// Answers the status request and the ping like a vanilla server.
+    #[default]
+    Normal,
+    /// Answers the status request and closes the connection instead of answering the ping.
+    NoPong,
+    /// Answers the ping with a different payload.
+    WrongPong,
+    /// Sends a malformed frame instead of the status response.
+    Malformed(Malformed),
+    /// Closes the connection right after the handshake.
+    Close,
+    /// Keeps the connection open without ever answering.
+    Silent,
+}
+
+#[derive(Debug, Clone)]
+pub enum Malformed {
+    /// A status response whose json is invalid.
+    InvalidJson,
+    /// A frame length VarInt that never ends.
+    VarIntTooLong,
+    /// A frame that claims to be longer than what is sent before closing.
+    Truncated,
+    /// A valid status response sent with a packet id other than the status response's.
+    WrongPacketId,
+    /// A status response whose json claims to be 2 GiB long.
+    HugeString,
+    /// Arbitrary bytes.
+    Raw(Vec<u8>),
+}
+
+impl Malformed {
+    /// The bytes sent instead of a status response answering with `status_json`.
+    async fn bytes(&self, status_json: &str) -> io::Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        match self {
+            Self::InvalidJson => {
+                Packet::encode(&StatusResponse {
+                    json: "{\"version\":".to_owned().into(),
+                })
+                .await?
+                .write(&mut bytes)
+                .await?;
+            }
+            Self::VarIntTooLong => bytes.extend([0xff; 8]),
+            Self::Truncated => {
+                VarInt::from(100).write(&mut bytes).await?;
+                bytes.extend([0x00, 0x10]);
+            }
+            Self::WrongPacketId => {
+                Packet::new(
+                    0x42.into(),
+                    &StatusResponse {
+                        json: status_json.to_owned().into(),
+                    },
+                )
+                .await?
+                .write(&mut bytes)
+                .await?;
+            }
+            Self::HugeString => {
+                let mut payload = Vec::new();
+                VarInt::from(0x00).write(&mut payload).await?;
+                VarInt::from(i32::MAX).write(&mut payload).await?;
+                payload.extend(b"{}");
+                VarInt::try_from(payload.len())
+                    .map_err(io::Error::other)?
+                    .write(&mut bytes)
+                    .await?;
+                bytes.extend(payload);
+            }
+            Self::Raw(raw) => bytes.extend_from_slice(raw),
+        }
+        Ok(bytes)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MockServerBuilder {
+    status_json: String,
+    behaviour: Behaviour,
+    answer_legacy: bool,
+}
+
+impl Default for MockServerBuilder {
+    fn default() -> Self {
+        Self {
+            status_json: serde_json::json!({
+                "version": { "name": "1.21.4", "protocol": 769 },
+                "players": { "max": 20, "online": 0 },
+                "description": "A Minecraft Server",
+            })
+            .to_string(),
+            behaviour: Behaviour::default(),
+            answer_legacy: true,
+        }
+    }
+}
+
+impl MockServerBuilder {
+    pub fn status(self, status: &Status) -> Self {
+        self.status_json(serde_json::to_string(status).expect("status is always serializable"))
+    }
+
+    /// The json sent in the status response, which doesn't have to be a valid [`Status`].
+    pub fn status_json(self, json: impl Into<String>) -> Self {
+        Self {
+            status_json: json.into(),
+            ..self
+        }
+    }
+
+    pub fn behaviour(self, behaviour: Behaviour) -> Self {
+        Self { behaviour, ..self }
+    }
+
+    /// Whether to answer the legacy (pre 1.7) ping, which vanilla servers do. Defaults to `true`.
+    pub fn answer_legacy(self, answer_legacy: bool) -> Self {
+        Self {
+            answer_legacy,
+            ..self
+        }
+    }
+
+    pub async fn spawn(self) -> io::Result<MockServer> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let handshakes = Arc::new(Mutex::new(Vec::new()));
+        let config = Arc::new(self);
+        let handle = tokio::spawn({
+            let handshakes = handshakes.clone();
+            async move {
+                // owned by the accept loop so aborting it aborts the connections too
+                let mut connections = JoinSet::new();
+                while let Ok((socket, peer)) = listener.accept().await {
+                    while connections.try_join_next().is_some() {}
+                    let config = config.clone();
+                    let handshakes = handshakes.clone();
+                    connections.spawn(async move {
+                        if let Err(error) = config.handle(socket, &handshakes).await {
+                            tracing::debug!(%peer, ?error, "mock server connection failed");
+                        }
+                    });
+                }
+            }
+        });
+        Ok(MockServer {
+            addr,
+            handshakes,
+            handle,
+        })
+    }
+
+    async fn handle(
+        &self,
+        mut socket: TcpStream,
+        handshakes: &Mutex<Vec<Handshake>>,
+    ) -> io::Result<()> {
+        let Some(handshake) = serve::read_handshake(&mut socket).await? else {
+            return if self.answer_legacy {
+                self.answer_legacy_ping(socket).await
+            } else {
+                Ok(())
+            };
+        };
+        handshakes.lock().unwrap().push(handshake);
+
+        let mut connection = Connection::server(socket);
+        connection.set_state(State::Status);
+        match &self.behaviour {
+            Behaviour::Close => return Ok(()),
+            Behaviour::Silent => {
+                std::future::pending::<()>().await;
+            }
+            _ => {}
+        }
+
+        let Decoded::StatusRequest(_) = connection.read_decoded().await? else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "expected a status request",
+            ));
+        };
+        if let Behaviour::Malformed(malformed) = &self.behaviour {
+            connection
+                .get_mut()
+                .write_all(&malformed.bytes(&self.status_json).await?)
+                .await?;
+            return Ok(());
+        }
+        connection
+            .send(&StatusResponse {
+                json: self.status_json.clone().into(),
+            })
+            .await?;
+        if let Behaviour::NoPong = self.behaviour {
+            return Ok(());
+        }
+
+        let Decoded::PingRequest(ping) = connection.read_decoded().await? else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "expected a ping request",
+            ));
+        };
+        let payload = match self.behaviour {
+            Behaviour::WrongPong => ping.payload.wrapping_add(1),
+            _ => ping.payload,
+        };
+        connection.send(&PongResponse { payload }).await
+    }
+
+    async fn answer_legacy_ping(&self, mut socket: TcpStream) -> io::Result<()> {
+        let status = serde_json::from_str::<Status>(&self.status_json)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        socket.write_all(&legacy::encode_response(&status)).await
+    }
+}
+
+/// A running fake server, stopped when dropped.
+#[derive(Debug)]
+pub struct MockServer {
+    addr: SocketAddr,
+    handshakes: Arc<Mutex<Vec<Handshake>>>,
+    handle: JoinHandle<()>,
+}
+
+impl MockServer {
+    pub fn builder() -> MockServerBuilder {
+        MockServerBuilder::default()
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Every modern handshake received so far.
+    pub fn handshakes(&self) -> Vec<Handshake> {
+        self.handshakes.lock().unwrap().clone()
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+/// An in-memory `Write` that can be looked into while something else writes to a clone of it,
+/// e.g. a [`Recorder`](crate::packet::capture::Recorder).
+#[derive(Debug, Clone, Default)]
+pub struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl SharedBuffer {
+    /// Everything written so far.
+    pub fn contents(&self) -> Vec<u8> {
+        self.0.lock().unwrap().clone()
+    }
+}
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,132 @@
+//! Which protocol version every release of Minecraft speaks.
+//!
+//! Only releases since 1.7.2 are listed: before the netty rewrite protocol numbers were counted
+//! from scratch and overlap with the modern ones.
+
+use std::fmt;
+
+/// The releases that share a protocol version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Release {
+    pub protocol: i32,
+    /// From oldest to newest.
+    pub names: &'static [&'static str],
+}
+
+macro_rules! releases {
+    ($($protocol:literal => [$($name:literal),+ $(,)?]),* $(,)?) => {
+        &[$(Release { protocol: $protocol, names: &[$($name),+] }),*]
+    };
+}
+
+/// Every known release, sorted by protocol version.
+pub const RELEASES: &[Release] = releases![
+    4 => ["1.7.2", "1.7.3", "1.7.4", "1.7.5"],
+    5 => ["1.7.6", "1.7.7", "1.7.8", "1.7.9", "1.7.10"],
+    47 => ["1.8", "1.8.1", "1.8.2", "1.8.3", "1.8.4", "1.8.5", "1.8.6", "1.8.7", "1.8.8", "1.8.9"],
+    107 => ["1.9"],
+    108 => ["1.9.1"],
+    109 => ["1.9.2"],
+    110 => ["1.9.3", "1.9.4"],
+    210 => ["1.10", "1.10.1", "1.10.2"],
+    315 => ["1.11"],
+    316 => ["1.11.1", "1.11.2"],
+    335 => ["1.12"],
+    338 => ["1.12.1"],
+    340 => ["1.12.2"],
+    393 => ["1.13"],
+    401 => ["1.13.1"],
+    404 => ["1.13.2"],
+    477 => ["1.14"],
+    480 => ["1.14.1"],
+    485 => ["1.14.2"],
+    490 => ["1.14.3"],
+    498 => ["1.14.4"],
+    573 => ["1.15"],
+    575 => ["1.15.1"],
+    578 => ["1.15.2"],
+    735 => ["1.16"],
+    736 => ["1.16.1"],
+    751 => ["1.16.2"],
+    753 => ["1.16.3"],
+    754 => ["1.16.4", "1.16.5"],
+    755 => ["1.17"],
+    756 => ["1.17.1"],
+    757 => ["1.18", "1.18.1"],
+    758 => ["1.18.2"],
+    759 => ["1.19"],
+    760 => ["1.19.1", "1.19.2"],
+    761 => ["1.19.3"],
+    762 => ["1.19.4"],
+    763 => ["1.20", "1.20.1"],
+    764 => ["1.20.2"],
+    765 => ["1.20.3", "1.20.4"],
+    766 => ["1.20.5", "1.20.6"],
+    767 => ["1.21", "1.21.1"],
+    768 => ["1.21.2", "1.21.3"],
+    769 => ["1.21.4"],
+    770 => ["1.21.5"],
+    771 => ["1.21.6"],
+    772 => ["1.21.7", "1.21.8"],
+];
+
+impl Release {
+    pub fn by_protocol(protocol: i32) -> Option<&'static Self> {
+        RELEASES
+            .binary_search_by_key(&protocol, |r| r.protocol)
+            .ok()
+            .map(|i| &RELEASES[i])
+    }
+
+    /// Finds the release called `name`, e.g. `1.20.4`. A trailing `.0` is ignored.
+    pub fn by_name(name: &str) -> Option<&'static Self> {
+        let name = name.trim();
+        let name = name.strip_suffix(".0").unwrap_or(name);
+        RELEASES.iter().find(|r| r.names.contains(&name))
+    }
+}
+
+/// The range of names, e.g. `1.20.3-1.20.4`.
+impl fmt::Display for Release {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.names {
+            [only] => f.write_str(only),
+            [first, .., last] => write!(f, "{first}-{last}"),
+            [] => unreachable!("releases have names"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::HandshakeOptions;
+
+    #[test]
+    fn sorted_and_unique() {
+        assert!(RELEASES.windows(2).all(|w| w[0].protocol < w[1].protocol));
+        let mut names = RELEASES.iter().flat_map(|r| r.names).collect::<Vec<_>>();
+        let count = names.len();
+        names.sort();
+        names.dedup();
+        assert_eq!(names.len(), count);
+    }
+
+    #[test]
+    fn lookups() {
+        let release = Release::by_name("1.20.4").unwrap();
+        assert_eq!(release.protocol, 765);
+        assert_eq!(release.to_string(), "1.20.3-1.20.4");
+        assert_eq!(Release::by_protocol(765), Some(release));
+        assert_eq!(Release::by_name("1.21.0").unwrap().protocol, 767);
+        assert_eq!(Release::by_protocol(47).unwrap().to_string(), "1.8-1.8.9");
+        assert_eq!(Release::by_protocol(766 + 1000), None);
+        assert_eq!(Release::by_name("1.6.4"), None);
+        assert_eq!(
+            Release::by_protocol(HandshakeOptions::DEFAULT_PROTOCOL_VERSION)
+                .unwrap()
+                .names,
+            ["1.21.4"]
+        );
+    }
+}
@@ -0,0 +1,358 @@
+//! Querying a server over and over, telling what changed between queries.
+//!
+//! ```no_run
+//! # async fn example() {
+//! use mccli::watch::Watcher;
+//! use std::time::Duration;
+//!
+//! let mut events = Watcher::new(Duration::from_secs(30)).watch("mc.example.com".to_owned());
+//! while let Some(event) = events.recv().await {
+//!     println!("{}", event.change);
+//! }
+//! # }
+//! ```
+
+use crate::{Limits, Result, ServerInfo, Timeouts, scan::probe, types::server::Status};
+use serde::Serialize;
+use std::{
+    collections::BTreeSet,
+    fmt,
+    time::{Duration, SystemTime},
+};
+use tokio::{
+    sync::mpsc,
+    time::{self, MissedTickBehavior},
+};
+
+/// Something that changed between two queries.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Change {
+    /// The server answered, for the first time or after it didn't.
+    Online {
+        version: String,
+        motd: String,
+        players: u64,
+        max: u64,
+    },
+    /// The server didn't answer, for the first time or after it did.
+    Offline {
+        error: String,
+        category: &'static str,
+    },
+    PlayerJoined {
+        name: String,
+    },
+    PlayerLeft {
+        name: String,
+    },
+    PlayerCount {
+        from: u64,
+        to: u64,
+        max: u64,
+    },
+    Motd {
+        from: String,
+        to: String,
+    },
+    Version {
+        from: String,
+        to: String,
+    },
+}
+
+impl Change {
+    /// The name of the change, as in the `event` field of its json.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Online { .. } => "online",
+            Self::Offline { .. } => "offline",
+            Self::PlayerJoined { .. } => "player_joined",
+            Self::PlayerLeft { .. } => "player_left",
+            Self::PlayerCount { .. } => "player_count",
+            Self::Motd { .. } => "motd",
+            Self::Version { .. } => "version",
+        }
+    }
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Online {
+                version,
+                motd,
+                players,
+                max,
+            } => write!(f, "online, {version}, {players}/{max} players: {motd}"),
+            Self::Offline { error, .. } => write!(f, "offline: {error}"),
+            Self::PlayerJoined { name } => write!(f, "{name} joined"),
+            Self::PlayerLeft { name } => write!(f, "{name} left"),
+            Self::PlayerCount { from, to, max } => write!(f, "players: {from} -> {to}/{max}"),
+            Self::Motd { from, to } => write!(f, "motd changed from {from:?} to {to:?}"),
+            Self::Version { from, to } => write!(f, "version changed from {from} to {to}"),
+        }
+    }
+}
+
+/// A change, and when it was noticed.
+#[derive(Debug, Clone)]
+pub struct Event {
+    pub time: SystemTime,
+    pub change: Change,
+}
+
+/// What changed from the `previous` query, `None` for the first one, to the `current` one.
+///
+/// Errors are all the same to this, a server that keeps failing differently doesn't change.
+/// Players are told apart by the sample, which servers cap, usually at 12 players, so joins and
+/// leaves are only reported while every player online is in the sample. The count changes
+/// either way.
+pub fn changes(previous: Option<&Result<ServerInfo>>, current: &Result<ServerInfo>) -> Vec<Change> {
+    match (previous.map(|p| p.as_ref()), current) {
+        (Some(Ok(previous)), Ok(current)) => status_changes(&previous.status, &current.status),
+        (None | Some(Err(_)), Ok(current)) => {
+            let status = &current.status;
+            vec![Change::Online {
+                version: version(status),
+                motd: status.description.plain_text(),
+                players: status.players.online,
+                max: status.players.max,
+            }]
+        }
+        (None | Some(Ok(_)), Err(error)) => vec![Change::Offline {
+            error: error.with_sources().to_string(),
+            category: error.category().as_str(),
+        }],
+        (Some(Err(_)), Err(_)) => Vec::new(),
+    }
+}
+
+fn status_changes(previous: &Status, current: &Status) -> Vec<Change> {
+    let mut changes = Vec::new();
+    let (from, to) = (version(previous), version(current));
+    if from != to {
+        changes.push(Change::Version { from, to });
+    }
+    let (from, to) = (
+        previous.description.plain_text(),
+        current.description.plain_text(),
+    );
+    if from != to {
+        changes.push(Change::Motd { from, to });
+    }
+    if let (Some(before), Some(after)) = (sampled_players(previous), sampled_players(current)) {
+        changes.extend(
+            after
+                .difference(&before)
+                .map(|&name| Change::PlayerJoined { name: name.into() }),
+        );
+        changes.extend(
+            before
+                .difference(&after)
+                .map(|&name| Change::PlayerLeft { name: name.into() }),
+        );
+    }
+    if previous.players.online != current.players.online {
+        changes.push(Change::PlayerCount {
+            from: previous.players.online,
+            to: current.players.online,
+            max: current.players.max,
+        });
+    }
+    changes
+}
+
+fn version(status: &Status) -> String {
+    format!("{} ({})", status.version.name, status.version.protocol)
+}
+
+/// The names of the players online, if the sample has all of them.
+fn sampled_players(status: &Status) -> Option<BTreeSet<&str>> {
+    let players = &status.players;
+    (players.sample.len() as u64 == players.online)
+        .then(|| players.sample.iter().map(|p| p.name.as_str()).collect())
+}
+
+#[derive(Debug, Clone)]
+pub struct Watcher {
+    interval: Duration,
+    timeouts: Timeouts,
+    limits: Limits,
+}
+
+impl Watcher {
+    /// Queries every `interval`, or as soon as the previous query finished if it took longer.
+    ///
+    /// # Panics
+    ///
+    /// If `interval` is zero.
+    pub fn new(interval: Duration) -> Self {
+        assert!(!interval.is_zero(), "the watch interval can't be zero");
+        Self {
+            interval,
+            timeouts: Timeouts::default(),
+            limits: Limits::default(),
+        }
+    }
+
+    pub fn timeouts(self, timeouts: Timeouts) -> Self {
+        Self { timeouts, ..self }
+    }
+
+    /// How much the server may send. Defaults to the protocol's maximums.
+    pub fn limits(self, limits: Limits) -> Self {
+        Self { limits, ..self }
+    }
+
+    /// Watches `target`, which is resolved again for every query like [`resolve`](crate::resolve)
+    /// does. The first query always reports whether the server is online.
+    ///
+    /// Must be called from within a tokio runtime. Watching goes on until the receiver is
+    /// dropped, so the receiver only ends if the watching task panicked.
+    pub fn watch(self, target: String) -> mpsc::Receiver<Event> {
+        let (tx, rx) = mpsc::channel(16);
+        tokio::spawn(async move {
+            let mut interval = time::interval(self.interval);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            let mut previous = None;
+            loop {
+                interval.tick().await;
+                let result = self.limits.scope(probe(&target, &self.timeouts)).await;
+                let time = SystemTime::now();
+                for change in changes(previous.as_ref(), &result) {
+                    if tx.send(Event { time, change }).await.is_err() {
+                        return;
+                    }
+                }
+                if tx.is_closed() {
+                    return;
+                }
+                previous = Some(result);
+            }
+        });
+        rx
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        Error,
+        types::{
+            server::{Player, Players, Version},
+            text::TextComponent,
+        },
+    };
+
+    fn info(motd: &str, players: &[&str], online: u64) -> Result<ServerInfo> {
+        let players = Players {
+            sample: players
+                .iter()
+                .map(|&name| Player {
+                    id: crate::offline_uuid(name).to_string(),
+                    name: name.to_owned(),
+                })
+                .collect(),
+            ..Players::new(20, online)
+        };
+        Ok(ServerInfo {
+            status: Status::new(
+                Version::new("Paper 1.21.4", 769),
+                players,
+                TextComponent::text(motd),
+            ),
+            latency: Duration::from_millis(1),
+        })
+    }
+
+    fn offline() -> Result<ServerInfo> {
+        Err(Error::Protocol("expected pong".into()))
+    }
+
+    #[test]
+    fn online_and_offline() {
+        let up = info("hello", &[], 0);
+        assert_eq!(
+            changes(None, &up),
+            [Change::Online {
+                version: "Paper 1.21.4 (769)".to_owned(),
+                motd: "hello".to_owned(),
+                players: 0,
+                max: 20,
+            }]
+        );
+        let down = changes(Some(&up), &offline());
+        assert!(matches!(
+            &down[..],
+            [Change::Offline {
+                category: "invalid_response",
+                ..
+            }]
+        ));
+        assert_eq!(changes(Some(&offline()), &offline()), []);
+        assert_eq!(changes(Some(&offline()), &up)[0].name(), "online");
+    }
+
+    #[test]
+    fn players_motd_and_version() {
+        let before = info("hello", &["alice", "bob"], 2);
+        let mut after = info("maintenance", &["bob", "carol"], 2);
+        if let Ok(info) = &mut after {
+            info.status.version.protocol = 770;
+        }
+        assert_eq!(
+            changes(Some(&before), &after),
+            [
+                Change::Version {
+                    from: "Paper 1.21.4 (769)".to_owned(),
+                    to: "Paper 1.21.4 (770)".to_owned(),
+                },
+                Change::Motd {
+                    from: "hello".to_owned(),
+                    to: "maintenance".to_owned(),
+                },
+                Change::PlayerJoined {
+                    name: "carol".to_owned()
+                },
+                Change::PlayerLeft {
+                    name: "alice".to_owned()
+                },
+            ]
+        );
+
+        // a capped sample only changes the count
+        let capped = info("hello", &["alice", "bob"], 30);
+        assert_eq!(
+            changes(Some(&before), &capped),
+            [Change::PlayerCount {
+                from: 2,
+                to: 30,
+                max: 20
+            }]
+        );
+        assert_eq!(
+            changes(Some(&before), &info("hello", &["bob", "alice"], 2)),
+            []
+        );
+    }
+
+    #[test]
+    #[should_panic = "the watch interval can't be zero"]
+    fn zero_intervals_are_rejected() {
+        Watcher::new(Duration::ZERO);
+    }
+
+    #[test]
+    fn changes_as_json() {
+        let change = Change::PlayerJoined {
+            name: "alice".to_owned(),
+        };
+        assert_eq!(
+            serde_json::to_string(&change).unwrap(),
+            r#"{"event":"player_joined","name":"alice"}"#
+        );
+        assert_eq!(change.to_string(), "alice joined");
+    }
+}
@@ -0,0 +1,67 @@
+use mccli::{
+    HandshakeOptions, fetch_server_info,
+    packet::{
+        capture::{self, Reader, Recorder},
+        connection::Connection,
+        registry::{Decoded, Direction, State},
+    },
+    testing::{MockServer, SharedBuffer},
+};
+use serde_json::json;
+use std::io;
+use tokio::net::TcpListener;
+
+#[tokio::test]
+async fn recorded_queries_replay() {
+    let json = json!({
+        "version": { "name": "Paper 1.21.4", "protocol": 769 },
+        "players": { "max": 100, "online": 2 },
+        "description": "recorded",
+    });
+    let server = MockServer::builder()
+        .status_json(json.to_string())
+        .spawn()
+        .await
+        .unwrap();
+    let capture = SharedBuffer::default();
+    let recorder = Recorder::new(capture.clone()).unwrap();
+    let handshake = HandshakeOptions::new("play.example.com", 25565);
+    recorder
+        .scope(fetch_server_info(server.addr(), &handshake))
+        .await
+        .unwrap();
+
+    let bytes = capture.contents();
+    let records = Reader::new(&bytes[..])
+        .unwrap()
+        .collect::<io::Result<Vec<_>>>()
+        .unwrap();
+    let packets = [
+        (Direction::Serverbound, State::Handshaking, "Handshake"),
+        (Direction::Serverbound, State::Status, "StatusRequest"),
+        (Direction::Clientbound, State::Status, "StatusResponse"),
+        (Direction::Serverbound, State::Status, "PingRequest"),
+        (Direction::Clientbound, State::Status, "PongResponse"),
+    ];
+    assert_eq!(records.len(), packets.len());
+    for (record, (direction, state, name)) in records.iter().zip(packets) {
+        assert_eq!((record.direction, record.state), (direction, state));
+        assert_eq!(record.decode().await.unwrap().name(), name);
+    }
+    let Decoded::Handshake(sent) = records[0].decode().await.unwrap() else {
+        unreachable!();
+    };
+    assert_eq!(&*sent.server_address, "play.example.com");
+
+    // the replay answers like the original server, with the pong echoing the new ping
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let replay = tokio::spawn(async move {
+        let (socket, _) = listener.accept().await.unwrap();
+        capture::replay(&mut Connection::server(socket), &records).await
+    });
+    let info = fetch_server_info(addr, &handshake).await.unwrap();
+    replay.await.unwrap().unwrap();
+    assert_eq!(info.status.version.name, "Paper 1.21.4");
+    assert_eq!(info.status.description.plain_text(), "recorded");
+}
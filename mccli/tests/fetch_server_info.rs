@@ -0,0 +1,190 @@
+use mccli::{
+    Error, HandshakeOptions, Intent,
+    error::Category,
+    fetch_server_info,
+    testing::{Behaviour, Malformed, MockServer},
+};
+use serde_json::json;
+
+fn status_json() -> String {
+    json!({
+        "version": { "name": "Paper 1.21.4", "protocol": 769 },
+        "players": {
+            "max": 100,
+            "online": 2,
+            "sample": [
+                { "name": "alice", "id": "4566e69f-c907-48ee-8d71-d7ba5aa00d20" },
+                { "name": "bob", "id": "7bd5b459-1e6b-4753-8274-1fbd2fe9a4d5" },
+            ],
+        },
+        "description": { "text": "hello", "extra": [{ "text": " world", "bold": true }] },
+        "enforcesSecureChat": true,
+    })
+    .to_string()
+}
+
+#[tokio::test]
+async fn status_and_latency() {
+    let server = MockServer::builder()
+        .status_json(status_json())
+        .spawn()
+        .await
+        .unwrap();
+
+    let handshake = HandshakeOptions {
+        protocol_version: 767,
+        ..HandshakeOptions::new("play.example.com", 25565)
+    };
+    let info = fetch_server_info(server.addr(), &handshake).await.unwrap();
+
+    assert_eq!(info.status.version.name, "Paper 1.21.4");
+    assert_eq!(info.status.players.online, 2);
+    assert_eq!(info.status.players.sample[1].name, "bob");
+    assert!(info.status.enforces_secure_chat);
+    assert_eq!(info.status.description.plain_text(), "hello world");
+    assert_eq!(info.status.description.extra[0].style.bold, Some(true));
+
+    let [sent] = &server.handshakes()[..] else {
+        panic!("expected exactly one handshake");
+    };
+    assert_eq!(&*sent.server_address, "play.example.com");
+    assert_eq!(sent.server_port, 25565);
+    assert_eq!(i32::from(sent.protocol_version), 767);
+    assert_eq!(sent.intent, Intent::Status);
+}
+
+#[tokio::test]
+async fn falls_back_to_legacy_ping() {
+    let server = MockServer::builder()
+        .status_json(status_json())
+        .behaviour(Behaviour::Close)
+        .spawn()
+        .await
+        .unwrap();
+
+    let info = fetch_server_info(server.addr(), &HandshakeOptions::default())
+        .await
+        .unwrap();
+
+    assert_eq!(info.status.version.name, "Paper 1.21.4");
+    assert_eq!(info.status.players.max, 100);
+    assert!(info.status.players.sample.is_empty());
+    assert_eq!(info.status.description.plain_text(), "hello world");
+}
+
+#[tokio::test]
+async fn malformed_responses_are_errors() {
+    for malformed in [
+        Malformed::InvalidJson,
+        Malformed::VarIntTooLong,
+        Malformed::Truncated,
+        Malformed::WrongPacketId,
+        Malformed::HugeString,
+        Malformed::Raw(b"\x03\x00\x01".to_vec()),
+    ] {
+        let server = MockServer::builder()
+            .behaviour(Behaviour::Malformed(malformed.clone()))
+            .answer_legacy(false)
+            .spawn()
+            .await
+            .unwrap();
+
+        let result = fetch_server_info(server.addr(), &HandshakeOptions::default()).await;
+        match result {
+            Ok(_) => panic!("{malformed:?} was accepted: {result:?}"),
+            Err(error) => assert!(
+                error.is_invalid_response(),
+                "{malformed:?} isn't reported as junk: {error:?}"
+            ),
+        }
+    }
+}
+
+#[tokio::test]
+async fn status_response_must_have_its_packet_id() {
+    let server = MockServer::builder()
+        .behaviour(Behaviour::Malformed(Malformed::WrongPacketId))
+        .answer_legacy(false)
+        .spawn()
+        .await
+        .unwrap();
+
+    let error = fetch_server_info(server.addr(), &HandshakeOptions::default())
+        .await
+        .unwrap_err();
+    let error = anyhow::Error::from(error);
+    assert!(
+        format!("{error:#}").contains("expected status response packet"),
+        "{error:#}"
+    );
+}
+
+#[tokio::test]
+async fn closed_ports_are_unreachable() {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    drop(listener);
+
+    let error = fetch_server_info(addr, &HandshakeOptions::default())
+        .await
+        .unwrap_err();
+    assert!(matches!(error, Error::Connect { .. }), "{error:?}");
+    assert_eq!(error.category(), Category::Unreachable);
+}
+
+#[tokio::test]
+async fn pong_must_echo_the_payload() {
+    let server = MockServer::builder()
+        .behaviour(Behaviour::WrongPong)
+        .answer_legacy(false)
+        .spawn()
+        .await
+        .unwrap();
+
+    let error = fetch_server_info(server.addr(), &HandshakeOptions::default())
+        .await
+        .unwrap_err();
+    assert!(error.is_invalid_response(), "{error:?}");
+    let error = anyhow::Error::from(error);
+    assert!(
+        format!("{error:#}").contains("pong payload mismatch"),
+        "{error:#}"
+    );
+}
+
+#[tokio::test]
+async fn missing_pong_is_an_error() {
+    let server = MockServer::builder()
+        .behaviour(Behaviour::NoPong)
+        .answer_legacy(false)
+        .spawn()
+        .await
+        .unwrap();
+
+    assert!(
+        fetch_server_info(server.addr(), &HandshakeOptions::default())
+            .await
+            .is_err()
+    );
+}
+
+#[tokio::test]
+async fn dropping_the_server_closes_its_connections() {
+    let server = MockServer::builder()
+        .behaviour(Behaviour::Silent)
+        .answer_legacy(false)
+        .spawn()
+        .await
+        .unwrap();
+
+    let addr = server.addr();
+    let fetch =
+        tokio::spawn(async move { fetch_server_info(addr, &HandshakeOptions::default()).await });
+    while server.handshakes().is_empty() {
+        tokio::task::yield_now().await;
+    }
+    drop(server);
+
+    let error = fetch.await.unwrap().unwrap_err();
+    assert!(!matches!(error, Error::Timeout { .. }), "{error:?}");
+}
@@ -0,0 +1,79 @@
+use mccli::{
+    Timeouts,
+    scan::Scanner,
+    testing::{Behaviour, MockServer},
+};
+use std::{collections::HashMap, time::Duration};
+use tokio::net::TcpListener;
+
+fn short_timeouts() -> Timeouts {
+    Timeouts {
+        connect: Duration::from_millis(500),
+        handshake: Duration::from_millis(500),
+        read: Duration::from_millis(200),
+    }
+}
+
+#[tokio::test]
+async fn reports_every_target() {
+    let online = MockServer::builder().spawn().await.unwrap();
+    let silent = MockServer::builder()
+        .behaviour(Behaviour::Silent)
+        .spawn()
+        .await
+        .unwrap();
+    let closed = {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        listener.local_addr().unwrap()
+    };
+
+    let targets = [online.addr(), silent.addr(), closed].map(|a| a.to_string());
+    let mut reports = Scanner::new()
+        .concurrency(2)
+        .timeouts(short_timeouts())
+        .scan(targets.clone());
+
+    let mut results = HashMap::new();
+    while let Some(report) = reports.recv().await {
+        results.insert(report.target, report.result);
+    }
+    assert_eq!(results.len(), 3);
+
+    let info = results[&targets[0]].as_ref().unwrap();
+    assert_eq!(info.status.version.name, "1.21.4");
+
+    let error = results[&targets[1]].as_ref().unwrap_err();
+    assert!(
+        matches!(error, mccli::Error::Timeout { phase, .. } if phase.contains("status")),
+        "{error:?}"
+    );
+    // a silent server isn't retried with the legacy ping
+    assert_eq!(silent.handshakes().len(), 1);
+
+    assert!(results[&targets[2]].as_ref().unwrap_err().is_unreachable());
+}
+
+#[tokio::test]
+async fn silent_servers_dont_block_the_rest() {
+    let silent = MockServer::builder()
+        .behaviour(Behaviour::Silent)
+        .spawn()
+        .await
+        .unwrap();
+    let online = MockServer::builder().spawn().await.unwrap();
+
+    let mut reports = Scanner::new()
+        .concurrency(2)
+        .timeouts(Timeouts {
+            read: Duration::from_secs(60),
+            ..short_timeouts()
+        })
+        .scan([silent.addr(), online.addr()].map(|a| a.to_string()));
+
+    let first = tokio::time::timeout(Duration::from_secs(5), reports.recv())
+        .await
+        .expect("the online server should answer first")
+        .unwrap();
+    assert_eq!(first.target, online.addr().to_string());
+    assert!(first.result.is_ok());
+}